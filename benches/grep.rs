@@ -0,0 +1,108 @@
+///
+/// ファイル検索のベンチマーク（cargo bench --bench grep）
+/// 一時ディレクトリに小さなファイルをたくさんと大きなファイル（マップして読む）をいくつか作り，
+/// 1 スレッドと複数スレッド（順番を守る / 守らない）で全てのファイルを探す時間を比べる
+///
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use study_rust::grep::{search_files, Matcher, ParallelOptions, SearchOptions};
+
+// 作るファイル
+const SMALL_FILES: usize = 2000;
+const SMALL_FILE_LINES: usize = 300;
+const LARGE_FILES: usize = 4;
+const LARGE_FILE_LINES: usize = 60_000;
+
+// 探す正規表現（何行かに 1 行だけ一致する）
+const QUERY: &str = r"fn handle_\w*7\b";
+
+// 同じ条件で探す回数（最も速い時間と平均を表示する）
+const RUNS: usize = 5;
+
+fn main() -> io::Result<()> {
+    let dir = env::temp_dir().join(format!("study_rust_grep_bench_{}", std::process::id()));
+    println!("Writing files into {} ...", dir.display());
+    let files = write_files(&dir)?;
+
+    let options = SearchOptions::default();
+    let matcher = Matcher::new(QUERY, &options).expect("QUERY must be a valid regex");
+    let threads = ParallelOptions::default().thread_count();
+    let modes = [
+        ("1 thread", ParallelOptions { threads: 1, ordered: true }),
+        ("ordered", ParallelOptions { threads, ordered: true }),
+        ("unordered", ParallelOptions { threads, ordered: false }),
+    ];
+
+    println!("{} files, query '{}', {} threads\n", files.len(), QUERY, threads);
+    println!("{:<12} {:>10} {:>10} {:>8} {:>8}", "mode", "best", "average", "speedup", "matches");
+    let mut single_best = None;
+    for (label, parallel) in modes {
+        let mut times = Vec::new();
+        let mut matches = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            matches = 0;
+            search_files(&matcher, &files, &options, &parallel, |_, result| {
+                if let Ok(Some(lines)) = result {
+                    matches += lines.len();
+                }
+            });
+            times.push(start.elapsed());
+        }
+        let best = times.iter().min().copied().unwrap_or_default();
+        let average = times.iter().sum::<Duration>() / RUNS as u32;
+        let single = *single_best.get_or_insert(best);
+        println!(
+            "{:<12} {:>8.1}ms {:>8.1}ms {:>7.2}x {:>8}",
+            label,
+            best.as_secs_f64() * 1000.0,
+            average.as_secs_f64() * 1000.0,
+            single.as_secs_f64() / best.as_secs_f64(),
+            matches,
+        );
+    }
+
+    fs::remove_dir_all(&dir)
+}
+
+///
+/// ベンチマーク用のファイルを作る（ディレクトリを 100 ファイルごとに分ける）
+///
+fn write_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for index in 0..SMALL_FILES {
+        let sub_dir = dir.join(format!("module_{:02}", index / 100));
+        fs::create_dir_all(&sub_dir)?;
+        let path = sub_dir.join(format!("file_{:04}.rs", index));
+        write_source(&path, index, SMALL_FILE_LINES)?;
+        files.push(path);
+    }
+    for index in 0..LARGE_FILES {
+        let path = dir.join(format!("large_{}.rs", index));
+        write_source(&path, SMALL_FILES + index, LARGE_FILE_LINES)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+///
+/// それらしいソースコードの行を書き込む
+///
+fn write_source(path: &Path, seed: usize, lines: usize) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+    for line in 0..lines {
+        let n = seed * 31 + line;
+        match line % 6 {
+            0 => writeln!(writer, "fn handle_{}(value: usize) -> usize {{", n % 1000)?,
+            1 => writeln!(writer, "    // update the counter for request {}", n)?,
+            2 => writeln!(writer, "    let total = value * {} + {};", n % 97, n % 13)?,
+            3 => writeln!(writer, "    println!(\"total = {{}}\", total);")?,
+            4 => writeln!(writer, "    total")?,
+            _ => writeln!(writer, "}}")?,
+        }
+    }
+    writer.flush()
+}
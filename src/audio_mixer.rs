@@ -0,0 +1,252 @@
+///
+/// 音声ミキサー（bevy_practice と bevy_timing_game で共通）
+/// 音を鳴らすエンティティに AudioBus を付けると，バスごとの音量・ミュートと，効果音の再生中の音楽のダッキングが掛かる
+///
+use bevy::{
+    audio::{AudioSink, SpatialAudioSink, Volume},
+    prelude::*,
+};
+
+// ダッキングの既定値
+const DEFAULT_DUCKING_LEVEL: f32 = 0.35;
+const DEFAULT_DUCKING_ATTACK: f32 = 0.08;
+const DEFAULT_DUCKING_RELEASE: f32 = 0.6;
+
+///
+/// 音声ミキサー
+/// * ducking : Option<Ducking>     効果音の再生中に音楽を下げる設定（None ならダッキングしない）
+///
+pub struct AudioMixerPlugin {
+    pub ducking: Option<Ducking>,
+}
+
+impl Default for AudioMixerPlugin {
+    fn default() -> Self {
+        AudioMixerPlugin {
+            ducking: Some(Ducking::default()),
+        }
+    }
+}
+
+impl Plugin for AudioMixerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioMixer {
+            buses: [BusSettings::default(); AudioBus::ALL.len()],
+            ducking: self.ducking,
+        })
+        .init_resource::<DuckingGain>()
+        .add_observer(apply_initial_volume)
+        .add_systems(Last, (update_ducking, apply_bus_volumes).chain().in_set(MixerSystems));
+    }
+}
+
+///
+/// ミキサーがシンクの音量を設定するシステム
+/// SoundGain を変えるシステムはこれより前に実行する
+///
+#[derive(SystemSet, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MixerSystems;
+
+///
+/// 音を流すバス
+///
+#[derive(Component, Clone, Copy, Eq, PartialEq, Debug)]
+#[require(SoundGain)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Ambience,
+    Ui,
+}
+
+impl AudioBus {
+    pub const ALL: [AudioBus; 4] = [AudioBus::Music, AudioBus::Sfx, AudioBus::Ambience, AudioBus::Ui];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioBus::Music => "Music",
+            AudioBus::Sfx => "SFX",
+            AudioBus::Ambience => "Ambience",
+            AudioBus::Ui => "UI",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+///
+/// 音ごとの音量の倍率（バスの音量を掛ける前）
+/// 距離による減衰など，音ごとに音量を変える場合はシンクではなくこちらを変える
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SoundGain(pub f32);
+
+impl Default for SoundGain {
+    fn default() -> Self {
+        SoundGain(1.0)
+    }
+}
+
+///
+/// バスの設定
+/// * volume : f32      音量（0.0 〜 1.0）
+/// * muted : bool      ミュート
+///
+#[derive(Clone, Copy, Debug)]
+pub struct BusSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        BusSettings {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+///
+/// ダッキングの設定
+/// * level : f32       ダッキング中の音楽の音量の倍率
+/// * attack : f32      音楽を下げるのにかける時間（秒）
+/// * release : f32     効果音が終わってから音楽を戻すのにかける時間（秒）
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Ducking {
+    pub level: f32,
+    pub attack: f32,
+    pub release: f32,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Ducking {
+            level: DEFAULT_DUCKING_LEVEL,
+            attack: DEFAULT_DUCKING_ATTACK,
+            release: DEFAULT_DUCKING_RELEASE,
+        }
+    }
+}
+
+///
+/// バスごとの設定とダッキングの設定
+///
+#[derive(Resource)]
+pub struct AudioMixer {
+    buses: [BusSettings; AudioBus::ALL.len()],
+    pub ducking: Option<Ducking>,
+}
+
+impl AudioMixer {
+    pub fn bus(&self, bus: AudioBus) -> &BusSettings {
+        &self.buses[bus.index()]
+    }
+
+    pub fn bus_mut(&mut self, bus: AudioBus) -> &mut BusSettings {
+        &mut self.buses[bus.index()]
+    }
+
+    ///
+    /// 全てのバスを音量 1.0，ミュートなしに戻す
+    ///
+    pub fn reset_buses(&mut self) {
+        self.buses = [BusSettings::default(); AudioBus::ALL.len()];
+    }
+
+    ///
+    /// ミュートとダッキングを含めたバスの音量の倍率
+    ///
+    pub fn gain(&self, bus: AudioBus, ducking_gain: &DuckingGain) -> f32 {
+        let settings = self.bus(bus);
+        if settings.muted {
+            return 0.0;
+        }
+        match bus {
+            AudioBus::Music => settings.volume * ducking_gain.0,
+            _ => settings.volume,
+        }
+    }
+}
+
+///
+/// ダッキングによる今の音楽の音量の倍率（1.0 でダッキングなし）
+///
+#[derive(Resource)]
+pub struct DuckingGain(f32);
+
+impl Default for DuckingGain {
+    fn default() -> Self {
+        DuckingGain(1.0)
+    }
+}
+
+///
+/// 鳴らし始めの音量
+/// シンクは PlaybackSettings の音量で作られるので，AudioBus を付けた時点でバスの音量を反映しておく
+///
+fn apply_initial_volume(
+    trigger: Trigger<OnAdd, AudioBus>,
+    mut query: Query<(&AudioBus, &SoundGain, &mut PlaybackSettings)>,
+    mixer: Res<AudioMixer>,
+    ducking_gain: Res<DuckingGain>,
+) {
+    if let Ok((bus, sound_gain, mut settings)) = query.get_mut(trigger.entity()) {
+        settings.volume = Volume::new(sound_gain.0 * mixer.gain(*bus, &ducking_gain));
+    }
+}
+
+///
+/// 効果音が鳴っている間は音楽を下げ，鳴り終わったら戻す
+///
+fn update_ducking(
+    time: Res<Time>,
+    mixer: Res<AudioMixer>,
+    mut ducking_gain: ResMut<DuckingGain>,
+    sfx_query: Query<(&AudioBus, Option<&AudioSink>, Option<&SpatialAudioSink>)>,
+) {
+    let Some(ducking) = mixer.ducking else {
+        ducking_gain.0 = 1.0;
+        return;
+    };
+    let sfx_playing = !mixer.bus(AudioBus::Sfx).muted
+        && sfx_query.iter().any(|(bus, sink, spatial_sink)| {
+            *bus == AudioBus::Sfx
+                && (sink.is_some_and(|sink| !sink.is_paused() && !sink.empty())
+                    || spatial_sink.is_some_and(|sink| !sink.is_paused() && !sink.empty()))
+        });
+    let (target, seconds) = if sfx_playing {
+        (ducking.level, ducking.attack)
+    } else {
+        (1.0, ducking.release)
+    };
+    // seconds かけて 1.0 と level の間を動く速さで近づける
+    let step = (1.0 - ducking.level).abs() * time.delta_secs() / seconds.max(f32::EPSILON);
+    ducking_gain.0 = if ducking_gain.0 < target {
+        (ducking_gain.0 + step).min(target)
+    } else {
+        (ducking_gain.0 - step).max(target)
+    };
+}
+
+///
+/// 再生中の音のシンクに，音ごとの音量とバスの音量を掛けた音量を設定する
+///
+fn apply_bus_volumes(
+    mixer: Res<AudioMixer>,
+    ducking_gain: Res<DuckingGain>,
+    sink_query: Query<(&AudioBus, &SoundGain, Option<&AudioSink>, Option<&SpatialAudioSink>)>,
+) {
+    for (bus, sound_gain, sink, spatial_sink) in &sink_query {
+        let volume = sound_gain.0 * mixer.gain(*bus, &ducking_gain);
+        if let Some(sink) = sink {
+            sink.set_volume(volume);
+        }
+        if let Some(sink) = spatial_sink {
+            sink.set_volume(volume);
+        }
+    }
+}
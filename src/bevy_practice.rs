@@ -1,12 +1,26 @@
 ///
-/// bevy sample (Audio / Spatial Audio 2D)
+/// bevy sample (Audio / Spatial Audio 2D / 3D)
 ///
+mod attenuation;
+mod emitter;
+mod mixer_panel;
+mod propagation;
+mod recording;
+mod scene3d;
+mod visualize;
+
+use crate::audio_mixer::{AudioMixer, AudioMixerPlugin, Ducking};
+use crate::launcher::Demo;
 use bevy::{
-    audio::{AudioPlugin, SpatialScale},
+    audio::{AudioPlugin, DefaultSpatialScale, SpatialScale},
     color::palettes::css::*,
     prelude::*,
-    time::Stopwatch,
+    window::PrimaryWindow,
 };
+use attenuation::Attenuation;
+use emitter::{Emitter, EmitterAssets, NewEmitterSettings, Selected};
+use propagation::Motion;
+use recording::{EmitterDrag, StatusMessage};
 
 /// Spatial audio uses the distance to attenuate the sound volume. In 2D with the default camera,
 /// 1 pixel is 1 unit of distance, so we use a scale so that 100 pixels is 1 unit of distance for
@@ -14,37 +28,116 @@ use bevy::{
 #[allow(unused)]
 const AUDIO_SCALE: f32 = 1. / 100.0;
 
-#[allow(unused)]
+// リスナーをつかめる範囲（中心と両耳からの距離）
+const LISTENER_GRAB_RADIUS: f32 = 20.0;
+
 pub fn run_bevy_sample() {
     App::new()
         .add_plugins(DefaultPlugins.set(AudioPlugin {
             default_spatial_scale: SpatialScale::new_2d(AUDIO_SCALE),
             ..default()
         }))
-        .add_systems(Startup, setup)
-        .add_systems(Update, update_emitters)
-        .add_systems(Update, update_listener)
+        .insert_state(Demo::SpatialAudio)
+        .enable_state_scoped_entities::<Demo>()
+        .add_plugins((AudioMixerPlugin::default(), SpatialAudioPlugin))
         .run();
 }
 
+///
+/// 空間音響のデモ（Demo::SpatialAudio の間だけ動く）
+/// AudioMixerPlugin と Demo の状態はアプリ側で追加しておく
+///
+pub struct SpatialAudioPlugin;
+
+impl Plugin for SpatialAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<SceneMode>()
+            .enable_state_scoped_entities::<SceneMode>()
+            .add_plugins((
+                visualize::SpatialVisualizerPlugin,
+                attenuation::AttenuationPlugin,
+                propagation::PropagationPlugin,
+                scene3d::Scene3dPlugin,
+                recording::RecordingPlugin,
+                mixer_panel::MixerPanelPlugin,
+            ))
+            .init_resource::<CursorPosition>()
+            .init_resource::<ListenerDrag>()
+            .init_resource::<NewEmitterSettings>()
+            .init_resource::<EmitterAssets>()
+            .add_systems(OnEnter(Demo::SpatialAudio), enable_ducking)
+            .add_systems(OnEnter(SceneMode::TwoD), setup)
+            .add_systems(OnExit(SceneMode::TwoD), clear_cursor_position)
+            .add_systems(
+                Update,
+                (
+                    update_cursor_position,
+                    handle_mouse,
+                    emitter::edit_selected_emitter,
+                    emitter::show_selection,
+                    update_info_text,
+                    update_listener,
+                )
+                    .chain()
+                    .run_if(in_state(SceneMode::TwoD)),
+            )
+            .add_systems(
+                Update,
+                (emitter::update_emitters.after(update_cursor_position), switch_scene_mode)
+                    .run_if(in_state(Demo::SpatialAudio)),
+            );
+    }
+}
+
+///
+/// 2D と 3D のどちらのシーンか（デモを始めると 2D から）
+///
+#[derive(SubStates, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+#[source(Demo = Demo::SpatialAudio)]
+enum SceneMode {
+    #[default]
+    TwoD,
+    ThreeD,
+}
+
+///
+/// ワールド座標でのマウスカーソルの位置（ウィンドウの外なら None）
+///
+#[derive(Resource, Default)]
+struct CursorPosition(Option<Vec2>);
+
+///
+/// リスナーをドラッグ中なら，つかんだ位置とリスナーの中心とのずれ
+///
+#[derive(Resource, Default)]
+struct ListenerDrag(Option<Vec2>);
+
+///
+/// 選択中の音源の情報の表示
+///
+#[derive(Component)]
+struct InfoText;
+
 fn setup(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut default_spatial_scale: ResMut<DefaultSpatialScale>,
+    emitter_assets: Res<EmitterAssets>,
     asset_server: Res<AssetServer>,
 ) {
+    default_spatial_scale.0 = SpatialScale::new_2d(AUDIO_SCALE);
+
     // Space between the two ears
     let gap = 400.0;
 
     // sound emitter
-    commands.spawn((
-        Mesh2d(meshes.add(Circle::new(16.0))),
-        MeshMaterial2d(materials.add(Color::from(BLUE))),
-        Transform::from_translation(Vec3::new(0.0, 50.0, 0.0)),
-        Emitter::default(),
-        AudioPlayer::new(asset_server.load("sounds/Windless Slopes.ogg")),
-        PlaybackSettings::LOOP.with_spatial(true),
-    ));
+    emitter::spawn_emitter(
+        &mut commands,
+        &emitter_assets,
+        &asset_server,
+        Vec2::new(0.0, 50.0),
+        default(),
+        0,
+    );
 
     let listener = SpatialListener::new(gap);
     commands
@@ -52,6 +145,8 @@ fn setup(
             Transform::default(),
             Visibility::default(),
             listener.clone(),
+            Motion::default(),
+            StateScoped(SceneMode::TwoD),
         ))
         .with_children(|parent| {
             // left ear
@@ -69,44 +164,189 @@ fn setup(
 
     // example instructions
     commands.spawn((
-        Text::new("Up/Down/Left/Right: Move Listener\nSpace: Toggle Emitter Movement"),
+        Text::new(
+            "Up/Down/Left/Right or Drag: Move Listener\n\
+             Left Click: Add / Select Emitter\n\
+             Right Click or Delete: Remove Emitter\n\
+             S: Change Sound  P: Change Path\n\
+             A: Change Attenuation  C: Toggle Cone  R: Rotate Emitter\n\
+             Shift + Drag: Draw Wall  Shift + Right Click: Remove Wall\n\
+             [ / ]: Change Spatial Scale\n\
+             Drag Emitter: Record Path  K: Save Path  L: Load Path Files\n\
+             Space: Toggle Emitter Movement\n\
+             1-4: Select Bus  - / =: Bus Volume  M: Mute Bus  N: Toggle Ducking\n\
+             Tab: Switch to 3D",
+        ),
         Node {
             position_type: PositionType::Absolute,
             bottom: Val::Px(12.0),
             left: Val::Px(12.0),
             ..default()
         },
+        StateScoped(SceneMode::TwoD),
+    ));
+
+    // selected emitter
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        InfoText,
+        StateScoped(SceneMode::TwoD),
     ));
 
     // camera
-    commands.spawn(Camera2d);
+    commands.spawn((Camera2d, StateScoped(SceneMode::TwoD)));
 }
 
-#[derive(Component, Default)]
-struct Emitter {
-    stopwatch: Stopwatch,
+///
+/// 効果音の再生中に音楽を下げる（ミキサーは他のデモと共通なので，始めるたびに設定する）
+///
+fn enable_ducking(mut mixer: ResMut<AudioMixer>) {
+    mixer.ducking = Some(Ducking::default());
 }
 
-fn update_emitters(
-    time: Res<Time>,
-    mut emitters: Query<(&mut Transform, &mut Emitter), With<Emitter>>,
+///
+/// Tab で 2D と 3D のシーンを切り替える
+///
+fn switch_scene_mode(
     keyboard: Res<ButtonInput<KeyCode>>,
+    scene_mode: Res<State<SceneMode>>,
+    mut next_scene_mode: ResMut<NextState<SceneMode>>,
 ) {
-    for (mut emitter_transform, mut emitter) in emitters.iter_mut() {
-        if keyboard.just_pressed(KeyCode::Space) {
-            if emitter.stopwatch.is_paused() {
-                emitter.stopwatch.unpause();
-            } else {
-                emitter.stopwatch.pause();
-            }
-        }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        next_scene_mode.set(match **scene_mode {
+            SceneMode::TwoD => SceneMode::ThreeD,
+            SceneMode::ThreeD => SceneMode::TwoD,
+        });
+    }
+}
 
-        emitter.stopwatch.tick(time.delta());
+///
+/// 2D のシーンを抜けたらカーソルの位置を使わないようにする
+///
+fn clear_cursor_position(mut cursor_position: ResMut<CursorPosition>, mut listener_drag: ResMut<ListenerDrag>) {
+    cursor_position.0 = None;
+    listener_drag.0 = None;
+}
 
-        if !emitter.stopwatch.is_paused() {
-            emitter_transform.translation.x = ops::sin(emitter.stopwatch.elapsed_secs()) * 500.0;
+///
+/// カーソルの位置をワールド座標に変換しておく
+///
+fn update_cursor_position(
+    mut cursor_position: ResMut<CursorPosition>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    let (camera, camera_transform) = *camera;
+    cursor_position.0 = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok());
+}
+
+///
+/// マウスでの操作
+/// * 左クリック : 音源を選択してつかむ，リスナーをつかむ，何もない所なら音源を追加する
+/// * 右クリック : 音源を削除する
+///
+#[allow(clippy::too_many_arguments)]
+fn handle_mouse(
+    mut commands: Commands,
+    mut listener_drag: ResMut<ListenerDrag>,
+    mut emitter_drag: ResMut<EmitterDrag>,
+    mut listener_query: Single<(&mut Transform, &SpatialListener), Without<Emitter>>,
+    emitter_query: Query<(Entity, &Transform), With<Emitter>>,
+    selected_query: Query<Entity, With<Selected>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+    new_emitter_settings: Res<NewEmitterSettings>,
+    emitter_assets: Res<EmitterAssets>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        listener_drag.0 = None;
+    }
+    let Some(cursor) = cursor_position.0 else {
+        return;
+    };
+    let (listener_transform, listener) = &mut *listener_query;
+
+    // ドラッグ中はリスナーをカーソルに合わせて動かす
+    if let Some(grab_offset) = listener_drag.0 {
+        let position = cursor - grab_offset;
+        listener_transform.translation.x = position.x;
+        listener_transform.translation.y = position.y;
+        return;
+    }
+
+    // Shift を押している間は壁の操作（propagation）
+    if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    let emitters = || emitter_query.iter().map(|(entity, transform)| (entity, transform.translation.truncate()));
+    if mouse.just_pressed(MouseButton::Right) {
+        if let Some(entity) = emitter::emitter_at(cursor, emitters()) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // 音源の上なら選択してつかみ（動きを記録する），リスナーの上ならつかみ，どちらでもなければ音源を追加する
+    if let Some(entity) = emitter::emitter_at(cursor, emitters()) {
+        emitter::select_emitter(&mut commands, entity, selected_query.iter());
+        if let Ok((_, transform)) = emitter_query.get(entity) {
+            let grab_offset = cursor - transform.translation.truncate();
+            emitter_drag.begin(&mut commands, entity, grab_offset, time.elapsed_secs());
         }
+        return;
+    }
+    let center = listener_transform.translation;
+    let grabbed = [Vec3::ZERO, listener.left_ear_offset, listener.right_ear_offset]
+        .iter()
+        .any(|offset| (center + listener_transform.rotation * *offset).truncate().distance(cursor) <= LISTENER_GRAB_RADIUS);
+    if grabbed {
+        listener_drag.0 = Some(cursor - center.truncate());
+        return;
     }
+    let entity = emitter::spawn_emitter(
+        &mut commands,
+        &emitter_assets,
+        &asset_server,
+        cursor,
+        new_emitter_settings.path,
+        new_emitter_settings.sound,
+    );
+    emitter::select_emitter(&mut commands, entity, selected_query.iter());
+}
+
+///
+/// 音源の数と選択中の音源の情報を表示する
+///
+fn update_info_text(
+    mut info_text: Single<&mut Text, With<InfoText>>,
+    emitter_query: Query<(&Emitter, Option<&Attenuation>, Has<Selected>)>,
+    status_message: Res<StatusMessage>,
+) {
+    let selected = emitter_query
+        .iter()
+        .find(|(_, _, selected)| *selected)
+        .map_or("Selected: -".to_string(), |(emitter, attenuation, _)| {
+            let attenuation = attenuation.map_or("Bevy".to_string(), |attenuation| {
+                let cone = if attenuation.cone.is_some() { " + Cone" } else { "" };
+                format!("{}{}", attenuation.model.label(), cone)
+            });
+            format!("Selected: {} / {} / {}", emitter.sound_name(), emitter.path.label(), attenuation)
+        });
+    info_text.0 = format!("Emitters: {}\n{}\n{}", emitter_query.iter().len(), selected, status_message.0);
 }
 
 fn update_listener(
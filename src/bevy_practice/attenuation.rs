@@ -0,0 +1,357 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use bevy::{
+    audio::{DefaultSpatialScale, SpatialAudioSink},
+    color::palettes::css::*,
+    prelude::*,
+};
+use crate::audio_mixer::{MixerSystems, SoundGain};
+use crate::launcher::Demo;
+use super::SceneMode;
+use super::emitter::Selected;
+use super::propagation::Occlusion;
+use super::visualize::{ear_gains, ear_positions};
+
+// 左右の聞こえ方だけを Bevy（rodio）に任せるための仮想的な頭の半径
+// 耳も音源もこの距離に置くので，rodio の距離による減衰（1 単位以内は 1 倍）は掛からない
+const VIRTUAL_HEAD_RADIUS: f32 = 0.5;
+
+// 既定の減衰の設定（距離は音の単位）
+const DEFAULT_MIN_DISTANCE: f32 = 1.0;
+const DEFAULT_MAX_DISTANCE: f32 = 6.0;
+const DEFAULT_ROLLOFF: f32 = 1.0;
+const DEFAULT_CONE: Cone = Cone {
+    inner_angle: FRAC_PI_2,
+    outer_angle: PI,
+    outer_gain: 0.1,
+};
+
+// R キーで音源を回す角度
+const ROTATE_STEP: f32 = FRAC_PI_4;
+
+// 選択中の音源の減衰の範囲の表示
+const MIN_DISTANCE_COLOR: Srgba = DARK_ORANGE;
+const MAX_DISTANCE_COLOR: Srgba = MAROON;
+const CONE_COLOR: Srgba = PURPLE;
+const CONE_LENGTH: f32 = 120.0;
+
+///
+/// 距離による減衰の式
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum AttenuationModel {
+    // 最小距離から最大距離まで直線的に小さくする
+    Linear,
+    // 距離に反比例して小さくする
+    Inverse,
+    // 距離の累乗に反比例して小さくする
+    Exponential,
+}
+
+impl AttenuationModel {
+    pub fn label(self) -> &'static str {
+        match self {
+            AttenuationModel::Linear => "Linear",
+            AttenuationModel::Inverse => "Inverse",
+            AttenuationModel::Exponential => "Exponential",
+        }
+    }
+}
+
+///
+/// 音の向き（音源の X 軸の向きに強く聞こえる）
+/// * inner_angle : f32     減衰しない範囲の角度（ラジアン，全幅）
+/// * outer_angle : f32     この角度より外は outer_gain 倍になる（ラジアン，全幅）
+/// * outer_gain : f32      外側の音量の倍率
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Cone {
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub outer_gain: f32,
+}
+
+impl Cone {
+    ///
+    /// 音源の向きとリスナーへの向きのなす角から音量の倍率を求める
+    ///
+    fn gain(&self, angle: f32) -> f32 {
+        let inner = self.inner_angle / 2.0;
+        let outer = self.outer_angle / 2.0;
+        if angle <= inner {
+            1.0
+        } else if angle >= outer {
+            self.outer_gain
+        } else {
+            1.0 + (self.outer_gain - 1.0) * (angle - inner) / (outer - inner)
+        }
+    }
+}
+
+///
+/// 独自の距離による減衰
+/// この成分を付けた音源は，Bevy の距離による減衰の代わりにこの設定で音量を決める（左右の聞こえ方は Bevy のまま）
+/// * model : AttenuationModel      減衰の式
+/// * min_distance : f32            これより近いと減衰しない距離（音の単位）
+/// * max_distance : f32            これより遠くても減衰が進まない距離（音の単位）
+/// * rolloff : f32                 減衰の強さ
+/// * cone : Option<Cone>           音の向き（None なら全方向に同じ）
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Attenuation {
+    pub model: AttenuationModel,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rolloff: f32,
+    pub cone: Option<Cone>,
+}
+
+impl Attenuation {
+    pub fn new(model: AttenuationModel) -> Self {
+        Attenuation {
+            model,
+            min_distance: DEFAULT_MIN_DISTANCE,
+            max_distance: DEFAULT_MAX_DISTANCE,
+            rolloff: DEFAULT_ROLLOFF,
+            cone: None,
+        }
+    }
+
+    ///
+    /// 距離による音量の倍率（OpenAL の減衰の式と同じ）
+    ///
+    fn distance_gain(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(f32::EPSILON);
+        let max = self.max_distance.max(min);
+        let distance = distance.clamp(min, max);
+        match self.model {
+            AttenuationModel::Linear => {
+                if max == min {
+                    1.0
+                } else {
+                    (1.0 - self.rolloff * (distance - min) / (max - min)).clamp(0.0, 1.0)
+                }
+            }
+            AttenuationModel::Inverse => min / (min + self.rolloff * (distance - min)),
+            AttenuationModel::Exponential => (distance / min).powf(-self.rolloff),
+        }
+    }
+
+    ///
+    /// 音源とリスナーの位置と向きから音量の倍率を求める
+    /// * to_listener : Vec3    音源からリスナーへのベクトル（音の単位）
+    /// * facing : Vec3         音源の向き
+    ///
+    fn gain(&self, to_listener: Vec3, facing: Vec3) -> f32 {
+        let cone_gain = self.cone.map_or(1.0, |cone| {
+            if to_listener == Vec3::ZERO {
+                1.0
+            } else {
+                cone.gain(facing.angle_between(to_listener))
+            }
+        });
+        self.distance_gain(to_listener.length()) * cone_gain
+    }
+}
+
+///
+/// 音源が SpatialAudioSink に渡す位置
+/// * emitter : Vec3            音源の位置
+/// * ears : [Vec3; 2]          左右の耳の位置
+/// * gain : f32                音量の倍率
+///
+pub struct SinkPositions {
+    pub emitter: Vec3,
+    pub ears: [Vec3; 2],
+    pub gain: f32,
+}
+
+///
+/// 独自の減衰を使う音源の SpatialAudioSink に渡す位置と音量を求める
+/// 耳と音源はリスナーを中心とした仮想的な小さい頭の上に置き，左右の向きだけを rodio に渡す
+///
+pub fn sink_positions(
+    attenuation: &Attenuation,
+    emitter_transform: &GlobalTransform,
+    listener_transform: &GlobalTransform,
+    listener: &SpatialListener,
+    scale: Vec3,
+) -> SinkPositions {
+    let to_listener = (listener_transform.translation() - emitter_transform.translation()) * scale;
+    let facing = emitter_transform.rotation() * Vec3::X;
+    let local = listener_transform.affine().inverse().transform_point3(emitter_transform.translation());
+    SinkPositions {
+        emitter: local.normalize_or_zero() * VIRTUAL_HEAD_RADIUS,
+        ears: [
+            listener.left_ear_offset.normalize_or_zero() * VIRTUAL_HEAD_RADIUS,
+            listener.right_ear_offset.normalize_or_zero() * VIRTUAL_HEAD_RADIUS,
+        ],
+        gain: attenuation.gain(to_listener, facing),
+    }
+}
+
+///
+/// 両耳それぞれに届く音量の倍率（独自の減衰があればそれを使う）
+///
+pub fn emitter_ear_gains(
+    attenuation: Option<&Attenuation>,
+    emitter_transform: &GlobalTransform,
+    listener_transform: &GlobalTransform,
+    listener: &SpatialListener,
+    scale: Vec3,
+) -> [f32; 2] {
+    match attenuation {
+        Some(attenuation) => {
+            let positions = sink_positions(attenuation, emitter_transform, listener_transform, listener, scale);
+            ear_gains(positions.emitter, positions.ears[0], positions.ears[1]).map(|gain| gain * positions.gain)
+        }
+        None => {
+            let ears = ear_positions(listener_transform, listener);
+            ear_gains(emitter_transform.translation() * scale, ears[0] * scale, ears[1] * scale)
+        }
+    }
+}
+
+///
+/// 独自の減衰
+/// Bevy が SpatialAudioSink の位置を更新した後で，Attenuation を持つ音源の位置と音量を上書きする
+///
+pub struct AttenuationPlugin;
+
+impl Plugin for AttenuationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (edit_attenuation, draw_attenuation).chain().run_if(in_state(SceneMode::TwoD)),
+        )
+            // Bevy は PostUpdate で位置を更新するので，その後の Last で上書きする
+            // 音量は SoundGain に書き，ミキサーがバスの音量を掛けてシンクに設定する
+            .add_systems(
+                Last,
+                (restore_spatial_audio, apply_attenuation)
+                    .chain()
+                    .before(MixerSystems)
+                    .run_if(in_state(Demo::SpatialAudio)),
+            );
+    }
+}
+
+///
+/// 毎フレーム，Attenuation を持つ音源の SpatialAudioSink の位置を設定する
+/// 音ごとの音量（SoundGain）は全ての音源について，独自の減衰と壁による減衰を掛け合わせて設定する
+///
+#[allow(clippy::type_complexity)]
+fn apply_attenuation(
+    listener: Single<(&GlobalTransform, &SpatialListener)>,
+    mut emitter_query: Query<(&GlobalTransform, Option<&SpatialAudioSink>, Option<&Attenuation>, &Occlusion, &mut SoundGain)>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let (listener_transform, listener) = *listener;
+    for (emitter_transform, sink, attenuation, occlusion, mut sound_gain) in &mut emitter_query {
+        let Some(attenuation) = attenuation else {
+            sound_gain.0 = occlusion.0;
+            continue;
+        };
+        let positions = sink_positions(
+            attenuation,
+            emitter_transform,
+            listener_transform,
+            listener,
+            default_spatial_scale.0 .0,
+        );
+        sound_gain.0 = positions.gain * occlusion.0;
+        if let Some(sink) = sink {
+            sink.set_ears_position(positions.ears[0], positions.ears[1]);
+            sink.set_emitter_position(positions.emitter);
+        }
+    }
+}
+
+///
+/// Attenuation を外した音源を Bevy の減衰に戻す
+///
+fn restore_spatial_audio(
+    mut removed: RemovedComponents<Attenuation>,
+    listener: Single<(&GlobalTransform, &SpatialListener)>,
+    emitter_query: Query<(&GlobalTransform, &SpatialAudioSink)>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let scale = default_spatial_scale.0 .0;
+    let ears = ear_positions(listener.0, listener.1);
+    for entity in removed.read() {
+        let Ok((emitter_transform, sink)) = emitter_query.get(entity) else {
+            continue;
+        };
+        sink.set_ears_position(ears[0] * scale, ears[1] * scale);
+        sink.set_emitter_position(emitter_transform.translation() * scale);
+    }
+}
+
+///
+/// 選択中の音源の減衰を変える
+/// * A : 減衰の式を切り替える（Bevy の減衰 → Linear → Inverse → Exponential）
+/// * C : 音の向きの有無を切り替える
+/// * R : 音源を回す
+///
+fn edit_attenuation(
+    mut commands: Commands,
+    mut selected_query: Query<(Entity, &mut Transform, Option<&mut Attenuation>), With<Selected>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    let Ok((entity, mut transform, attenuation)) = selected_query.get_single_mut() else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        transform.rotate_z(ROTATE_STEP);
+    }
+    match attenuation {
+        None => {
+            if keyboard.just_pressed(KeyCode::KeyA) {
+                commands.entity(entity).insert(Attenuation::new(AttenuationModel::Linear));
+            }
+        }
+        Some(mut attenuation) => {
+            if keyboard.just_pressed(KeyCode::KeyA) {
+                match attenuation.model {
+                    AttenuationModel::Linear => attenuation.model = AttenuationModel::Inverse,
+                    AttenuationModel::Inverse => attenuation.model = AttenuationModel::Exponential,
+                    AttenuationModel::Exponential => {
+                        commands.entity(entity).remove::<Attenuation>();
+                    }
+                }
+            }
+            if keyboard.just_pressed(KeyCode::KeyC) {
+                attenuation.cone = match attenuation.cone {
+                    Some(_) => None,
+                    None => Some(DEFAULT_CONE),
+                };
+            }
+        }
+    }
+}
+
+///
+/// 選択中の音源の最小・最大距離の輪と，音の向きの範囲を描く
+///
+fn draw_attenuation(
+    mut gizmos: Gizmos,
+    selected_query: Query<(&GlobalTransform, &Attenuation), With<Selected>>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let scale = default_spatial_scale.0 .0.x;
+    for (transform, attenuation) in &selected_query {
+        let center = transform.translation().truncate();
+        gizmos.circle_2d(center, attenuation.min_distance / scale, MIN_DISTANCE_COLOR);
+        gizmos.circle_2d(center, attenuation.max_distance / scale, MAX_DISTANCE_COLOR);
+        let Some(cone) = attenuation.cone else {
+            continue;
+        };
+        let facing = (transform.rotation() * Vec3::X).truncate();
+        for half_angle in [cone.inner_angle / 2.0, cone.outer_angle / 2.0] {
+            for side in [-1.0, 1.0] {
+                let edge = Vec2::from_angle(side * half_angle).rotate(facing);
+                gizmos.line_2d(center, center + edge * CONE_LENGTH, CONE_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use bevy::{
+    audio::SpatialAudioSink,
+    color::palettes::css::*,
+    prelude::*,
+    time::Stopwatch,
+};
+use crate::audio_mixer::AudioBus;
+use super::{CursorPosition, SceneMode, AUDIO_SCALE};
+use super::propagation::{Motion, Occlusion};
+use super::recording::{Dragged, KeyframePath};
+
+// 選べる音源（assets からの相対パス）
+const SOUND_FILES: &[&str] = &["sounds/Windless Slopes.ogg", "sounds/timing.ogg"];
+// 音源ごとの色
+const SOUND_COLORS: &[Srgba] = &[BLUE, ORANGE];
+// 音源ごとのミキサーのバス（曲は Music，打鍵音は SFX）
+const SOUND_BUSES: &[AudioBus] = &[AudioBus::Music, AudioBus::Sfx];
+
+// 音源の見た目
+const EMITTER_RADIUS: f32 = 16.0;
+const SELECTION_RING_WIDTH: f32 = 4.0;
+const SELECTION_RING_COLOR: Srgba = BLACK;
+
+// 動き方の大きさ
+const CIRCLE_RADIUS: f32 = 150.0;
+const LINE_AMPLITUDE: f32 = 500.0;
+const FIGURE_EIGHT_SIZE: Vec2 = Vec2::new(300.0, 150.0);
+const MOUSE_FOLLOW_SPEED: f32 = 150.0;
+
+// 3D での音源の高さ
+const EMITTER_HEIGHT_3D: f32 = 1.0;
+
+///
+/// 音源の動き方
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum EmitterPath {
+    Circle,
+    #[default]
+    Line,
+    FigureEight,
+    MouseFollow,
+    // 記録した経路やキーフレームのファイル（KeyframePath）に沿って動く
+    Recorded,
+}
+
+impl EmitterPath {
+    const ALL: [EmitterPath; 4] = [
+        EmitterPath::Circle,
+        EmitterPath::Line,
+        EmitterPath::FigureEight,
+        EmitterPath::MouseFollow,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EmitterPath::Circle => "Circle",
+            EmitterPath::Line => "Line",
+            EmitterPath::FigureEight => "Figure-eight",
+            EmitterPath::MouseFollow => "Mouse-follow",
+            EmitterPath::Recorded => "Recorded",
+        }
+    }
+
+    ///
+    /// 次の動き方（Recorded は経路を記録した時だけ選ばれる）
+    ///
+    fn next(self) -> Self {
+        match Self::ALL.iter().position(|path| *path == self) {
+            Some(index) => Self::ALL[(index + 1) % Self::ALL.len()],
+            None => Self::ALL[0],
+        }
+    }
+
+    ///
+    /// 起点からのずれ（経過時間 0 で起点を通る）
+    /// MouseFollow と Recorded は決まった経路を持たないので None
+    ///
+    fn offset(self, t: f32) -> Option<Vec2> {
+        match self {
+            EmitterPath::Circle => Some(Vec2::new(ops::cos(t) - 1.0, ops::sin(t)) * CIRCLE_RADIUS),
+            EmitterPath::Line => Some(Vec2::new(ops::sin(t) * LINE_AMPLITUDE, 0.0)),
+            EmitterPath::FigureEight => Some(Vec2::new(ops::sin(t), ops::sin(2.0 * t)) * FIGURE_EIGHT_SIZE),
+            EmitterPath::MouseFollow | EmitterPath::Recorded => None,
+        }
+    }
+}
+
+///
+/// 音源
+/// * stopwatch : Stopwatch     経路上の位置を決める経過時間
+/// * origin : Vec2             経路の起点
+/// * path : EmitterPath        動き方
+/// * sound : usize             SOUND_FILES の番号
+///
+#[derive(Component, Default)]
+#[require(Motion, Occlusion)]
+pub struct Emitter {
+    stopwatch: Stopwatch,
+    origin: Vec2,
+    pub path: EmitterPath,
+    pub sound: usize,
+}
+
+impl Emitter {
+    pub fn new(origin: Vec2, path: EmitterPath, sound: usize) -> Self {
+        Emitter {
+            origin,
+            path,
+            sound,
+            ..default()
+        }
+    }
+
+    ///
+    /// 記録した経路（KeyframePath）に沿って最初から動かす
+    ///
+    pub fn follow_keyframes(&mut self) {
+        self.path = EmitterPath::Recorded;
+        self.stopwatch.reset();
+    }
+
+    pub fn sound_name(&self) -> &'static str {
+        let file = SOUND_FILES[self.sound];
+        file.rsplit('/').next().unwrap_or(file)
+    }
+}
+
+///
+/// 音源ファイルのパス
+///
+pub fn sound_file(sound: usize) -> &'static str {
+    SOUND_FILES[sound]
+}
+
+///
+/// 音源ファイルごとの色
+///
+pub fn sound_color(sound: usize) -> Color {
+    Color::from(SOUND_COLORS[sound])
+}
+
+///
+/// 音源ファイルごとのミキサーのバス
+///
+pub fn sound_bus(sound: usize) -> AudioBus {
+    SOUND_BUSES[sound]
+}
+
+///
+/// 経路上の位置（2D のピクセル）を音源の位置にする
+/// 3D では同じ配置を AUDIO_SCALE で縮めて床の上に置く（2D の上が 3D の奥）
+///
+pub fn place(translation: &mut Vec3, position: Vec2, scene_mode: SceneMode) {
+    match scene_mode {
+        SceneMode::TwoD => {
+            translation.x = position.x;
+            translation.y = position.y;
+        }
+        SceneMode::ThreeD => {
+            translation.x = position.x * AUDIO_SCALE;
+            translation.y = EMITTER_HEIGHT_3D;
+            translation.z = -position.y * AUDIO_SCALE;
+        }
+    }
+}
+
+///
+/// 選択中の音源
+///
+#[derive(Component)]
+pub struct Selected;
+
+///
+/// 選択中を表す輪
+///
+#[derive(Component)]
+pub struct SelectionRing;
+
+///
+/// 音源の描画に使うメッシュとマテリアル
+///
+#[derive(Resource)]
+pub struct EmitterAssets {
+    mesh: Handle<Mesh>,
+    ring_mesh: Handle<Mesh>,
+    ring_material: Handle<ColorMaterial>,
+    sound_materials: Vec<Handle<ColorMaterial>>,
+}
+
+///
+/// 新しい音源に使う設定（最後に選んだ音源と動き方を引き継ぐ）
+///
+#[derive(Resource, Default)]
+pub struct NewEmitterSettings {
+    pub path: EmitterPath,
+    pub sound: usize,
+}
+
+///
+/// 音源の描画に使うメッシュとマテリアルを用意する
+/// 最初の SceneMode の OnEnter は Startup より前に実行されるので，リソースの追加時に作る
+///
+impl FromWorld for EmitterAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let mesh = meshes.add(Circle::new(EMITTER_RADIUS));
+        let ring_mesh = meshes.add(Annulus::new(EMITTER_RADIUS + 2.0, EMITTER_RADIUS + 2.0 + SELECTION_RING_WIDTH));
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        EmitterAssets {
+            mesh,
+            ring_mesh,
+            ring_material: materials.add(Color::from(SELECTION_RING_COLOR)),
+            sound_materials: SOUND_COLORS
+                .iter()
+                .map(|color| materials.add(Color::from(*color)))
+                .collect(),
+        }
+    }
+}
+
+///
+/// 音源を追加する
+///
+pub fn spawn_emitter(
+    commands: &mut Commands,
+    emitter_assets: &EmitterAssets,
+    asset_server: &AssetServer,
+    position: Vec2,
+    path: EmitterPath,
+    sound: usize,
+) -> Entity {
+    commands
+        .spawn((
+            Mesh2d(emitter_assets.mesh.clone()),
+            MeshMaterial2d(emitter_assets.sound_materials[sound].clone()),
+            Transform::from_translation(position.extend(0.0)),
+            Emitter::new(position, path, sound),
+            AudioPlayer::new(asset_server.load(SOUND_FILES[sound])),
+            PlaybackSettings::LOOP.with_spatial(true),
+            sound_bus(sound),
+            StateScoped(SceneMode::TwoD),
+        ))
+        .with_child((
+            Mesh2d(emitter_assets.ring_mesh.clone()),
+            MeshMaterial2d(emitter_assets.ring_material.clone()),
+            Transform::from_xyz(0.0, 0.0, -0.1),
+            Visibility::Hidden,
+            SelectionRing,
+        ))
+        .id()
+}
+
+///
+/// 位置にある音源（重なっていれば一番近いもの）
+///
+pub fn emitter_at(position: Vec2, emitters: impl Iterator<Item = (Entity, Vec2)>) -> Option<Entity> {
+    emitters
+        .map(|(entity, translation)| (entity, translation.distance(position)))
+        .filter(|(_, distance)| *distance <= EMITTER_RADIUS + SELECTION_RING_WIDTH)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity)
+}
+
+///
+/// 音源を選択する（選択は 1 つだけ）
+///
+pub fn select_emitter(commands: &mut Commands, entity: Entity, selected: impl Iterator<Item = Entity>) {
+    for other in selected {
+        commands.entity(other).remove::<Selected>();
+    }
+    commands.entity(entity).insert(Selected);
+}
+
+///
+/// 選択中の音源の音源ファイルや動き方を変える
+/// * S      : 音源ファイルを切り替える
+/// * P      : 動き方を切り替える
+/// * Delete : 削除する
+///
+pub fn edit_selected_emitter(
+    mut commands: Commands,
+    mut new_emitter_settings: ResMut<NewEmitterSettings>,
+    mut selected_query: Query<(Entity, &mut Emitter, &mut MeshMaterial2d<ColorMaterial>, &Transform), With<Selected>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    emitter_assets: Res<EmitterAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok((entity, mut emitter, mut material, transform)) = selected_query.get_single_mut() else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Delete) || keyboard.just_pressed(KeyCode::Backspace) {
+        commands.entity(entity).despawn_recursive();
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        emitter.sound = (emitter.sound + 1) % SOUND_FILES.len();
+        material.0 = emitter_assets.sound_materials[emitter.sound].clone();
+        // 再生中のシンクを外すと，新しい AudioPlayer で再生し直される
+        commands
+            .entity(entity)
+            .remove::<SpatialAudioSink>()
+            .insert((AudioPlayer::new(asset_server.load(SOUND_FILES[emitter.sound])), sound_bus(emitter.sound)));
+    }
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        emitter.path = emitter.path.next();
+        // 今の位置から新しい経路で動き始める
+        emitter.origin = transform.translation.truncate();
+        emitter.stopwatch.reset();
+    }
+    // 記録した経路は音源ごとのものなので引き継がない
+    if emitter.path != EmitterPath::Recorded {
+        new_emitter_settings.path = emitter.path;
+    }
+    new_emitter_settings.sound = emitter.sound;
+}
+
+///
+/// 選択中の音源にだけ輪を表示する
+///
+pub fn show_selection(
+    emitter_query: Query<(&Children, Has<Selected>), With<Emitter>>,
+    mut ring_query: Query<&mut Visibility, With<SelectionRing>>,
+) {
+    for (children, selected) in &emitter_query {
+        for child in children.iter() {
+            if let Ok(mut visibility) = ring_query.get_mut(*child) {
+                visibility.set_if_neq(if selected { Visibility::Inherited } else { Visibility::Hidden });
+            }
+        }
+    }
+}
+
+///
+/// 音源を経路に沿って動かす（Space で全ての音源の動きを止める・再開する）
+///
+pub fn update_emitters(
+    time: Res<Time>,
+    mut emitters: Query<(&mut Transform, &mut Emitter, Option<&KeyframePath>), Without<Dragged>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+    scene_mode: Res<State<SceneMode>>,
+) {
+    for (mut emitter_transform, mut emitter, keyframe_path) in emitters.iter_mut() {
+        if keyboard.just_pressed(KeyCode::Space) {
+            if emitter.stopwatch.is_paused() {
+                emitter.stopwatch.unpause();
+            } else {
+                emitter.stopwatch.pause();
+            }
+        }
+
+        emitter.stopwatch.tick(time.delta());
+
+        if emitter.stopwatch.is_paused() {
+            continue;
+        }
+        let elapsed = emitter.stopwatch.elapsed_secs();
+        match (emitter.path.offset(elapsed), keyframe_path) {
+            (Some(offset), _) => {
+                place(&mut emitter_transform.translation, emitter.origin + offset, **scene_mode);
+            }
+            (None, Some(keyframe_path)) if emitter.path == EmitterPath::Recorded => {
+                place(&mut emitter_transform.translation, keyframe_path.position_at(elapsed), **scene_mode);
+            }
+            (None, _) => {
+                // カーソルは 2D でしか使わない
+                let Some(target) = cursor_position.0 else {
+                    continue;
+                };
+                let current = emitter_transform.translation.truncate();
+                let position = current.move_towards(target, MOUSE_FOLLOW_SPEED * time.delta_secs());
+                place(&mut emitter_transform.translation, position, **scene_mode);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use crate::audio_mixer::{AudioBus, AudioMixer, Ducking};
+use crate::launcher::Demo;
+
+// - = キーで変える音量の幅
+const VOLUME_STEP: f32 = 0.1;
+
+// ミキサーの表示（右下）
+const PANEL_MARGIN: f32 = 12.0;
+
+// バスを選ぶキー（AudioBus::ALL の順）
+const BUS_KEYS: [KeyCode; 4] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4];
+
+///
+/// ミキサーの操作と表示（2D と 3D で共通）
+/// * 1 〜 4 : バスを選ぶ
+/// * - =    : 選択中のバスの音量を下げる・上げる
+/// * M      : 選択中のバスをミュートする
+/// * N      : ダッキングの有無を切り替える
+///
+pub struct MixerPanelPlugin;
+
+impl Plugin for MixerPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedBus>()
+            .add_systems(OnEnter(Demo::SpatialAudio), setup_panel)
+            .add_systems(Update, (edit_mixer, update_panel).chain().run_if(in_state(Demo::SpatialAudio)));
+    }
+}
+
+///
+/// 操作中のバス
+///
+#[derive(Resource)]
+struct SelectedBus(AudioBus);
+
+impl Default for SelectedBus {
+    fn default() -> Self {
+        SelectedBus(AudioBus::Music)
+    }
+}
+
+///
+/// バスごとの設定の表示
+///
+#[derive(Component)]
+struct MixerText;
+
+fn setup_panel(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(PANEL_MARGIN),
+            right: Val::Px(PANEL_MARGIN),
+            ..default()
+        },
+        MixerText,
+        StateScoped(Demo::SpatialAudio),
+    ));
+}
+
+///
+/// キーでバスを選び，音量・ミュート・ダッキングを変える
+///
+fn edit_mixer(
+    mut mixer: ResMut<AudioMixer>,
+    mut selected_bus: ResMut<SelectedBus>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    for (bus, key) in AudioBus::ALL.iter().zip(BUS_KEYS) {
+        if keyboard.just_pressed(key) {
+            selected_bus.0 = *bus;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyN) {
+        mixer.ducking = match mixer.ducking {
+            Some(_) => None,
+            None => Some(Ducking::default()),
+        };
+    }
+
+    let settings = mixer.bus_mut(selected_bus.0);
+    if keyboard.just_pressed(KeyCode::Minus) {
+        settings.volume = (settings.volume - VOLUME_STEP).max(0.0);
+    }
+    if keyboard.just_pressed(KeyCode::Equal) {
+        settings.volume = (settings.volume + VOLUME_STEP).min(1.0);
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+    }
+}
+
+///
+/// バスごとの音量とミュート，ダッキングの有無を表示する（選択中のバスに > を付ける）
+///
+fn update_panel(
+    mut mixer_text: Single<&mut Text, With<MixerText>>,
+    mixer: Res<AudioMixer>,
+    selected_bus: Res<SelectedBus>,
+) {
+    let mut text = "Mixer\n".to_string();
+    for bus in AudioBus::ALL {
+        let settings = mixer.bus(bus);
+        let cursor = if bus == selected_bus.0 { ">" } else { " " };
+        let muted = if settings.muted { "  (Muted)" } else { "" };
+        text += &format!("{} {:<8} {:>3.0}%{}\n", cursor, bus.label(), settings.volume * 100.0, muted);
+    }
+    text += &format!("Ducking: {}", if mixer.ducking.is_some() { "On" } else { "Off" });
+    mixer_text.0 = text;
+}
@@ -0,0 +1,251 @@
+use bevy::{
+    audio::{DefaultSpatialScale, SpatialAudioSink},
+    color::palettes::css::*,
+    prelude::*,
+};
+use crate::launcher::Demo;
+use super::{CursorPosition, SceneMode};
+use super::emitter::Emitter;
+
+// 音速（音の単位 / 秒）
+const SPEED_OF_SOUND: f32 = 343.0;
+// ドップラー効果を分かりやすくするための倍率（OpenAL の Doppler Factor と同じ）
+const DOPPLER_FACTOR: f32 = 20.0;
+// 再生速度の範囲
+const MIN_DOPPLER_SPEED: f32 = 0.5;
+const MAX_DOPPLER_SPEED: f32 = 2.0;
+// 速度の平滑化の強さ（大きいほど速く追従する）
+const VELOCITY_SMOOTHING: f32 = 10.0;
+
+// 壁 1 枚あたりの音量の倍率
+const WALL_OCCLUSION_GAIN: f32 = 0.3;
+// 遮られた時の音量の変化の速さ（大きいほど速く変わる）
+const OCCLUSION_SMOOTHING: f32 = 8.0;
+
+// 壁の見た目
+const WALL_THICKNESS: f32 = 8.0;
+const WALL_COLOR: Srgba = DIM_GRAY;
+const WALL_DRAFT_COLOR: Srgba = GRAY;
+// これより短い壁は作らない
+const MIN_WALL_LENGTH: f32 = 10.0;
+
+///
+/// 音の伝わり方
+/// 音源とリスナーの相対速度からドップラー効果で再生速度を変え，壁に遮られた音源の音量を下げる
+/// 再生中のシンクには後からフィルタを掛けられないので，壁の効果は音量だけで表す
+///
+pub struct PropagationPlugin;
+
+impl Plugin for PropagationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WallDraft>()
+            .add_systems(
+                Update,
+                (edit_walls, draw_walls, update_occlusion).chain().run_if(in_state(SceneMode::TwoD)),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_doppler
+                    .after(TransformSystem::TransformPropagate)
+                    .run_if(in_state(Demo::SpatialAudio)),
+            );
+    }
+}
+
+///
+/// 速度を求めるための前のフレームの位置
+/// * previous : Option<Vec3>       前のフレームの位置（最初のフレームは None）
+/// * velocity : Vec3               平滑化した速度（ワールド座標 / 秒）
+///
+#[derive(Component, Default)]
+pub struct Motion {
+    previous: Option<Vec3>,
+    velocity: Vec3,
+}
+
+impl Motion {
+    ///
+    /// 今の位置から速度を更新する
+    ///
+    fn update(&mut self, position: Vec3, delta_secs: f32) {
+        if let Some(previous) = self.previous {
+            let velocity = (position - previous) / delta_secs;
+            let t = (VELOCITY_SMOOTHING * delta_secs).min(1.0);
+            self.velocity = self.velocity.lerp(velocity, t);
+        }
+        self.previous = Some(position);
+    }
+}
+
+///
+/// 壁による音量の倍率（壁がなければ 1）
+///
+#[derive(Component)]
+pub struct Occlusion(pub f32);
+
+impl Default for Occlusion {
+    fn default() -> Self {
+        Occlusion(1.0)
+    }
+}
+
+///
+/// 音を遮る壁
+///
+#[derive(Component)]
+struct Wall {
+    start: Vec2,
+    end: Vec2,
+}
+
+///
+/// 描いている途中の壁の始点
+///
+#[derive(Resource, Default)]
+struct WallDraft(Option<Vec2>);
+
+///
+/// 2 つの線分が交わるか
+///
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let ab = b - a;
+    let cd = d - c;
+    let denominator = ab.perp_dot(cd);
+    if denominator == 0.0 {
+        return false;
+    }
+    let t = (c - a).perp_dot(cd) / denominator;
+    let u = (c - a).perp_dot(ab) / denominator;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+///
+/// 点から線分までの距離
+///
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = if segment == Vec2::ZERO {
+        0.0
+    } else {
+        ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    };
+    point.distance(start + segment * t)
+}
+
+///
+/// 壁の操作
+/// * Shift + ドラッグ   : 壁を描く
+/// * Shift + 右クリック : 壁を削除する
+///
+fn edit_walls(
+    mut commands: Commands,
+    mut wall_draft: ResMut<WallDraft>,
+    wall_query: Query<(Entity, &Wall)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+) {
+    let Some(cursor) = cursor_position.0 else {
+        return;
+    };
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if shift && mouse.just_pressed(MouseButton::Left) {
+        wall_draft.0 = Some(cursor);
+    }
+    if mouse.just_released(MouseButton::Left) {
+        if let Some(start) = wall_draft.0.take() {
+            if start.distance(cursor) >= MIN_WALL_LENGTH {
+                spawn_wall(&mut commands, start, cursor);
+            }
+        }
+    }
+    if shift && mouse.just_pressed(MouseButton::Right) {
+        let hit = wall_query
+            .iter()
+            .find(|(_, wall)| distance_to_segment(cursor, wall.start, wall.end) <= WALL_THICKNESS);
+        if let Some((entity, _)) = hit {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+///
+/// 壁を追加する
+///
+fn spawn_wall(commands: &mut Commands, start: Vec2, end: Vec2) {
+    let segment = end - start;
+    commands.spawn((
+        Sprite::from_color(WALL_COLOR, Vec2::new(segment.length(), WALL_THICKNESS)),
+        Transform::from_translation(((start + end) / 2.0).extend(-1.0))
+            .with_rotation(Quat::from_rotation_z(segment.to_angle())),
+        Wall { start, end },
+        StateScoped(SceneMode::TwoD),
+    ));
+}
+
+///
+/// 描いている途中の壁を線で示す
+///
+fn draw_walls(mut gizmos: Gizmos, wall_draft: Res<WallDraft>, cursor_position: Res<CursorPosition>) {
+    if let (Some(start), Some(cursor)) = (wall_draft.0, cursor_position.0) {
+        gizmos.line_2d(start, cursor, WALL_DRAFT_COLOR);
+    }
+}
+
+///
+/// 音源とリスナーの間にある壁の数から音量の倍率を求める
+/// 急に音量が変わらないよう，少しずつ近づける
+///
+fn update_occlusion(
+    time: Res<Time>,
+    listener: Single<&GlobalTransform, With<SpatialListener>>,
+    mut emitter_query: Query<(&GlobalTransform, &mut Occlusion), With<Emitter>>,
+    wall_query: Query<&Wall>,
+) {
+    let listener_position = listener.translation().truncate();
+    let t = (OCCLUSION_SMOOTHING * time.delta_secs()).min(1.0);
+    for (emitter_transform, mut occlusion) in &mut emitter_query {
+        let emitter_position = emitter_transform.translation().truncate();
+        let walls = wall_query
+            .iter()
+            .filter(|wall| segments_intersect(emitter_position, listener_position, wall.start, wall.end))
+            .count();
+        let target = WALL_OCCLUSION_GAIN.powi(walls as i32);
+        occlusion.0 += (target - occlusion.0) * t;
+    }
+}
+
+///
+/// 音源とリスナーの相対速度から，ドップラー効果による再生速度を設定する
+/// 速度は音源からリスナーへの向きの成分だけを使う（OpenAL と同じ式）
+///
+fn apply_doppler(
+    time: Res<Time>,
+    mut listener: Single<(&GlobalTransform, &mut Motion), With<SpatialListener>>,
+    mut emitter_query: Query<(&GlobalTransform, &mut Motion, Option<&SpatialAudioSink>), Without<SpatialListener>>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let delta_secs = time.delta_secs();
+    if delta_secs <= 0.0 {
+        return;
+    }
+    let scale = default_spatial_scale.0 .0;
+    let (listener_transform, listener_motion) = &mut *listener;
+    listener_motion.update(listener_transform.translation(), delta_secs);
+    let listener_position = listener_transform.translation() * scale;
+    let listener_velocity = listener_motion.velocity * scale;
+    let max_speed = SPEED_OF_SOUND / DOPPLER_FACTOR;
+
+    for (emitter_transform, mut motion, sink) in &mut emitter_query {
+        motion.update(emitter_transform.translation(), delta_secs);
+        let Some(sink) = sink else {
+            continue;
+        };
+        let to_listener = (listener_position - emitter_transform.translation() * scale).normalize_or_zero();
+        let listener_speed = to_listener.dot(listener_velocity).min(max_speed);
+        let emitter_speed = to_listener.dot(motion.velocity * scale).min(max_speed);
+        let speed = (SPEED_OF_SOUND - DOPPLER_FACTOR * listener_speed) / (SPEED_OF_SOUND - DOPPLER_FACTOR * emitter_speed);
+        sink.set_speed(speed.clamp(MIN_DOPPLER_SPEED, MAX_DOPPLER_SPEED));
+    }
+}
@@ -0,0 +1,320 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use super::{CursorPosition, SceneMode};
+use super::emitter::{self, Emitter, EmitterAssets, EmitterPath, NewEmitterSettings, Selected};
+
+// 経路ファイルの置き場所
+const PATHS_DIR: &str = "save/paths";
+const PATH_FILE_PREFIX: &str = "path_";
+const PATH_FILE_EXTENSION: &str = "txt";
+
+// これより動かさなかったドラッグは記録しない（クリックでの選択とみなす）
+const MIN_RECORD_DISTANCE: f32 = 10.0;
+
+///
+/// 音源の経路の記録と再生
+/// 音源をドラッグすると動きを記録し，離すとその動きを繰り返す
+/// 記録した経路はファイルに保存でき，キーフレームのファイル（時刻, x, y）から音源を動かせる
+///
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EmitterDrag>()
+            .init_resource::<StatusMessage>()
+            .add_systems(OnExit(SceneMode::TwoD), cancel_emitter_drag)
+            .add_systems(
+                Update,
+                (record_emitter_drag, save_selected_path, load_path_files)
+                    .chain()
+                    .run_if(in_state(SceneMode::TwoD)),
+            );
+    }
+}
+
+///
+/// 経路の 1 点
+/// * time : f32            経路の最初からの時刻（秒）
+/// * position : Vec2       位置（2D のピクセル）
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: Vec2,
+}
+
+///
+/// キーフレームで決まる経路（最後のキーフレームまで進んだら最初に戻る）
+///
+#[derive(Component, Clone, Debug)]
+pub struct KeyframePath(Vec<Keyframe>);
+
+impl KeyframePath {
+    ///
+    /// 時刻順に並べて作る（キーフレームが 1 つもなければ None）
+    ///
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Option<Self> {
+        if keyframes.is_empty() {
+            return None;
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Some(KeyframePath(keyframes))
+    }
+
+    fn duration(&self) -> f32 {
+        self.0.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    ///
+    /// 時刻 t の位置（前後のキーフレームの間を直線で補間する）
+    ///
+    pub fn position_at(&self, t: f32) -> Vec2 {
+        let duration = self.duration();
+        let t = if duration > 0.0 { t.rem_euclid(duration) } else { 0.0 };
+        let next = self.0.partition_point(|keyframe| keyframe.time <= t);
+        match (next.checked_sub(1).and_then(|index| self.0.get(index)), self.0.get(next)) {
+            (Some(before), Some(after)) => {
+                let ratio = (t - before.time) / (after.time - before.time);
+                before.position.lerp(after.position, ratio)
+            }
+            (Some(keyframe), None) | (None, Some(keyframe)) => keyframe.position,
+            (None, None) => Vec2::ZERO,
+        }
+    }
+
+    pub fn first_position(&self) -> Vec2 {
+        self.0[0].position
+    }
+
+    ///
+    /// ファイルに書く形にする（1 行に "時刻, x, y"）
+    ///
+    fn to_text(&self) -> String {
+        let mut text = "# time, x, y\n".to_string();
+        for keyframe in &self.0 {
+            text += &format!("{:.3}, {:.1}, {:.1}\n", keyframe.time, keyframe.position.x, keyframe.position.y);
+        }
+        text
+    }
+
+    ///
+    /// "時刻, x, y" の行を読み込む（# から後と空行は無視する，区切りはカンマか空白）
+    ///
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut keyframes = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| format!("line {} : {}", index + 1, e))?;
+            let [time, x, y] = values[..] else {
+                return Err(format!("line {} : expected 'time, x, y'", index + 1));
+            };
+            if !time.is_finite() || time < 0.0 {
+                return Err(format!("line {} : invalid time {}", index + 1, time));
+            }
+            keyframes.push(Keyframe {
+                time,
+                position: Vec2::new(x, y),
+            });
+        }
+        KeyframePath::new(keyframes).ok_or_else(|| "no keyframes".to_string())
+    }
+}
+
+///
+/// ドラッグ中の音源
+///
+#[derive(Component)]
+pub struct Dragged;
+
+///
+/// 音源のドラッグの記録
+/// * entity : Entity               ドラッグ中の音源
+/// * grab_offset : Vec2            つかんだ位置と音源の中心とのずれ
+/// * started : f32                 ドラッグを始めた時刻
+/// * keyframes : Vec<Keyframe>     記録した位置
+///
+struct DragRecording {
+    entity: Entity,
+    grab_offset: Vec2,
+    started: f32,
+    keyframes: Vec<Keyframe>,
+}
+
+///
+/// ドラッグ中の音源の記録（ドラッグしていなければ None）
+///
+#[derive(Resource, Default)]
+pub struct EmitterDrag(Option<DragRecording>);
+
+impl EmitterDrag {
+    ///
+    /// 音源のドラッグを始める
+    ///
+    pub fn begin(&mut self, commands: &mut Commands, entity: Entity, grab_offset: Vec2, now: f32) {
+        commands.entity(entity).insert(Dragged);
+        self.0 = Some(DragRecording {
+            entity,
+            grab_offset,
+            started: now,
+            keyframes: Vec::new(),
+        });
+    }
+}
+
+///
+/// 経路の保存・読み込みの結果の表示
+///
+#[derive(Resource, Default)]
+pub struct StatusMessage(pub String);
+
+///
+/// ドラッグ中の音源をカーソルに合わせて動かし，その位置を記録する
+/// 離したら記録した経路で動くようにする
+///
+fn record_emitter_drag(
+    mut commands: Commands,
+    mut emitter_drag: ResMut<EmitterDrag>,
+    mut emitter_query: Query<(&mut Transform, &mut Emitter)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor_position: Res<CursorPosition>,
+    time: Res<Time>,
+) {
+    let Some(recording) = &mut emitter_drag.0 else {
+        return;
+    };
+    let Ok((mut transform, mut emitter)) = emitter_query.get_mut(recording.entity) else {
+        // ドラッグ中に削除された
+        emitter_drag.0 = None;
+        return;
+    };
+
+    if let Some(cursor) = cursor_position.0 {
+        let position = cursor - recording.grab_offset;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        recording.keyframes.push(Keyframe {
+            time: time.elapsed_secs() - recording.started,
+            position,
+        });
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+
+    commands.entity(recording.entity).remove::<Dragged>();
+    let moved = recording
+        .keyframes
+        .first()
+        .is_some_and(|first| recording.keyframes.iter().any(|k| k.position.distance(first.position) >= MIN_RECORD_DISTANCE));
+    if moved {
+        if let Some(path) = KeyframePath::new(std::mem::take(&mut recording.keyframes)) {
+            emitter.follow_keyframes();
+            commands.entity(recording.entity).insert(path);
+        }
+    }
+    emitter_drag.0 = None;
+}
+
+///
+/// 2D のシーンを抜けたらドラッグをやめる
+///
+fn cancel_emitter_drag(mut emitter_drag: ResMut<EmitterDrag>) {
+    emitter_drag.0 = None;
+}
+
+///
+/// 経路ファイルのパス（path_1.txt, path_2.txt, ... のうち使われていないもの）
+///
+fn next_path_file() -> PathBuf {
+    (1..)
+        .map(|number| Path::new(PATHS_DIR).join(format!("{}{}.{}", PATH_FILE_PREFIX, number, PATH_FILE_EXTENSION)))
+        .find(|path| !path.exists())
+        .unwrap_or_default()
+}
+
+///
+/// K で選択中の音源の記録した経路をファイルに保存する
+///
+fn save_selected_path(
+    mut status_message: ResMut<StatusMessage>,
+    selected_query: Query<&KeyframePath, With<Selected>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyK) {
+        return;
+    }
+    let Ok(keyframe_path) = selected_query.get_single() else {
+        status_message.0 = "Select an emitter with a recorded path to save it".to_string();
+        return;
+    };
+    let path = next_path_file();
+    let result = fs::create_dir_all(PATHS_DIR).and_then(|_| fs::write(&path, keyframe_path.to_text()));
+    status_message.0 = match result {
+        Ok(_) => format!("Saved {}", path.display()),
+        Err(e) => format!("Failed to save {} : {}", path.display(), e),
+    };
+}
+
+///
+/// L で経路ファイルを全て読み込み，ファイルごとに音源を追加してその経路で動かす
+///
+fn load_path_files(
+    mut commands: Commands,
+    mut status_message: ResMut<StatusMessage>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    new_emitter_settings: Res<NewEmitterSettings>,
+    emitter_assets: Res<EmitterAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    let mut files: Vec<PathBuf> = match fs::read_dir(PATHS_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == PATH_FILE_EXTENSION))
+            .collect(),
+        Err(e) => {
+            status_message.0 = format!("Failed to read {} : {}", PATHS_DIR, e);
+            return;
+        }
+    };
+    files.sort();
+
+    let mut loaded = 0;
+    let mut errors = Vec::new();
+    for file in &files {
+        let keyframe_path = fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| KeyframePath::parse(&text));
+        match keyframe_path {
+            Ok(keyframe_path) => {
+                let entity = emitter::spawn_emitter(
+                    &mut commands,
+                    &emitter_assets,
+                    &asset_server,
+                    keyframe_path.first_position(),
+                    EmitterPath::Recorded,
+                    new_emitter_settings.sound,
+                );
+                commands.entity(entity).insert(keyframe_path);
+                loaded += 1;
+            }
+            Err(e) => errors.push(format!("{} : {}", file.display(), e)),
+        }
+    }
+    status_message.0 = format!("Loaded {} path file(s) from {}", loaded, PATHS_DIR);
+    for error in errors {
+        warn!("{}", error);
+        status_message.0 += &format!("\n{}", error);
+    }
+}
@@ -0,0 +1,235 @@
+use std::f32::consts::FRAC_PI_2;
+use bevy::{
+    audio::{DefaultSpatialScale, SpatialScale},
+    input::mouse::AccumulatedMouseMotion,
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use super::SceneMode;
+use super::emitter::{self, EmitterPath};
+use super::propagation::Motion;
+
+// 3D では 1 単位を 1 m とし，そのまま音の単位として使う
+const AUDIO_SCALE_3D: f32 = 1.0;
+
+// 一人称視点のリスナー
+const EYE_HEIGHT: f32 = 1.6;
+const EAR_GAP: f32 = 0.2;
+const MOVE_SPEED: f32 = 4.0;
+const MOUSE_SENSITIVITY: f32 = 0.003;
+// 真上・真下を向くと向きが定まらないので少し手前で止める
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// 床と目印の柱
+const FLOOR_SIZE: f32 = 40.0;
+const FLOOR_COLOR: Color = Color::srgb(0.35, 0.4, 0.35);
+const PILLAR_SIZE: Vec3 = Vec3::new(0.4, 3.0, 0.4);
+const PILLAR_COLOR: Color = Color::srgb(0.6, 0.6, 0.65);
+const PILLAR_POSITIONS: [Vec2; 4] = [
+    Vec2::new(-6.0, -6.0),
+    Vec2::new(6.0, -6.0),
+    Vec2::new(-6.0, 6.0),
+    Vec2::new(6.0, 6.0),
+];
+
+// 音源（位置は 2D と同じくピクセルで指定し，emitter::place で 3D に置く）
+const EMITTER_RADIUS_3D: f32 = 0.25;
+const EMITTERS_3D: [(Vec2, EmitterPath, usize); 3] = [
+    (Vec2::new(0.0, 400.0), EmitterPath::Circle, 0),
+    (Vec2::new(-500.0, -200.0), EmitterPath::FigureEight, 1),
+    (Vec2::new(400.0, 100.0), EmitterPath::Line, 1),
+];
+
+///
+/// 3D の空間音響のシーン
+/// カメラ（リスナー）を WASD とマウスで一人称視点で動かす
+///
+pub struct Scene3dPlugin;
+
+impl Plugin for Scene3dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SceneMode::ThreeD), (setup_3d, grab_cursor))
+            .add_systems(OnExit(SceneMode::ThreeD), release_cursor)
+            .add_systems(
+                Update,
+                (toggle_cursor_grab, look_around, move_listener)
+                    .chain()
+                    .run_if(in_state(SceneMode::ThreeD)),
+            );
+    }
+}
+
+///
+/// 一人称視点の向き（ラジアン）
+///
+#[derive(Component, Default)]
+struct FirstPersonController {
+    yaw: f32,
+    pitch: f32,
+}
+
+///
+/// ThreeD 遷移時のセットアップ関数
+/// カメラにリスナーを付け，床と柱と音源を生成する
+///
+fn setup_3d(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut default_spatial_scale: ResMut<DefaultSpatialScale>,
+    asset_server: Res<AssetServer>,
+) {
+    default_spatial_scale.0 = SpatialScale::new(AUDIO_SCALE_3D);
+
+    // camera (listener)
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, EYE_HEIGHT, 0.0),
+        SpatialListener::new(EAR_GAP),
+        Motion::default(),
+        FirstPersonController::default(),
+        StateScoped(SceneMode::ThreeD),
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        StateScoped(SceneMode::ThreeD),
+    ));
+
+    // floor
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(FLOOR_SIZE, FLOOR_SIZE))),
+        MeshMaterial3d(materials.add(FLOOR_COLOR)),
+        StateScoped(SceneMode::ThreeD),
+    ));
+
+    // pillars
+    let pillar_mesh = meshes.add(Cuboid::from_size(PILLAR_SIZE));
+    let pillar_material = materials.add(PILLAR_COLOR);
+    for position in PILLAR_POSITIONS {
+        commands.spawn((
+            Mesh3d(pillar_mesh.clone()),
+            MeshMaterial3d(pillar_material.clone()),
+            Transform::from_xyz(position.x, PILLAR_SIZE.y / 2.0, position.y),
+            StateScoped(SceneMode::ThreeD),
+        ));
+    }
+
+    // sound emitters
+    let emitter_mesh = meshes.add(Sphere::new(EMITTER_RADIUS_3D));
+    for (origin, path, sound) in EMITTERS_3D {
+        let mut transform = Transform::default();
+        emitter::place(&mut transform.translation, origin, SceneMode::ThreeD);
+        commands.spawn((
+            Mesh3d(emitter_mesh.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: emitter::sound_color(sound),
+                emissive: emitter::sound_color(sound).to_linear(),
+                ..default()
+            })),
+            transform,
+            emitter::Emitter::new(origin, path, sound),
+            AudioPlayer::new(asset_server.load(emitter::sound_file(sound))),
+            PlaybackSettings::LOOP.with_spatial(true),
+            emitter::sound_bus(sound),
+            StateScoped(SceneMode::ThreeD),
+        ));
+    }
+
+    // example instructions
+    commands.spawn((
+        Text::new(
+            "WASD: Move  Mouse: Look\n\
+             Click: Capture Mouse  Esc: Release Mouse\n\
+             Space: Toggle Emitter Movement  [ / ]: Change Spatial Scale\n\
+             1-4: Select Bus  - / =: Bus Volume  M: Mute Bus  N: Toggle Ducking\n\
+             Tab: Switch to 2D",
+        ),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        StateScoped(SceneMode::ThreeD),
+    ));
+}
+
+///
+/// マウスカーソルを固定して隠す（視点操作用）
+///
+fn grab_cursor(mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    window.cursor_options.grab_mode = CursorGrabMode::Locked;
+    window.cursor_options.visible = false;
+}
+
+///
+/// マウスカーソルを元に戻す
+///
+fn release_cursor(mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    window.cursor_options.grab_mode = CursorGrabMode::None;
+    window.cursor_options.visible = true;
+}
+
+///
+/// Esc でマウスカーソルを解放し，クリックで再び固定する
+///
+fn toggle_cursor_grab(
+    window: Single<&mut Window, With<PrimaryWindow>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        release_cursor(window);
+    } else if mouse.just_pressed(MouseButton::Left) {
+        grab_cursor(window);
+    }
+}
+
+///
+/// マウスの移動で視点を回す（カーソルを固定している間だけ）
+///
+fn look_around(
+    window: Single<&Window, With<PrimaryWindow>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mut camera: Single<(&mut Transform, &mut FirstPersonController)>,
+) {
+    if window.cursor_options.grab_mode == CursorGrabMode::None {
+        return;
+    }
+    let (transform, controller) = &mut *camera;
+    controller.yaw -= mouse_motion.delta.x * MOUSE_SENSITIVITY;
+    controller.pitch = (controller.pitch - mouse_motion.delta.y * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+    transform.rotation = Quat::from_euler(EulerRot::YXZ, controller.yaw, controller.pitch, 0.0);
+}
+
+///
+/// WASD で水平に移動する（向いている方向が前）
+///
+fn move_listener(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut camera: Single<(&mut Transform, &FirstPersonController)>,
+) {
+    let (transform, controller) = &mut *camera;
+    let forward = Quat::from_rotation_y(controller.yaw) * Vec3::NEG_Z;
+    let right = Quat::from_rotation_y(controller.yaw) * Vec3::X;
+    let mut direction = Vec3::ZERO;
+    if keyboard.pressed(KeyCode::KeyW) {
+        direction += forward;
+    }
+    if keyboard.pressed(KeyCode::KeyS) {
+        direction -= forward;
+    }
+    if keyboard.pressed(KeyCode::KeyD) {
+        direction += right;
+    }
+    if keyboard.pressed(KeyCode::KeyA) {
+        direction -= right;
+    }
+    transform.translation += direction.normalize_or_zero() * MOVE_SPEED * time.delta_secs();
+}
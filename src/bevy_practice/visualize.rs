@@ -0,0 +1,225 @@
+use bevy::{
+    audio::{DefaultSpatialScale, SpatialAudioSink, SpatialScale},
+    color::palettes::css::*,
+    prelude::*,
+};
+use crate::audio_mixer::{AudioBus, AudioMixer, DuckingGain};
+use crate::launcher::Demo;
+use super::attenuation::{emitter_ear_gains, Attenuation};
+use super::SceneMode;
+use super::emitter::Emitter;
+use super::propagation::Occlusion;
+
+// 距離の輪（音の単位で 1, 2, ... の距離に引く）
+const RING_COUNT: usize = 5;
+const RING_COLOR: Srgba = GRAY;
+// 音量が減り始める距離（1 単位）の輪
+const UNIT_RING_COLOR: Srgba = DARK_GREEN;
+
+// 音源から耳への線（音量が小さいほど薄くする）
+const EAR_LINE_MIN_ALPHA: f32 = 0.1;
+
+// [ ] キーで SpatialScale を変える倍率
+const SCALE_STEP: f32 = 1.25;
+
+// 耳ごとの音量の表示（右上）
+const METER_SIZE: Vec2 = Vec2::new(200.0, 14.0);
+const METER_MARGIN: f32 = 12.0;
+const METER_BG_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const EAR_COLORS: [Srgba; 2] = [RED, LIME];
+
+///
+/// 耳ごとの音量の表示のバー（0 が左耳，1 が右耳）
+///
+#[derive(Component)]
+struct MeterBar(usize);
+
+///
+/// 耳ごとの音量の数値と SpatialScale の表示
+///
+#[derive(Component)]
+struct MeterText;
+
+///
+/// 空間音響の可視化
+/// リスナーの周りの距離の輪，音源から耳への線（2D のみ），耳ごとの音量を表示する
+/// 音量は rodio の SpatialSink と同じ計算で求める（独自の減衰や壁による減衰，ミキサーの音量も掛ける）
+///
+pub struct SpatialVisualizerPlugin;
+
+impl Plugin for SpatialVisualizerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(Demo::SpatialAudio), setup_meter)
+            .add_systems(
+                Update,
+                (
+                    adjust_spatial_scale,
+                    (draw_distance_rings, draw_ear_lines).run_if(in_state(SceneMode::TwoD)),
+                    update_meter,
+                )
+                    .chain()
+                    .run_if(in_state(Demo::SpatialAudio)),
+            );
+    }
+}
+
+///
+/// 両耳それぞれの音量の倍率（位置は SpatialScale を掛けたもの）
+/// rodio の Spatial::set_positions と同じく，距離の 2 乗に反比例する減衰と左右の差による減衰を掛け合わせる
+///
+pub fn ear_gains(emitter: Vec3, left_ear: Vec3, right_ear: Vec3) -> [f32; 2] {
+    let left_dist_sq = emitter.distance_squared(left_ear);
+    let right_dist_sq = emitter.distance_squared(right_ear);
+    let max_diff = left_ear.distance(right_ear);
+    let left_dist = left_dist_sq.sqrt();
+    let right_dist = right_dist_sq.sqrt();
+    let left_diff_modifier = (((left_dist - right_dist) / max_diff + 1.0) / 4.0 + 0.5).min(1.0);
+    let right_diff_modifier = (((right_dist - left_dist) / max_diff + 1.0) / 4.0 + 0.5).min(1.0);
+    let left_dist_modifier = (1.0 / left_dist_sq).min(1.0);
+    let right_dist_modifier = (1.0 / right_dist_sq).min(1.0);
+    [left_diff_modifier * left_dist_modifier, right_diff_modifier * right_dist_modifier]
+}
+
+///
+/// リスナーの両耳のワールド座標
+///
+pub fn ear_positions(listener_transform: &GlobalTransform, listener: &SpatialListener) -> [Vec3; 2] {
+    [
+        listener_transform.transform_point(listener.left_ear_offset),
+        listener_transform.transform_point(listener.right_ear_offset),
+    ]
+}
+
+///
+/// Demo::SpatialAudio に入った時に耳ごとの音量の表示を生成する
+///
+fn setup_meter(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(METER_MARGIN),
+                right: Val::Px(METER_MARGIN),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            StateScoped(Demo::SpatialAudio),
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::default(), MeterText));
+            for (ear, color) in EAR_COLORS.iter().enumerate() {
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Px(METER_SIZE.x),
+                            height: Val::Px(METER_SIZE.y),
+                            ..default()
+                        },
+                        BackgroundColor(METER_BG_COLOR),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::from(*color)),
+                        MeterBar(ear),
+                    ));
+            }
+        });
+}
+
+///
+/// [ ] キーで SpatialScale を小さく・大きくする
+/// DefaultSpatialScale を変えると，再生中の音源にもすぐに反映される
+///
+fn adjust_spatial_scale(
+    mut default_spatial_scale: ResMut<DefaultSpatialScale>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    let factor = if keyboard.just_pressed(KeyCode::BracketLeft) {
+        1.0 / SCALE_STEP
+    } else if keyboard.just_pressed(KeyCode::BracketRight) {
+        SCALE_STEP
+    } else {
+        return;
+    };
+    default_spatial_scale.0 = SpatialScale(default_spatial_scale.0 .0 * factor);
+}
+
+///
+/// リスナーの周りに，音の単位で 1, 2, ... の距離の輪を描く
+/// 1 の輪の内側では距離による減衰がない
+///
+fn draw_distance_rings(
+    mut gizmos: Gizmos,
+    listener: Single<&GlobalTransform, With<SpatialListener>>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let center = listener.translation().truncate();
+    let scale = default_spatial_scale.0 .0.x;
+    for distance in 1..=RING_COUNT {
+        let color = if distance == 1 { UNIT_RING_COLOR } else { RING_COLOR };
+        gizmos.circle_2d(center, distance as f32 / scale, color);
+    }
+}
+
+///
+/// 音源から両耳へ線を引く（その耳に届く音量が小さいほど薄くする）
+///
+fn draw_ear_lines(
+    mut gizmos: Gizmos,
+    listener: Single<(&GlobalTransform, &SpatialListener)>,
+    emitter_query: Query<(&GlobalTransform, Option<&Attenuation>, &Occlusion), With<Emitter>>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+) {
+    let (listener_transform, listener) = *listener;
+    let ears = ear_positions(listener_transform, listener);
+    let scale = default_spatial_scale.0 .0;
+    for (emitter_transform, attenuation, occlusion) in &emitter_query {
+        let gains = emitter_ear_gains(attenuation, emitter_transform, listener_transform, listener, scale);
+        for ((ear, gain), color) in ears.iter().zip(gains).zip(EAR_COLORS) {
+            let alpha = (gain * occlusion.0).clamp(EAR_LINE_MIN_ALPHA, 1.0);
+            gizmos.line_2d(emitter_transform.translation().truncate(), ear.truncate(), color.with_alpha(alpha));
+        }
+    }
+}
+
+///
+/// 耳ごとに，全ての音源から届く音量の合計を表示する
+///
+fn update_meter(
+    listener: Single<(&GlobalTransform, &SpatialListener)>,
+    // Occlusion は音源にしか付かない
+    emitter_query: Query<(&GlobalTransform, &SpatialAudioSink, Option<&Attenuation>, &Occlusion, &AudioBus)>,
+    mut bar_query: Query<(&mut Node, &MeterBar)>,
+    mut meter_text: Single<&mut Text, With<MeterText>>,
+    default_spatial_scale: Res<DefaultSpatialScale>,
+    mixer: Res<AudioMixer>,
+    ducking_gain: Res<DuckingGain>,
+) {
+    let (listener_transform, listener) = *listener;
+    let scale = default_spatial_scale.0 .0;
+    let mut volumes = [0.0; 2];
+    // シンクの音量は独自の減衰と壁による減衰とミキサーの音量を掛けたものなので，それらの倍率だけで求まる
+    for (emitter_transform, sink, attenuation, occlusion, bus) in &emitter_query {
+        if sink.is_paused() {
+            continue;
+        }
+        let gains = emitter_ear_gains(attenuation, emitter_transform, listener_transform, listener, scale);
+        let bus_gain = mixer.gain(*bus, &ducking_gain);
+        for (volume, gain) in volumes.iter_mut().zip(gains) {
+            *volume += gain * occlusion.0 * bus_gain;
+        }
+    }
+
+    for (mut node, bar) in &mut bar_query {
+        node.width = Val::Percent(volumes[bar.0].min(1.0) * 100.0);
+    }
+    meter_text.0 = format!(
+        "Spatial Scale: {:.4}\nLeft: {:.2}  Right: {:.2}",
+        scale.x, volumes[0], volumes[1]
+    );
+}
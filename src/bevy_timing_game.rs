@@ -1,9 +1,28 @@
+mod achievements;
+mod background;
+mod beatmap;
+mod chart;
+mod gauge;
+mod ghost;
+mod loading;
+mod menu;
+mod online;
+mod results;
+mod tempo;
+mod tutorial;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::env;
 use bevy::prelude::*;
-use bevy::input::keyboard::KeyboardInput;
 use bevy::sprite::Anchor;
 use bevy::window::WindowMode;
-use bevy::time::Time;
+use bevy::time::{Stopwatch, Time};
 use bevy::color::palettes::css;
+use chart::Chart;
+use crate::audio_mixer::{AudioBus, AudioMixer, AudioMixerPlugin};
+use crate::launcher::Demo;
+use results::{PlayerResult, SongResult};
 
 #[allow(unused)]
 // ウィンドウ設定
@@ -11,14 +30,15 @@ const WINDOW_SIZE: Vec2 = Vec2::new(800.0, 600.0);
 // ゲームの背景色
 const BG_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 
-// "Press Any Key..."
-const PRESS_ANY_KEY_FONT_SIZE: f32 = 50.0;
-const PRESS_ANY_KEY_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
-
 // Score Board
 const SCOREBOARD_FONT_SIZE: f32 = 40.0;
 const SCOREBOARD_COLOR: Color = Color::BLACK;
 
+// 2 人対戦時の設定
+// 1P は上段，2P は下段の譜面部分を使う
+const VERSUS_LANE_OFFSET: f32 = 120.0;
+const VERSUS_SCOREBOARD_MARGIN: f32 = 60.0;
+
 // "譜面部分" の設定
 const SLIDER_SIZE: Vec2 = Vec2::new(500.0, 50.0);
 const SLIDER_DEFAULT_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
@@ -38,6 +58,8 @@ const SLIDER_PERFECT_PADDING: f32 = (SLIDER_GOOD_RANGE - SLIDER_PERFECT_RANGE) /
 
 const SLIDER_RANGE_PADDING: f32 = 100.0;
 const NOTES_OK_RANGE_OFFSET: f32 = -(WINDOW_SIZE.x / 2.0) + SLIDER_RANGE_PADDING;
+// 判定ラインの位置（"可" の範囲の中心）
+const JUDGE_LINE_X: f32 = NOTES_OK_RANGE_OFFSET + SLIDER_GOOD_RANGE / 2.0;
 
 
 
@@ -47,36 +69,309 @@ const NOTES_OK_RANGE_OFFSET: f32 = -(WINDOW_SIZE.x / 2.0) + SLIDER_RANGE_PADDING
 
 // 音符
 const NOTE_SIZE: Vec2 = Vec2::new(24.0, 24.0);
+// 譜面の開始時の BPM での速度（スクロール速度が変わるとこれに倍率が掛かる）
 const NOTE_SPEED: f32 = -400.0;
 const NOTE_COLOR: Color = Color::Srgba(css::RED);
-const INITIAL_NOTE_DIRECTION: Vec2 = Vec2::new(0.5, -0.5);
+// ノーツの出現位置（画面右端の外側）
+const NOTE_SPAWN_X: f32 = WINDOW_SIZE.x / 2.0 + NOTE_SIZE.x;
+
+// 判定の時間幅（判定時刻の前後，秒）
+// 開始時の速度で "良"・"可" の範囲を通過する時間に合わせる
+const PERFECT_WINDOW: f32 = SLIDER_PERFECT_RANGE / 2.0 / -NOTE_SPEED;
+const GOOD_WINDOW: f32 = SLIDER_GOOD_RANGE / 2.0 / -NOTE_SPEED;
+
+// 打鍵音（キー音を持たないノーツ用）
+const HIT_SOUND: &str = "sounds/timing.ogg";
+// 最後のノーツから曲の終了までの余白（秒）
+const SONG_END_MARGIN: f32 = 2.0;
 
+// ベストスコアなどの保存先
+const SAVE_DIR: &str = "save";
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default, States)]
+// 遊ぶ譜面ファイル（.bms / .bme / .osu）を指定する環境変数
+pub const CHART_ENV_VAR: &str = "TIMING_GAME_CHART";
+
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default, SubStates)]
+#[source(Demo = Demo::TimingGame)]
 enum AppState {
     #[default]
     MainMenu,
+    Options,
+    Achievements,
+    Loading,
     PlayingGame,
+    Results,
+}
+
+///
+/// プレイモード（1 人プレイ / 2 人対戦 / 練習 / チュートリアル）
+/// 練習・チュートリアルではベストスコアを記録せず，Esc でタイトルに戻れる
+///
+#[derive(Resource, Clone, Copy, Eq, PartialEq, Debug, Default)]
+enum GameMode {
+    #[default]
+    Single,
+    Versus,
+    Practice,
+    Tutorial,
 }
 
-#[derive(Resource, Component)]
+impl GameMode {
+    ///
+    /// このモードで参加するプレイヤー
+    ///
+    fn players(self) -> &'static [PlayerSide] {
+        match self {
+            GameMode::Single | GameMode::Practice | GameMode::Tutorial => &[PlayerSide::One],
+            GameMode::Versus => &[PlayerSide::One, PlayerSide::Two],
+        }
+    }
+}
+
+///
+/// プレイヤー（1P / 2P）
+/// プレイヤーごとのエンティティ・ノーツ・スコア表示に付ける
+///
+#[derive(Component, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+enum PlayerSide {
+    One,
+    Two,
+}
+
+impl PlayerSide {
+    ///
+    /// 表示名
+    ///
+    fn label(self) -> &'static str {
+        match self {
+            PlayerSide::One => "1P",
+            PlayerSide::Two => "2P",
+        }
+    }
+
+    ///
+    /// 譜面部分の y 座標
+    ///
+    fn lane_y(self, game_mode: GameMode) -> f32 {
+        match (game_mode, self) {
+            (GameMode::Single | GameMode::Practice | GameMode::Tutorial, _) => 0.0,
+            (GameMode::Versus, PlayerSide::One) => VERSUS_LANE_OFFSET,
+            (GameMode::Versus, PlayerSide::Two) => -VERSUS_LANE_OFFSET,
+        }
+    }
+
+    ///
+    /// スコアボードの表示位置（画面上端からの距離）
+    ///
+    fn scoreboard_top(self, game_mode: GameMode) -> f32 {
+        match game_mode {
+            GameMode::Single | GameMode::Practice | GameMode::Tutorial => 0.0,
+            GameMode::Versus => {
+                WINDOW_SIZE.y / 2.0 - self.lane_y(game_mode) - SLIDER_SIZE.y / 2.0 - VERSUS_SCOREBOARD_MARGIN
+            }
+        }
+    }
+
+    ///
+    /// 打鍵の判定
+    /// 1P はスペースキー，2P はエンターキーまたはゲームパッドの South ボタン
+    ///
+    fn just_hit(self, keyboard_input: &ButtonInput<KeyCode>, gamepads: &Query<&Gamepad>) -> bool {
+        match self {
+            PlayerSide::One => keyboard_input.just_pressed(KeyCode::Space),
+            PlayerSide::Two => {
+                keyboard_input.just_pressed(KeyCode::Enter)
+                    || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::South))
+            }
+        }
+    }
+}
+
+///
+/// プレイヤーごとのスコア
+/// プレイヤーのエンティティとスコア表示用の TextSpan に付ける
+///
+#[derive(Component)]
 struct ScoreBoard {
     score: isize,
 }
 
-#[derive(Component)]
-struct PressAnyKey;
+///
+/// プレイヤーごとの判定の回数
+/// * perfect_streak : usize         現在の "良" の連続数
+/// * max_perfect_streak : usize     曲の中での "良" の最大連続数
+///
+#[derive(Component, Clone, Copy, Debug, Default)]
+struct Judgements {
+    perfect: usize,
+    good: usize,
+    miss: usize,
+    perfect_streak: usize,
+    max_perfect_streak: usize,
+}
+
+///
+/// プレイヤーごとの打鍵した時刻（曲の開始からのミリ秒）
+/// リーダーボードに送るリプレイのチェックサムの計算に使う
+///
+#[derive(Component, Default)]
+struct InputLog(Vec<u32>);
 
 #[derive(Component)]
 struct NOTE;
 
-#[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
+///
+/// ノーツの判定時刻と見た目の位置
+/// * time : f32        曲の開始からの判定時刻（秒）
+/// * scroll : f64      テンポマップ上のスクロール位置
+///
+#[derive(Component)]
+struct NoteTiming {
+    time: f32,
+    scroll: f64,
+}
+
+///
+/// ノーツ固有のキー音
+///
+#[derive(Component)]
+struct KeySound(Handle<AudioSource>);
+
+///
+/// オプション画面で変更できる設定
+/// * scroll_speed : f32       ノーツの見た目の速度の倍率（判定の時間幅は変わらない）
+/// * background_dim : f32     背景のビジュアライザの暗さ（0.0 でそのまま，1.0 で非表示）
+/// * gauge : GaugeType        1 人プレイのゲージ
+/// * note_skin : NoteSkin     ノーツの見た目（実績で解放）
+/// * menu_sound : bool        メニューの効果音を鳴らすか
+/// * music_volume : f32       BGM の音量（ミキサーの Music バス）
+/// * sfx_volume : f32         打鍵音・キー音の音量（ミキサーの SFX バス）
+///
+#[derive(Resource)]
+struct Settings {
+    scroll_speed: f32,
+    background_dim: f32,
+    gauge: GaugeType,
+    note_skin: NoteSkin,
+    menu_sound: bool,
+    music_volume: f32,
+    sfx_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            scroll_speed: 1.0,
+            background_dim: 0.25,
+            gauge: GaugeType::Normal,
+            note_skin: NoteSkin::Classic,
+            menu_sound: true,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+        }
+    }
+}
+
+///
+/// ゲージの種類
+/// HARD では見逃しでゲージが減り，なくなるとその場で曲が終わる（途中終了）
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum GaugeType {
+    Normal,
+    Hard,
+}
+
+///
+/// ノーツの見た目
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum NoteSkin {
+    Classic,
+    Ocean,
+    Gold,
+}
+
+impl NoteSkin {
+    const ALL: [NoteSkin; 3] = [NoteSkin::Classic, NoteSkin::Ocean, NoteSkin::Gold];
+
+    ///
+    /// 表示名
+    ///
+    fn label(self) -> &'static str {
+        match self {
+            NoteSkin::Classic => "Classic",
+            NoteSkin::Ocean => "Ocean",
+            NoteSkin::Gold => "Gold",
+        }
+    }
+
+    ///
+    /// ノーツの色
+    ///
+    fn color(self) -> Color {
+        match self {
+            NoteSkin::Classic => NOTE_COLOR,
+            NoteSkin::Ocean => Color::Srgba(css::DEEP_SKY_BLUE),
+            NoteSkin::Gold => Color::Srgba(css::ORANGE),
+        }
+    }
+}
+
+///
+/// HARD ゲージがなくなり，曲が途中で終わったか
+///
+#[derive(Resource, Default)]
+struct SongFailed(bool);
+
+///
+/// チュートリアルなどで一時的に譜面を差し替えている間，退避しておく元の譜面
+/// タイトル画面に戻ると元に戻す
+///
+#[derive(Resource)]
+struct SavedChart(Chart);
+
+///
+/// 譜面を差し替え，元の譜面を退避する
+///
+fn swap_chart(commands: &mut Commands, chart: &mut Chart, replacement: Chart) {
+    commands.insert_resource(SavedChart(std::mem::replace(chart, replacement)));
+}
+
+///
+/// 曲の開始からの経過時間
+///
+#[derive(Resource, Default)]
+struct SongClock(Stopwatch);
+
+///
+/// 次に出現させる譜面上のノーツの位置
+///
+#[derive(Resource, Default)]
+struct NoteCursor(usize);
+
+///
+/// 次に再生する譜面上の BGM の位置
+///
+#[derive(Resource, Default)]
+struct BgmCursor(usize);
+
+///
+/// 曲の開始前に読み込んだキー音・BGM
+/// ハンドルを保持しておくことで，打鍵時に読み込みが発生しないようにする
+///
+#[derive(Resource, Default)]
+struct KeySoundBank {
+    sounds: HashMap<String, Handle<AudioSource>>,
+    hit_sound: Handle<AudioSource>,
+    // キー音モードでは空振り時に音を鳴らさない
+    keysound_mode: bool,
+}
 
 ///
 /// タイミングゲームの実行
 ///
-#[allow(unused)]
 pub fn play_game() {
     // ウィンドウ設定
     let window_plugin = WindowPlugin {
@@ -92,51 +387,99 @@ pub fn play_game() {
     // タイミングゲームの起動
     App::new()
         .add_plugins(DefaultPlugins.set(window_plugin))
-        .init_state::<AppState>()
-        .insert_resource(ClearColor(BG_COLOR))
-        .insert_resource(Time::<Fixed>::from_seconds(1.0 / 60.0))
-        .insert_resource(ScoreBoard { score: 0 })
-        .add_systems(Startup, setup)
-        .add_systems(OnEnter(AppState::MainMenu), setup_title_screen)
-        .add_systems(OnEnter(AppState::PlayingGame), setup_play_game_screen)
-        .add_systems(Update, switch_state)
-        .add_systems(Update, press_any_key.run_if(in_state(AppState::MainMenu)))
-        .add_systems(Update, decide_timing.run_if(in_state(AppState::PlayingGame)))
-        .add_systems(Update, apply_velocity.run_if(in_state(AppState::PlayingGame)))
-        .add_systems(Update, update_scoreboard.run_if(in_state(AppState::PlayingGame)))
+        .insert_state(Demo::TimingGame)
+        .enable_state_scoped_entities::<Demo>()
+        .add_plugins((AudioMixerPlugin::default(), TimingGamePlugin))
         .run();
 }
 
 ///
-/// タイミングゲームのセットアップ
-/// 必要な bundle を生成する
+/// タイミングゲーム（Demo::TimingGame の間だけ動く）
+/// AudioMixerPlugin と Demo の状態はアプリ側で追加しておく
 ///
-fn setup (mut commands: Commands) {
-    // カメラ（画面描画用）を生成
-    commands.spawn(Camera2d::default());
+pub struct TimingGamePlugin;
+
+impl Plugin for TimingGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<AppState>()
+            .insert_resource(load_chart().unwrap_or_else(|(path, e)| {
+                eprintln!("Problem loading {} : {} (playing the demo chart instead)", path, e);
+                Chart::demo()
+            }))
+            .init_resource::<GameMode>()
+            .init_resource::<SongResult>()
+            .init_resource::<Settings>()
+            .enable_state_scoped_entities::<AppState>()
+            .add_systems(OnEnter(Demo::TimingGame), (setup, apply_volume_settings))
+            .add_systems(OnEnter(AppState::PlayingGame), (setup_play_game_screen, start_song))
+            .add_systems(OnEnter(AppState::MainMenu), restore_chart.run_if(resource_exists::<SavedChart>))
+            .add_systems(OnEnter(AppState::Results), results::setup_results_screen)
+            .add_systems(
+                Update,
+                (switch_state, apply_volume_settings.run_if(resource_changed::<Settings>))
+                    .run_if(in_state(Demo::TimingGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    tick_song_clock,
+                    play_bgm,
+                    spawn_notes,
+                    update_note_positions,
+                    decide_timing,
+                    despawn_missed_notes,
+                    update_scoreboard,
+                    // チュートリアルは台本の最後で終わる
+                    finish_song.run_if(not(resource_equals(GameMode::Tutorial))),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::PlayingGame)),
+            )
+            .add_plugins((
+                menu::MenuPlugin,
+                loading::LoadingPlugin,
+                background::BackgroundPlugin,
+                ghost::GhostPlugin,
+                online::LeaderboardPlugin,
+                tutorial::TutorialPlugin,
+                gauge::GaugePlugin,
+                achievements::AchievementsPlugin,
+            ));
+    }
 }
 
 ///
-/// MainMenu 遷移時のセットアップ関数
+/// 譜面の読み込み（指定がなければデモ譜面）
+/// ランチャーから起動した時は他のデモも動いているので，読めない譜面でもプロセスは終了させずにエラーを返す
+/// ### Return
+/// * chart : Chart     遊ぶ譜面（読めなければ譜面のパスとエラー）
+///
+fn load_chart() -> Result<Chart, (String, beatmap::BeatmapError)> {
+    let Ok(path) = env::var(CHART_ENV_VAR) else {
+        return Ok(Chart::demo());
+    };
+    let beatmap = beatmap::load_beatmap(Path::new(&path)).map_err(|e| (path, e))?;
+    // 対応していない機能は無視して遊べるようにするが，何を無視したかは知らせる
+    for warning in &beatmap.warnings {
+        eprintln!("Warning : {}", warning);
+    }
+    Ok(beatmap.chart)
+}
+
+///
+/// タイミングゲームのセットアップ
 /// 必要な bundle を生成する
 ///
-fn setup_title_screen (mut commands: Commands, asset_server: Res<AssetServer>) {
-    // "Press Any Key" の文字を表示する
+fn setup (mut commands: Commands) {
+    // カメラ（画面描画用）を生成
+    // 背景色は他のデモと共通の ClearColor ではなくカメラに持たせる
     commands.spawn((
-        Text::new("Press Any Key ..."),
-        TextFont {
-            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-            font_size: PRESS_ANY_KEY_FONT_SIZE,
+        Camera2d,
+        Camera {
+            clear_color: ClearColorConfig::Custom(BG_COLOR),
             ..default()
         },
-        TextColor(PRESS_ANY_KEY_COLOR.into()),
-        TextLayout::new_with_justify(JustifyText::Center),
-        // Node {
-        //     display: Display::None,
-        //     position_type: PositionType::Absolute,
-        //     ..default()
-        // },
-        PressAnyKey,
+        StateScoped(Demo::TimingGame),
     ));
 }
 
@@ -144,16 +487,38 @@ fn setup_title_screen (mut commands: Commands, asset_server: Res<AssetServer>) {
 /// PlayingGame 遷移時のセットアップ関数
 /// 必要な bundle を生成する
 ///
-fn setup_play_game_screen (mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_play_game_screen (
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_mode: Res<GameMode>,
+) {
+    for &player_side in game_mode.players() {
+        spawn_play_field(&mut commands, &asset_server, *game_mode, player_side);
+    }
+}
+
+///
+/// プレイヤー 1 人分の譜面部分とスコアボードを生成する
+///
+fn spawn_play_field (
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    game_mode: GameMode,
+    player_side: PlayerSide,
+) {
+    let lane_y = player_side.lane_y(game_mode);
+
     // スライダーの生成
-    commands.spawn(
+    commands.spawn((
         Sprite {
             color: SLIDER_DEFAULT_COLOR,
-            custom_size: Some(Vec2::new(WINDOW_SIZE.x, SLIDER_SIZE.y).into()),
+            custom_size: Some(Vec2::new(WINDOW_SIZE.x, SLIDER_SIZE.y)),
             anchor: Anchor::Center,
             ..default()
         },
-    );
+        Transform::from_xyz(0.0, lane_y, 0.0),
+        StateScoped(AppState::PlayingGame),
+    ));
 
     // // デバッグ用
     // [
@@ -192,25 +557,19 @@ fn setup_play_game_screen (mut commands: Commands, asset_server: Res<AssetServer
                 },
                 // PERFECT : -300 + (24 / 2) + 0 = -300 + 12 = -288
                 // GOOD    : -300 + (40 / 2) + 8 = -300 + 28 = -272
-                Transform::from_xyz(NOTES_OK_RANGE_OFFSET + (range / 2.0) + padding, 0.0, *pos_z),
+                Transform::from_xyz(NOTES_OK_RANGE_OFFSET + (range / 2.0) + padding, lane_y, *pos_z),
+                StateScoped(AppState::PlayingGame),
             ));
         });
 
-    // ノーツの生成
-    commands.spawn((
-        Sprite{
-            color: NOTE_COLOR,
-            custom_size: Some(NOTE_SIZE),
-            ..default()
-        },
-        Transform::from_xyz(WINDOW_SIZE.x / 2.0 - 100.0, 0.0, 3.0),
-        Velocity(INITIAL_NOTE_DIRECTION.normalize() * NOTE_SPEED),
-        NOTE,
-    ));
-
     // スコアボードの生成
+    let label = match game_mode {
+        GameMode::Single | GameMode::Tutorial => "Score: ".to_string(),
+        GameMode::Practice => "Practice Score: ".to_string(),
+        GameMode::Versus => format!("{} Score: ", player_side.label()),
+    };
     commands.spawn((
-        Text::new("Score: "),
+        Text::new(label),
         TextFont {
             font: asset_server.load("fonts/FiraSans-Bold.ttf"),
             font_size: SCOREBOARD_FONT_SIZE,
@@ -218,6 +577,12 @@ fn setup_play_game_screen (mut commands: Commands, asset_server: Res<AssetServer
         },
         TextColor(SCOREBOARD_COLOR.into()),
         TextLayout::new_with_justify(JustifyText::Left),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(player_side.scoreboard_top(game_mode)),
+            ..default()
+        },
+        StateScoped(AppState::PlayingGame),
     ))
     .with_child((
         TextSpan::new("0"),
@@ -228,23 +593,202 @@ fn setup_play_game_screen (mut commands: Commands, asset_server: Res<AssetServer
         },
         TextColor(SCOREBOARD_COLOR.into()),
         ScoreBoard { score: 0 },
+        player_side,
     ));
 }
 
 ///
-/// キー押下イベントハンドラ
-/// AppState が MainMenu の状態で使用
+/// 曲の開始
+/// プレイヤーと経過時間を初期化する（キー音・BGM は Loading で読み込み済み）
+///
+fn start_song (mut commands: Commands, game_mode: Res<GameMode>) {
+    // プレイヤーごとのスコアを用意する
+    for &player_side in game_mode.players() {
+        commands.spawn((
+            player_side,
+            ScoreBoard { score: 0 },
+            Judgements::default(),
+            InputLog::default(),
+            StateScoped(AppState::PlayingGame),
+        ));
+    }
+    commands.insert_resource(SongClock::default());
+    commands.insert_resource(NoteCursor::default());
+    commands.insert_resource(BgmCursor::default());
+    commands.insert_resource(SongFailed::default());
+}
+
+///
+/// タイトル画面に戻ったら，差し替えていた譜面を元に戻す
+///
+fn restore_chart (mut commands: Commands, mut chart: ResMut<Chart>, mut saved_chart: ResMut<SavedChart>) {
+    *chart = std::mem::take(&mut saved_chart.0);
+    commands.remove_resource::<SavedChart>();
+}
+
+///
+/// 曲の経過時間を進める
+///
+fn tick_song_clock (mut song_clock: ResMut<SongClock>, time: Res<Time>) {
+    song_clock.0.tick(time.delta());
+}
+
+///
+/// 設定の音量をミキサーのバスに反映する
+/// キー音は曲の一部なので，打鍵のたびに BGM を下げるダッキングは使わない
+///
+fn apply_volume_settings (settings: Res<Settings>, mut mixer: ResMut<AudioMixer>) {
+    mixer.ducking = None;
+    mixer.bus_mut(AudioBus::Music).volume = settings.music_volume;
+    mixer.bus_mut(AudioBus::Sfx).volume = settings.sfx_volume;
+}
+
+///
+/// 譜面に従って BGM を再生する
+///
+fn play_bgm (
+    mut commands: Commands,
+    mut bgm_cursor: ResMut<BgmCursor>,
+    song_clock: Res<SongClock>,
+    chart: Res<Chart>,
+    key_sound_bank: Res<KeySoundBank>,
+) {
+    let now = song_clock.0.elapsed_secs();
+    while let Some(bgm_event) = chart.bgm.get(bgm_cursor.0) {
+        if bgm_event.time > now {
+            break;
+        }
+        bgm_cursor.0 += 1;
+        if let Some(handle) = key_sound_bank.sounds.get(&bgm_event.sound) {
+            commands.spawn((
+                AudioPlayer::new(handle.clone()),
+                PlaybackSettings::DESPAWN,
+                AudioBus::Music,
+                StateScoped(AppState::PlayingGame),
+            ));
+        }
+    }
+}
+
+///
+/// 1 拍あたりのスクロール量（ピクセル）
+/// 譜面の開始時の BPM で NOTE_SPEED に設定の倍率を掛けた速度になるようにする
+///
+fn pixels_per_scroll(chart: &Chart, settings: &Settings) -> f64 {
+    -NOTE_SPEED as f64 * settings.scroll_speed as f64 * 60.0 / chart.tempo.initial_bpm()
+}
+
+///
+/// 現在のスクロール位置から見たノーツの x 座標
+///
+fn note_x(chart: &Chart, settings: &Settings, note_scroll: f64, current_scroll: f64) -> f32 {
+    JUDGE_LINE_X + ((note_scroll - current_scroll) * pixels_per_scroll(chart, settings)) as f32
+}
+
+///
+/// 譜面に従ってノーツを生成する
+/// 画面右端に入る位置までスクロールしてきたノーツを出現させる
+///
+fn spawn_notes (
+    mut commands: Commands,
+    mut note_cursor: ResMut<NoteCursor>,
+    song_clock: Res<SongClock>,
+    chart: Res<Chart>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    key_sound_bank: Res<KeySoundBank>,
+) {
+    let current_scroll = chart.tempo.time_to_scroll(song_clock.0.elapsed_secs() as f64);
+    while let Some(chart_note) = chart.notes.get(note_cursor.0) {
+        let note_scroll = chart.tempo.time_to_scroll(chart_note.time as f64);
+        let spawn_x = note_x(&chart, &settings, note_scroll, current_scroll);
+        if spawn_x > NOTE_SPAWN_X {
+            break;
+        }
+        note_cursor.0 += 1;
+
+        let key_sound = chart_note.keysound.as_ref().and_then(|path| key_sound_bank.sounds.get(path));
+
+        // 全てのプレイヤーに同じノーツを流す
+        for &player_side in game_mode.players() {
+            let mut note = commands.spawn((
+                Sprite{
+                    color: settings.note_skin.color(),
+                    custom_size: Some(NOTE_SIZE),
+                    ..default()
+                },
+                Transform::from_xyz(spawn_x, player_side.lane_y(*game_mode), 3.0),
+                NoteTiming {
+                    time: chart_note.time,
+                    scroll: note_scroll,
+                },
+                NOTE,
+                player_side,
+                StateScoped(AppState::PlayingGame),
+            ));
+            if let Some(handle) = key_sound {
+                note.insert(KeySound(handle.clone()));
+            }
+        }
+    }
+}
+
+///
+/// 判定時間を過ぎたノーツを見逃しとして消去する
 ///
-fn press_any_key (
-    mut key_board_event: EventReader<KeyboardInput>,
-    press_any_key_query: Query<Entity, With<PressAnyKey>>,
+fn despawn_missed_notes (
     mut commands: Commands,
-    mut inkey: ResMut<ButtonInput<KeyCode>>
+    song_clock: Res<SongClock>,
+    note_query: Query<(Entity, &PlayerSide, &NoteTiming), With<NOTE>>,
+    mut player_query: Query<(&PlayerSide, &mut Judgements)>,
 ) {
-    for _event in key_board_event.read() {
-        let press_any_key_entity = press_any_key_query.single();
-        commands.entity(press_any_key_entity).despawn();
-        inkey.reset_all();
+    let now = song_clock.0.elapsed_secs();
+    for (entity, note_side, note_timing) in &note_query {
+        if now > note_timing.time + GOOD_WINDOW {
+            commands.entity(entity).despawn();
+            for (player_side, mut judgements) in &mut player_query {
+                if player_side == note_side {
+                    judgements.miss += 1;
+                    judgements.perfect_streak = 0;
+                }
+            }
+        }
+    }
+}
+
+///
+/// 全てのノーツが流れ終わったら（HARD ゲージがなくなったらその場で）結果画面に移る
+///
+#[allow(clippy::too_many_arguments)]
+fn finish_song (
+    mut next_state: ResMut<NextState<AppState>>,
+    mut song_result: ResMut<SongResult>,
+    song_clock: Res<SongClock>,
+    note_cursor: Res<NoteCursor>,
+    chart: Res<Chart>,
+    game_mode: Res<GameMode>,
+    song_failed: Res<SongFailed>,
+    note_query: Query<(), With<NOTE>>,
+    player_query: Query<(&PlayerSide, &ScoreBoard, &Judgements, &InputLog), Without<TextSpan>>,
+) {
+    let all_spawned = note_cursor.0 >= chart.notes.len();
+    let song_over = song_clock.0.elapsed_secs() > chart.last_note_time() + SONG_END_MARGIN;
+    if song_failed.0 || (all_spawned && note_query.is_empty() && song_over) {
+        // プレイヤーのエンティティは PlayingGame を抜けると消えるので，結果を控えておく
+        *song_result = SongResult {
+            game_mode: *game_mode,
+            failed: song_failed.0,
+            players: player_query
+                .iter()
+                .map(|(player_side, score_board, judgements, input_log)| PlayerResult {
+                    player_side: *player_side,
+                    score: score_board.score,
+                    judgements: *judgements,
+                    replay_checksum: study_rust::leaderboard::replay_checksum(&input_log.0),
+                })
+                .collect(),
+        };
+        next_state.set(AppState::Results);
     }
 }
 
@@ -253,13 +797,25 @@ fn press_any_key (
 ///
 fn switch_state (
     mut next_state: ResMut<NextState<AppState>>,
+    game_mode: Res<GameMode>,
     key_input: Res<ButtonInput<KeyCode>>,
     current_state: Res<State<AppState>>,
 ) {
-    if key_input.just_pressed(KeyCode::Space) {
-        if *current_state.get() == AppState::MainMenu {
-                next_state.set(AppState::PlayingGame);
+    match *current_state.get() {
+        AppState::Results => {
+            if key_input.just_pressed(KeyCode::Space) {
+                next_state.set(AppState::MainMenu);
+            }
+        }
+        // 練習・チュートリアルの途中でタイトルに戻れる
+        AppState::PlayingGame => {
+            let can_quit = matches!(*game_mode, GameMode::Practice | GameMode::Tutorial);
+            if can_quit && key_input.just_pressed(KeyCode::Escape) {
+                next_state.set(AppState::MainMenu);
+            }
         }
+        // タイトル画面・オプション画面の操作は menu で行う
+        AppState::MainMenu | AppState::Options | AppState::Achievements | AppState::Loading => {}
     }
 }
 
@@ -268,66 +824,102 @@ fn switch_state (
 /// AppState が PlayingGame の状態で使用
 ///
 fn update_scoreboard (
-    score_board: Res<ScoreBoard>,
-    mut score_board_query: Query<&mut TextSpan, With<ScoreBoard>>,
+    player_query: Query<(&PlayerSide, &ScoreBoard), Without<TextSpan>>,
+    mut score_board_query: Query<(&PlayerSide, &mut TextSpan), With<ScoreBoard>>,
 ) {
-    // スコアボードのクエリアイテムを取得する．
-    let mut text_span = score_board_query.single_mut();
-    // スコアを更新する
-    **text_span = score_board.score.to_string();
+    for (player_side, score_board) in &player_query {
+        // プレイヤーに対応するスコアボードのクエリアイテムを取得する．
+        for (text_side, mut text_span) in &mut score_board_query {
+            if text_side == player_side {
+                // スコアを更新する
+                **text_span = score_board.score.to_string();
+            }
+        }
+    }
 }
 
 ///
-/// 速度を適用する
+/// ノーツの位置を更新する
+/// 曲の経過時間からテンポマップ上のスクロール位置を求めて配置する
 ///
-fn apply_velocity (
-    mut query: Query<(&mut Transform, &mut Velocity)>,
-    time_step: Res<Time<Fixed>>
+fn update_note_positions (
+    mut query: Query<(&mut Transform, &NoteTiming)>,
+    song_clock: Res<SongClock>,
+    chart: Res<Chart>,
+    settings: Res<Settings>,
 ) {
-    for (mut trans, mut velocity) in &mut query {
-        let trans_x = trans.translation.x;
-        let note_offset = NOTE_SIZE.x / 2.0;
-        if trans_x >= WINDOW_SIZE.x / 2.0 - note_offset || trans_x <= - WINDOW_SIZE.x / 2.0 + note_offset {
-            velocity.0 = -velocity.0;
-        }
-        trans.translation.x += velocity.x * time_step.delta().as_secs_f32();
+    let current_scroll = chart.tempo.time_to_scroll(song_clock.0.elapsed_secs() as f64);
+    for (mut trans, note_timing) in &mut query {
+        trans.translation.x = note_x(&chart, &settings, note_timing.scroll, current_scroll);
     }
 }
 
 ///
 /// キー入力時のタイミング判定
+/// プレイヤーごとに自分のノーツだけを判定する
 ///
 fn decide_timing (
     mut commands: Commands,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut score_board: ResMut<ScoreBoard>,
-    note_query: Query<&Transform, With<NOTE>>,
-    asset_server: Res<AssetServer>,
+    gamepads: Query<&Gamepad>,
+    mut player_query: Query<(&PlayerSide, &mut ScoreBoard, &mut Judgements, &mut InputLog), Without<TextSpan>>,
+    note_query: Query<(Entity, &PlayerSide, &NoteTiming, Option<&KeySound>), With<NOTE>>,
+    key_sound_bank: Res<KeySoundBank>,
+    song_clock: Res<SongClock>,
 ) {
-    // 音符位置の取得
-    let note_transform = note_query.single();
-
-    // スペースキーの入力があれば処理を行う
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        // キー押下イベントの通知
+    // 判定は見た目の位置ではなく，判定時刻とのずれで行う
+    // （停止やスクロール速度の変化があっても判定の幅が変わらないように）
+    let now = song_clock.0.elapsed_secs();
+
+    for (player_side, mut score_board, mut judgements, mut input_log) in &mut player_query {
+        // 打鍵があったプレイヤーだけ処理を行う
+        if !player_side.just_hit(&keyboard_input, &gamepads) {
+            continue;
+        }
+        input_log.0.push((now * 1000.0) as u32);
+
+        // "可" の時間内で，判定時刻に最も近いノーツを判定対象とする
+        let target = note_query
+            .iter()
+            .filter(|(_, note_side, _, _)| *note_side == player_side)
+            .filter(|(_, _, note_timing, _)| (note_timing.time - now).abs() < GOOD_WINDOW)
+            .min_by(|(_, _, a, _), (_, _, b, _)| {
+                let diff_a = (a.time - now).abs();
+                let diff_b = (b.time - now).abs();
+                diff_a.total_cmp(&diff_b)
+            });
+
+        let Some((note_entity, _, note_timing, key_sound)) = target else {
+            // 空振り：キー音モードでは音を鳴らさない
+            score_board.score += SLIDER_DEFAULT_POINTS;
+            if !key_sound_bank.keysound_mode {
+                commands.spawn((
+                    AudioPlayer::new(key_sound_bank.hit_sound.clone()),
+                    PlaybackSettings::DESPAWN,
+                    AudioBus::Sfx,
+                ));
+            }
+            continue;
+        };
+
+        // ノーツのキー音（なければ共通の打鍵音）を鳴らす
+        let sound = key_sound.map_or_else(|| key_sound_bank.hit_sound.clone(), |key_sound| key_sound.0.clone());
         commands.spawn((
-            AudioPlayer::new(asset_server.load("sounds/timing.ogg")),
+            AudioPlayer::new(sound),
+            PlaybackSettings::DESPAWN,
+            AudioBus::Sfx,
         ));
 
-        //
-        let note_transform_x = note_transform.translation.x;
-        let perfect_min = NOTES_OK_RANGE_OFFSET + SLIDER_PERFECT_PADDING;
-        let perfect_max = NOTES_OK_RANGE_OFFSET + SLIDER_PERFECT_PADDING + SLIDER_PERFECT_RANGE;
-        let good_min = NOTES_OK_RANGE_OFFSET;
-        let good_max = NOTES_OK_RANGE_OFFSET + SLIDER_GOOD_RANGE;
-
-        if perfect_min < note_transform_x && note_transform_x < perfect_max {
+        if (note_timing.time - now).abs() < PERFECT_WINDOW {
             score_board.score += SLIDER_PERFECT_POINTS;
-        } else if good_min < note_transform_x && note_transform_x < good_max {
-            score_board.score += SLIDER_GOOD_POINTS;
+            judgements.perfect += 1;
+            judgements.perfect_streak += 1;
+            judgements.max_perfect_streak = judgements.max_perfect_streak.max(judgements.perfect_streak);
         } else {
-            score_board.score += SLIDER_DEFAULT_POINTS;
+            score_board.score += SLIDER_GOOD_POINTS;
+            judgements.good += 1;
+            judgements.perfect_streak = 0;
         }
+        commands.entity(note_entity).despawn();
     }
-
-}
\ No newline at end of file
+}
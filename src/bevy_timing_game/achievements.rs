@@ -0,0 +1,479 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy::color::palettes::css;
+use super::{
+    decide_timing, swap_chart, AppState, GameMode, GaugeType, Judgements, NoteSkin, Settings, SAVE_DIR,
+};
+use super::chart::Chart;
+use crate::launcher::Demo;
+use super::results::SongResult;
+
+// 実績の保存先（SAVE_DIR の中）
+const ACHIEVEMENTS_FILE: &str = "achievements.txt";
+
+// 実績の条件
+const PERFECT_STREAK_GOAL: usize = 100;
+const SONGS_PLAYED_GOAL: usize = 10;
+
+// 解放の通知
+const TOAST_SECONDS: f32 = 3.0;
+const TOAST_FONT_SIZE: f32 = 20.0;
+const TOAST_TOP: f32 = 12.0;
+const TOAST_PADDING: f32 = 10.0;
+const TOAST_BG_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.85);
+const TOAST_COLOR: Color = Color::WHITE;
+
+// 実績一覧の画面
+const LIST_TITLE_FONT_SIZE: f32 = 40.0;
+const LIST_NAME_FONT_SIZE: f32 = 24.0;
+const LIST_DESCRIPTION_FONT_SIZE: f32 = 16.0;
+const LIST_UNLOCKED_COLOR: Color = Color::Srgba(css::DARK_GOLDENROD);
+const LIST_LOCKED_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+const LIST_TEXT_COLOR: Color = Color::BLACK;
+const LIST_ENTRY_MARGIN: f32 = 10.0;
+
+///
+/// 実績
+/// プレイの結果から解放し，ファイルに保存する
+/// 一部の実績はノーツの見た目やボーナス譜面を解放する
+///
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_achievements())
+            .init_resource::<ToastQueue>()
+            .add_systems(
+                OnExit(AppState::MainMenu),
+                swap_in_bonus_song.run_if(resource_exists::<BonusSongSelected>),
+            )
+            .add_systems(
+                Update,
+                check_perfect_streak
+                    .after(decide_timing)
+                    .run_if(in_state(AppState::PlayingGame).and(counts_for_achievements)),
+            )
+            .add_systems(OnEnter(AppState::Results), record_song_result.run_if(counts_for_achievements))
+            .add_systems(OnEnter(AppState::Achievements), setup_achievements_screen)
+            .add_systems(Update, leave_achievements_screen.run_if(in_state(AppState::Achievements)))
+            .add_systems(Update, show_toasts.run_if(in_state(Demo::TimingGame)));
+    }
+}
+
+///
+/// 実績の種類
+///
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+enum Achievement {
+    FullCombo,
+    PerfectStreak,
+    HardClear,
+    Regular,
+}
+
+impl Achievement {
+    const ALL: [Achievement; 4] = [
+        Achievement::FullCombo,
+        Achievement::PerfectStreak,
+        Achievement::HardClear,
+        Achievement::Regular,
+    ];
+
+    ///
+    /// 保存ファイルに書く名前
+    ///
+    fn id(self) -> &'static str {
+        match self {
+            Achievement::FullCombo => "full_combo",
+            Achievement::PerfectStreak => "perfect_streak",
+            Achievement::HardClear => "hard_clear",
+            Achievement::Regular => "regular",
+        }
+    }
+
+    ///
+    /// 表示名
+    ///
+    fn name(self) -> &'static str {
+        match self {
+            Achievement::FullCombo => "Full Combo",
+            Achievement::PerfectStreak => "Perfect Streak",
+            Achievement::HardClear => "Hard Clear",
+            Achievement::Regular => "Regular",
+        }
+    }
+
+    ///
+    /// 解放の条件
+    ///
+    fn description(self) -> String {
+        match self {
+            Achievement::FullCombo => "Finish a song without missing a note".to_string(),
+            Achievement::PerfectStreak => format!("Hit {} PERFECTs in a row", PERFECT_STREAK_GOAL),
+            Achievement::HardClear => "Clear a song with the HARD gauge".to_string(),
+            Achievement::Regular => format!("Play {} songs", SONGS_PLAYED_GOAL),
+        }
+    }
+
+    ///
+    /// 解放される内容
+    ///
+    fn reward(self) -> Option<Reward> {
+        match self {
+            Achievement::FullCombo => Some(Reward::Skin(NoteSkin::Ocean)),
+            Achievement::PerfectStreak => Some(Reward::Skin(NoteSkin::Gold)),
+            Achievement::HardClear => None,
+            Achievement::Regular => Some(Reward::BonusSong),
+        }
+    }
+}
+
+///
+/// 実績で解放される内容
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum Reward {
+    Skin(NoteSkin),
+    BonusSong,
+}
+
+impl Reward {
+    fn label(self) -> String {
+        match self {
+            Reward::Skin(skin) => format!("{} note skin", skin.label()),
+            Reward::BonusSong => "Bonus song".to_string(),
+        }
+    }
+}
+
+///
+/// 解放した実績と，実績の条件に使う記録
+///
+#[derive(Resource, Default)]
+pub struct Achievements {
+    unlocked: BTreeSet<Achievement>,
+    songs_played: usize,
+}
+
+impl Achievements {
+    ///
+    /// ノーツの見た目が使えるか
+    ///
+    pub(super) fn is_skin_unlocked(&self, skin: NoteSkin) -> bool {
+        skin == NoteSkin::Classic || self.is_reward_unlocked(Reward::Skin(skin))
+    }
+
+    ///
+    /// ボーナス譜面が遊べるか
+    ///
+    pub(super) fn is_bonus_song_unlocked(&self) -> bool {
+        self.is_reward_unlocked(Reward::BonusSong)
+    }
+
+    fn is_reward_unlocked(&self, reward: Reward) -> bool {
+        self.unlocked.iter().any(|achievement| achievement.reward() == Some(reward))
+    }
+
+    ///
+    /// 実績を解放する（新しく解放した場合は true）
+    ///
+    fn unlock(&mut self, achievement: Achievement, toast_queue: &mut ToastQueue) -> bool {
+        if !self.unlocked.insert(achievement) {
+            return false;
+        }
+        let reward = achievement
+            .reward()
+            .map_or(String::new(), |reward| format!("\nUnlocked: {}", reward.label()));
+        toast_queue.0.push_back(format!("Achievement: {}{}", achievement.name(), reward));
+        true
+    }
+
+    ///
+    /// テキストにする（1 行に 1 項目）
+    ///
+    fn to_text(&self) -> String {
+        let mut lines = vec![format!("songs_played {}", self.songs_played)];
+        lines.extend(self.unlocked.iter().map(|achievement| format!("unlocked {}", achievement.id())));
+        lines.join("\n") + "\n"
+    }
+
+    ///
+    /// to_text で作ったテキストを読み込む（読めない行は無視する）
+    ///
+    fn parse(text: &str) -> Self {
+        let mut achievements = Achievements::default();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("songs_played", count)) => achievements.songs_played = count.trim().parse().unwrap_or(0),
+                Some(("unlocked", id)) => {
+                    if let Some(achievement) = Achievement::ALL.into_iter().find(|a| a.id() == id.trim()) {
+                        achievements.unlocked.insert(achievement);
+                    }
+                }
+                _ => {}
+            }
+        }
+        achievements
+    }
+}
+
+///
+/// タイトル画面でボーナス譜面が選ばれた
+///
+#[derive(Resource)]
+pub struct BonusSongSelected;
+
+///
+/// 表示待ちの解放の通知
+///
+#[derive(Resource, Default)]
+struct ToastQueue(VecDeque<String>);
+
+///
+/// 表示中の解放の通知
+///
+#[derive(Component)]
+struct Toast(Timer);
+
+///
+/// 実績の保存ファイルのパス
+///
+fn achievements_path() -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(ACHIEVEMENTS_FILE)
+}
+
+///
+/// 保存済みの実績を読み込む（ファイルがなければ何も解放していない状態）
+///
+fn load_achievements() -> Achievements {
+    fs::read_to_string(achievements_path()).map_or_else(|_| Achievements::default(), |text| Achievements::parse(&text))
+}
+
+///
+/// 実績をファイルに保存する
+///
+fn save_achievements(achievements: &Achievements) {
+    let result = fs::create_dir_all(SAVE_DIR).and_then(|_| fs::write(achievements_path(), achievements.to_text()));
+    if let Err(e) = result {
+        warn!("failed to save achievements: {}", e);
+    }
+}
+
+///
+/// 実績の対象になるプレイか（練習・チュートリアルは対象外）
+///
+fn counts_for_achievements(game_mode: Res<GameMode>) -> bool {
+    matches!(*game_mode, GameMode::Single | GameMode::Versus)
+}
+
+///
+/// "良" の連続数の実績は，達成したらその場で通知する
+///
+fn check_perfect_streak (
+    mut achievements: ResMut<Achievements>,
+    mut toast_queue: ResMut<ToastQueue>,
+    judgements_query: Query<&Judgements>,
+) {
+    let reached = judgements_query
+        .iter()
+        .any(|judgements| judgements.max_perfect_streak >= PERFECT_STREAK_GOAL);
+    if reached && achievements.unlock(Achievement::PerfectStreak, &mut toast_queue) {
+        save_achievements(&achievements);
+    }
+}
+
+///
+/// Results 遷移時に曲の結果から実績を判定する
+///
+fn record_song_result (
+    mut achievements: ResMut<Achievements>,
+    mut toast_queue: ResMut<ToastQueue>,
+    song_result: Res<SongResult>,
+    settings: Res<Settings>,
+) {
+    achievements.songs_played += 1;
+    let mut earned = Vec::new();
+    for player in &song_result.players {
+        let judgements = &player.judgements;
+        if !song_result.failed && judgements.miss == 0 && judgements.perfect + judgements.good > 0 {
+            earned.push(Achievement::FullCombo);
+        }
+        if judgements.max_perfect_streak >= PERFECT_STREAK_GOAL {
+            earned.push(Achievement::PerfectStreak);
+        }
+    }
+    let hard_gauge = song_result.game_mode == GameMode::Single && settings.gauge == GaugeType::Hard;
+    if hard_gauge && !song_result.failed {
+        earned.push(Achievement::HardClear);
+    }
+    if achievements.songs_played >= SONGS_PLAYED_GOAL {
+        earned.push(Achievement::Regular);
+    }
+
+    for achievement in earned {
+        achievements.unlock(achievement, &mut toast_queue);
+    }
+    // 遊んだ曲数は毎回変わるので常に保存する
+    save_achievements(&achievements);
+}
+
+///
+/// ボーナス譜面に差し替える（タイトル画面に戻ると元の譜面に戻る）
+///
+fn swap_in_bonus_song (mut commands: Commands, mut chart: ResMut<Chart>) {
+    swap_chart(&mut commands, &mut chart, Chart::bonus());
+    commands.remove_resource::<BonusSongSelected>();
+}
+
+///
+/// 解放の通知を 1 つずつ画面上部に表示する
+/// 画面を切り替えても表示し続けるので，AppState の StateScoped は付けない（デモを抜けたら消す）
+///
+fn show_toasts (
+    mut commands: Commands,
+    mut toast_queue: ResMut<ToastQueue>,
+    mut toast_query: Query<(Entity, &mut Toast)>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+) {
+    if let Ok((entity, mut toast)) = toast_query.get_single_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    let Some(message) = toast_queue.0.pop_front() else {
+        return;
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                top: Val::Px(TOAST_TOP),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            GlobalZIndex(10),
+            Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+            StateScoped(Demo::TimingGame),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::all(Val::Px(TOAST_PADDING)),
+                        ..default()
+                    },
+                    BackgroundColor(TOAST_BG_COLOR),
+                ))
+                .with_child((
+                    Text::new(message),
+                    TextFont {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: TOAST_FONT_SIZE,
+                        ..default()
+                    },
+                    TextColor(TOAST_COLOR),
+                    TextLayout::new_with_justify(JustifyText::Center),
+                ));
+        });
+}
+
+///
+/// Achievements 遷移時のセットアップ関数
+/// 実績の一覧と解放状況を表示する
+///
+fn setup_achievements_screen (
+    mut commands: Commands,
+    achievements: Res<Achievements>,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_font = |font_size: f32| TextFont {
+        font: font.clone(),
+        font_size,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            StateScoped(AppState::Achievements),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Achievements"),
+                text_font(LIST_TITLE_FONT_SIZE),
+                TextColor(LIST_TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(LIST_ENTRY_MARGIN * 2.0)),
+                    ..default()
+                },
+            ));
+
+            for achievement in Achievement::ALL {
+                let unlocked = achievements.unlocked.contains(&achievement);
+                let (status, color) = if unlocked {
+                    ("[Unlocked] ", LIST_UNLOCKED_COLOR)
+                } else {
+                    ("[Locked] ", LIST_LOCKED_COLOR)
+                };
+                let reward = achievement
+                    .reward()
+                    .map_or(String::new(), |reward| format!("  (Reward: {})", reward.label()));
+                parent
+                    .spawn((
+                        Text::new(format!("{}{}\n", status, achievement.name())),
+                        text_font(LIST_NAME_FONT_SIZE),
+                        TextColor(color),
+                        TextLayout::new_with_justify(JustifyText::Center),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(LIST_ENTRY_MARGIN)),
+                            ..default()
+                        },
+                    ))
+                    .with_child((
+                        TextSpan::new(format!("{}{}", achievement.description(), reward)),
+                        text_font(LIST_DESCRIPTION_FONT_SIZE),
+                        TextColor(LIST_TEXT_COLOR),
+                    ));
+            }
+
+            parent.spawn((
+                Text::new(format!(
+                    "Songs played: {}\n\nEsc / Enter: Back to Title",
+                    achievements.songs_played
+                )),
+                text_font(LIST_DESCRIPTION_FONT_SIZE),
+                TextColor(LIST_LOCKED_COLOR),
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    margin: UiRect::top(Val::Px(LIST_ENTRY_MARGIN)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+///
+/// 実績一覧の画面からタイトル画面に戻る
+///
+fn leave_achievements_screen (
+    mut next_state: ResMut<NextState<AppState>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+) {
+    let keys = [KeyCode::Escape, KeyCode::Enter, KeyCode::Space];
+    if keyboard_input.any_just_pressed(keys) || mouse_input.just_pressed(MouseButton::Left) {
+        next_state.set(AppState::MainMenu);
+    }
+}
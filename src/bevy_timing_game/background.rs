@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::io::Cursor;
+use bevy::prelude::*;
+use bevy::audio::Source;
+use bevy::sprite::Anchor;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use super::{tick_song_clock, AppState, KeySoundBank, Settings, SongClock, WINDOW_SIZE};
+use super::chart::Chart;
+
+// ビジュアライザ用に間引いたモノラル波形のサンプリング周波数
+const VISUALIZER_SAMPLE_RATE: u32 = 11025;
+
+// スペクトラムバー
+// 1 フレームごとに曲の現在位置の SPECTRUM_WINDOW サンプルを周波数ごとに分解する
+const SPECTRUM_WINDOW: usize = 512;
+const SPECTRUM_BAR_COUNT: usize = 32;
+const SPECTRUM_MIN_FREQ: f32 = 60.0;
+const SPECTRUM_MAX_FREQ: f32 = 5000.0;
+const SPECTRUM_GAIN: f32 = 8.0;
+// バーが下がる速さ（1 秒あたりの最大の高さに対する割合）
+const SPECTRUM_FALL_SPEED: f32 = 2.0;
+const SPECTRUM_BAR_GAP: f32 = 4.0;
+const SPECTRUM_MAX_HEIGHT: f32 = 240.0;
+const SPECTRUM_COLOR: Color = Color::srgb(0.55, 0.65, 0.9);
+
+// 拍ごとに画面全体を光らせる
+const BEAT_PULSE_COLOR: Color = Color::srgb(1.0, 0.85, 0.5);
+const BEAT_PULSE_ALPHA: f32 = 0.35;
+
+// 譜面部分（z = 0 〜 3）より奥に描く
+const BACKGROUND_Z: f32 = -10.0;
+
+///
+/// 譜面部分の奥に表示する背景のビジュアライザ
+/// 曲の BGM をデコードしたスペクトラムバーと，テンポマップの拍に合わせた点滅を表示する
+/// 暗さはオプション画面の Background Dim で変更する
+///
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::PlayingGame), (setup_background, start_decoding_song))
+            .add_systems(
+                Update,
+                (
+                    receive_decoded_song.run_if(resource_exists::<DecodingSong>),
+                    update_beat_pulse,
+                    update_spectrum,
+                )
+                    .chain()
+                    .after(tick_song_clock)
+                    .run_if(in_state(AppState::PlayingGame)),
+            );
+    }
+}
+
+///
+/// デコード中の BGM
+///
+#[derive(Resource)]
+struct DecodingSong(Task<Vec<f32>>);
+
+///
+/// デコード済みの BGM
+/// 曲の開始時刻を 0 として，譜面の全ての BGM を VISUALIZER_SAMPLE_RATE のモノラルに混ぜたもの
+///
+#[derive(Resource)]
+struct SongWaveform(Vec<f32>);
+
+///
+/// スペクトラムバー
+/// * band : usize      周波数帯の番号（低い方から）
+/// * level : f32       現在の高さ（0.0 〜 1.0）
+///
+#[derive(Component)]
+struct SpectrumBar {
+    band: usize,
+    level: f32,
+}
+
+///
+/// 拍に合わせて点滅する背景
+///
+#[derive(Component)]
+struct BeatPulse;
+
+///
+/// PlayingGame 遷移時のセットアップ関数
+/// 背景の点滅とスペクトラムバーを生成する
+///
+fn setup_background (mut commands: Commands) {
+    commands.spawn((
+        Sprite {
+            color: BEAT_PULSE_COLOR.with_alpha(0.0),
+            custom_size: Some(WINDOW_SIZE),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, BACKGROUND_Z),
+        BeatPulse,
+        StateScoped(AppState::PlayingGame),
+    ));
+
+    let bar_width = (WINDOW_SIZE.x - SPECTRUM_BAR_GAP * (SPECTRUM_BAR_COUNT + 1) as f32) / SPECTRUM_BAR_COUNT as f32;
+    for band in 0..SPECTRUM_BAR_COUNT {
+        let x = -WINDOW_SIZE.x / 2.0 + SPECTRUM_BAR_GAP + bar_width / 2.0 + band as f32 * (bar_width + SPECTRUM_BAR_GAP);
+        commands.spawn((
+            Sprite {
+                color: SPECTRUM_COLOR.with_alpha(0.0),
+                custom_size: Some(Vec2::new(bar_width, 0.0)),
+                anchor: Anchor::BottomCenter,
+                ..default()
+            },
+            Transform::from_xyz(x, -WINDOW_SIZE.y / 2.0, BACKGROUND_Z + 1.0),
+            SpectrumBar { band, level: 0.0 },
+            StateScoped(AppState::PlayingGame),
+        ));
+    }
+}
+
+///
+/// 譜面の BGM のデコードを開始する
+/// 曲の長さによっては時間がかかるので，ゲームを止めないよう別スレッドで行う
+///
+fn start_decoding_song (
+    mut commands: Commands,
+    chart: Res<Chart>,
+    key_sound_bank: Res<KeySoundBank>,
+    audio_assets: Res<Assets<AudioSource>>,
+) {
+    // 前の曲の波形・デコード中のタスクは破棄する
+    commands.remove_resource::<SongWaveform>();
+    commands.remove_resource::<DecodingSong>();
+
+    let events: Vec<(f32, String)> = chart.bgm.iter().map(|bgm_event| (bgm_event.time, bgm_event.sound.clone())).collect();
+    let sources: HashMap<String, AudioSource> = events
+        .iter()
+        .filter_map(|(_, sound)| {
+            let handle = key_sound_bank.sounds.get(sound)?;
+            Some((sound.clone(), audio_assets.get(handle)?.clone()))
+        })
+        .collect();
+    if sources.is_empty() {
+        // BGM のない譜面（デモ譜面など）は拍の点滅だけを表示する
+        return;
+    }
+
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let decoded: HashMap<String, Vec<f32>> = sources
+            .into_iter()
+            .map(|(sound, source)| (sound, decode_mono(&source)))
+            .collect();
+        mix_bgm(&events, &decoded)
+    });
+    commands.insert_resource(DecodingSong(task));
+}
+
+///
+/// 音声をデコードし，VISUALIZER_SAMPLE_RATE のモノラル波形にする
+/// デコードできない形式の場合は空の波形を返す
+///
+fn decode_mono(source: &AudioSource) -> Vec<f32> {
+    // AudioSource::decoder は対応していない形式で panic するので，rodio で直接デコードしてエラーを受け取る
+    let Ok(decoder) = rodio::Decoder::new(Cursor::new(source.clone())) else {
+        return Vec::new();
+    };
+    let channels = decoder.channels().max(1) as usize;
+    let step = decoder.sample_rate() as f64 / VISUALIZER_SAMPLE_RATE as f64;
+
+    let samples: Vec<i16> = decoder.collect();
+    let frames: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().map(|sample| *sample as f32 / i16::MAX as f32).sum::<f32>() / channels as f32)
+        .collect();
+    (0..)
+        .map(|i| (i as f64 * step) as usize)
+        .take_while(|&index| index < frames.len())
+        .map(|index| frames[index])
+        .collect()
+}
+
+///
+/// 譜面の BGM を再生時刻に合わせて 1 つの波形に混ぜる
+///
+fn mix_bgm(events: &[(f32, String)], decoded: &HashMap<String, Vec<f32>>) -> Vec<f32> {
+    let offset_of = |time: f32| (time.max(0.0) * VISUALIZER_SAMPLE_RATE as f32) as usize;
+    let length = events
+        .iter()
+        .filter_map(|(time, sound)| decoded.get(sound).map(|samples| offset_of(*time) + samples.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut mixed = vec![0.0; length];
+    for (time, sound) in events {
+        if let Some(samples) = decoded.get(sound) {
+            let offset = offset_of(*time);
+            for (mixed_sample, sample) in mixed[offset..].iter_mut().zip(samples) {
+                *mixed_sample += sample;
+            }
+        }
+    }
+    mixed
+}
+
+///
+/// デコードが終わった BGM を受け取る
+///
+fn receive_decoded_song (mut commands: Commands, mut decoding_song: ResMut<DecodingSong>) {
+    if let Some(samples) = block_on(poll_once(&mut decoding_song.0)) {
+        commands.insert_resource(SongWaveform(samples));
+        commands.remove_resource::<DecodingSong>();
+    }
+}
+
+///
+/// 拍の頭で明るくなり，次の拍に向けて暗くなるように点滅させる
+///
+fn update_beat_pulse (
+    mut pulse_query: Query<&mut Sprite, With<BeatPulse>>,
+    song_clock: Res<SongClock>,
+    chart: Res<Chart>,
+    settings: Res<Settings>,
+) {
+    let beat = chart.tempo.time_to_beat(song_clock.0.elapsed_secs() as f64);
+    let pulse = (1.0 - beat.fract() as f32).powi(3);
+    for mut sprite in &mut pulse_query {
+        sprite.color = BEAT_PULSE_COLOR.with_alpha(BEAT_PULSE_ALPHA * pulse * (1.0 - settings.background_dim));
+    }
+}
+
+///
+/// 曲の現在位置の波形からスペクトラムバーの高さを更新する
+/// バーは音量が下がってもすぐには下がらず，少しずつ下がる
+///
+fn update_spectrum (
+    mut bar_query: Query<(&mut SpectrumBar, &mut Sprite)>,
+    song_waveform: Option<Res<SongWaveform>>,
+    song_clock: Res<SongClock>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let levels = song_waveform.map(|song_waveform| {
+        let start = (song_clock.0.elapsed_secs() * VISUALIZER_SAMPLE_RATE as f32) as usize;
+        let end = (start + SPECTRUM_WINDOW).min(song_waveform.0.len());
+        let window = song_waveform.0.get(start..end).unwrap_or(&[]);
+        spectrum(window)
+    });
+
+    let fall = SPECTRUM_FALL_SPEED * time.delta_secs();
+    for (mut bar, mut sprite) in &mut bar_query {
+        let target = levels.as_ref().map_or(0.0, |levels| levels[bar.band]);
+        bar.level = target.max(bar.level - fall);
+        sprite.color = SPECTRUM_COLOR.with_alpha(1.0 - settings.background_dim);
+        if let Some(size) = sprite.custom_size.as_mut() {
+            size.y = bar.level * SPECTRUM_MAX_HEIGHT;
+        }
+    }
+}
+
+///
+/// 波形を SPECTRUM_BAR_COUNT 個の周波数帯に分解し，それぞれの強さ（0.0 〜 1.0）を返す
+/// 周波数帯は低音から高音まで対数で等間隔に並べる
+///
+fn spectrum(window: &[f32]) -> [f32; SPECTRUM_BAR_COUNT] {
+    let mut levels = [0.0; SPECTRUM_BAR_COUNT];
+    if window.len() < 2 {
+        return levels;
+    }
+    for (band, level) in levels.iter_mut().enumerate() {
+        let ratio = band as f32 / (SPECTRUM_BAR_COUNT - 1) as f32;
+        let freq = SPECTRUM_MIN_FREQ * (SPECTRUM_MAX_FREQ / SPECTRUM_MIN_FREQ).powf(ratio);
+        *level = (band_magnitude(window, freq) * SPECTRUM_GAIN).sqrt().min(1.0);
+    }
+    levels
+}
+
+///
+/// 1 つの周波数の強さを求める（Goertzel 法，ハン窓を掛ける）
+///
+fn band_magnitude(window: &[f32], freq: f32) -> f32 {
+    let n = window.len();
+    let coeff = 2.0 * (TAU * freq / VISUALIZER_SAMPLE_RATE as f32).cos();
+    let (mut s1, mut s2) = (0.0, 0.0);
+    for (i, sample) in window.iter().enumerate() {
+        let hann = 0.5 - 0.5 * (TAU * i as f32 / (n - 1) as f32).cos();
+        let s0 = sample * hann + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    (s1 * s1 + s2 * s2 - coeff * s1 * s2).max(0.0).sqrt() / n as f32
+}
@@ -0,0 +1,154 @@
+///
+/// 既存の譜面ファイル（BMS / osu!mania）の読み込み
+///
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::chart::Chart;
+
+mod bms;
+mod osu;
+
+// AssetServer が読み込むディレクトリ
+const ASSET_DIR: &str = "assets";
+
+///
+/// 読み込んだ譜面
+/// * chart : Chart               タイミングゲームで遊べる形に変換した譜面
+/// * warnings : Vec<String>      対応していない機能など，変換時に無視した内容
+///
+pub struct Beatmap {
+    pub chart: Chart,
+    pub warnings: Vec<String>,
+}
+
+///
+/// 譜面の読み込みエラー
+///
+#[derive(Debug)]
+pub enum BeatmapError {
+    // ファイルが読めない
+    Io(io::Error),
+    // 拡張子から形式が判別できない
+    UnknownFormat(PathBuf),
+    // 書式の誤り
+    Parse { line: usize, message: String },
+    // 遊べない譜面（osu! の mania 以外のモード，ノーツがないなど）
+    Unsupported(String),
+}
+
+impl fmt::Display for BeatmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeatmapError::Io(e) => write!(f, "{}", e),
+            BeatmapError::UnknownFormat(path) => {
+                write!(f, "unknown beatmap format: {} (expected .bms, .bme or .osu)", path.display())
+            }
+            BeatmapError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            BeatmapError::Unsupported(message) => write!(f, "unsupported beatmap: {}", message),
+        }
+    }
+}
+
+impl Error for BeatmapError {}
+
+impl From<io::Error> for BeatmapError {
+    fn from(e: io::Error) -> Self {
+        BeatmapError::Io(e)
+    }
+}
+
+///
+/// 譜面ファイルを読み込む
+/// 形式は拡張子で判別する
+///
+pub fn load_beatmap(path: &Path) -> Result<Beatmap, BeatmapError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    // BMS は Shift_JIS のことが多いので，UTF-8 として読めない部分は置き換える
+    let bytes = fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut beatmap = match extension.as_deref() {
+        Some("bms") | Some("bme") => bms::parse(&text, base_dir)?,
+        Some("osu") => osu::parse(&text, base_dir)?,
+        _ => return Err(BeatmapError::UnknownFormat(path.to_path_buf())),
+    };
+    if beatmap.chart.notes.is_empty() {
+        return Err(BeatmapError::Unsupported("no playable notes".to_string()));
+    }
+    if beatmap.chart.title.is_empty() {
+        beatmap.chart.title = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    }
+    beatmap.chart.sort();
+    merge_chords(&mut beatmap);
+    Ok(beatmap)
+}
+
+///
+/// 譜面ファイルからの相対パスを，AssetServer で読み込めるパスに変換する
+/// assets ディレクトリの外にあるファイルや，存在しないファイルは警告を出して None を返す
+///
+fn to_asset_path(base_dir: &Path, file: &str, warnings: &mut Vec<String>) -> Option<String> {
+    let mut path = base_dir.join(file.replace('\\', "/"));
+
+    // BMS では .wav と書いて .ogg を置くことが多いので，見つからなければ .ogg も探す
+    if !path.exists() {
+        let ogg = path.with_extension("ogg");
+        if !ogg.exists() {
+            warnings.push(format!("sound file not found: {}", path.display()));
+            return None;
+        }
+        path = ogg;
+    }
+
+    // 絶対パス・相対パスのどちらで指定されても判定できるよう，正規化してから比べる
+    let asset_dir = fs::canonicalize(ASSET_DIR).unwrap_or_else(|_| PathBuf::from(ASSET_DIR));
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    let Ok(relative) = path.strip_prefix(&asset_dir) else {
+        warnings.push(format!(
+            "sound file is outside the {}/ directory and cannot be loaded: {}",
+            ASSET_DIR,
+            path.display()
+        ));
+        return None;
+    };
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+///
+/// 同時押し（同じ時刻の複数のノーツ）を 1 つのノーツにまとめる
+/// タイミングゲームは 1 レーンなので，先頭のノーツのキー音だけを残す
+///
+fn merge_chords(beatmap: &mut Beatmap) {
+    let before = beatmap.chart.notes.len();
+    beatmap.chart.notes.dedup_by(|b, a| (a.time - b.time).abs() < f32::EPSILON);
+    let merged = before - beatmap.chart.notes.len();
+    if merged > 0 {
+        beatmap.warnings.push(format!(
+            "{} simultaneous notes were merged because the timing game has a single lane",
+            merged
+        ));
+    }
+}
+
+///
+/// 同じ内容の警告は 1 度だけ出す
+///
+fn warn_once(warnings: &mut Vec<String>, message: String) {
+    if !warnings.contains(&message) {
+        warnings.push(message);
+    }
+}
@@ -0,0 +1,335 @@
+///
+/// BMS（.bms / .bme）形式の読み込み
+///
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use super::{to_asset_path, warn_once, Beatmap, BeatmapError};
+use crate::bevy_timing_game::chart::{BgmEvent, Chart, ChartNote};
+use crate::bevy_timing_game::tempo::{TempoEvent, TempoMap};
+
+// #BPM が指定されていない場合の BPM（BMS の慣例）
+const DEFAULT_BPM: f64 = 130.0;
+// 1 小節の拍数（#xxx02 で倍率を指定できる）
+const BEATS_PER_MEASURE: f64 = 4.0;
+// #STOP の単位（4 拍を 192 分割した長さ）
+const STOP_UNITS_PER_BEAT: f64 = 48.0;
+
+///
+/// チャンネル行のオブジェクト 1 つ分
+///
+struct BmsObject {
+    measure: u32,
+    position: f64,
+    channel: String,
+    value: String,
+}
+
+///
+/// BMS のテキストを譜面に変換する
+/// * text : &str          BMS ファイルの内容
+/// * base_dir : &Path     BMS ファイルのあるディレクトリ（#WAV の相対パスの基準）
+///
+pub fn parse(text: &str, base_dir: &Path) -> Result<Beatmap, BeatmapError> {
+    let mut warnings = Vec::new();
+    let mut title = String::new();
+    let mut initial_bpm = DEFAULT_BPM;
+    let mut wavs: HashMap<String, String> = HashMap::new();
+    let mut extended_bpms: HashMap<String, f64> = HashMap::new();
+    let mut stops: HashMap<String, f64> = HashMap::new();
+    let mut scrolls: HashMap<String, f64> = HashMap::new();
+    let mut measure_lengths: HashMap<u32, f64> = HashMap::new();
+    let mut objects: Vec<BmsObject> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        // '#' で始まらない行はコメント
+        let Some(body) = line.strip_prefix('#') else {
+            continue;
+        };
+
+        // チャンネル行 "#mmmcc:data"
+        if let Some((head, data)) = body.split_once(':') {
+            // 文字コードを読み替えた行には複数バイトの文字が入ることがあるので，バイト位置で切る前に確かめる
+            if head.len() == 5 && !head.is_ascii() {
+                return Err(BeatmapError::Parse {
+                    line: line_no,
+                    message: format!("invalid channel '#{}'", head),
+                });
+            }
+            if head.len() == 5 && head[..3].bytes().all(|b| b.is_ascii_digit()) {
+                let measure: u32 = head[..3].parse().unwrap_or_default();
+                let channel = head[3..].to_ascii_uppercase();
+                let data = data.trim();
+                if channel == "02" {
+                    let ratio = data.parse::<f64>().map_err(|e| BeatmapError::Parse {
+                        line: line_no,
+                        message: format!("invalid measure length '{}': {}", data, e),
+                    })?;
+                    measure_lengths.insert(measure, ratio);
+                    continue;
+                }
+                if !data.is_ascii() {
+                    return Err(BeatmapError::Parse {
+                        line: line_no,
+                        message: format!("non-ASCII characters in channel data '{}'", data),
+                    });
+                }
+                if data.len() % 2 != 0 {
+                    return Err(BeatmapError::Parse {
+                        line: line_no,
+                        message: format!("odd number of characters in channel data '{}'", data),
+                    });
+                }
+                let count = data.len() / 2;
+                for i in 0..count {
+                    let value = data[i * 2..i * 2 + 2].to_ascii_uppercase();
+                    if value != "00" {
+                        objects.push(BmsObject {
+                            measure,
+                            position: i as f64 / count as f64,
+                            channel: channel.clone(),
+                            value,
+                        });
+                    }
+                }
+                continue;
+            }
+        }
+
+        // ヘッダ行 "#COMMAND value"
+        let (command, value) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+        let command = command.to_ascii_uppercase();
+        let value = value.trim();
+        match command.as_str() {
+            "TITLE" => title = value.to_string(),
+            "BPM" => {
+                initial_bpm = value.parse().map_err(|e| BeatmapError::Parse {
+                    line: line_no,
+                    message: format!("invalid #BPM '{}': {}", value, e),
+                })?;
+            }
+            "RANDOM" | "SETRANDOM" | "IF" | "ELSEIF" | "ELSE" | "ENDIF" | "ENDRANDOM" | "SWITCH" | "CASE" => {
+                warn_once(&mut warnings, "#RANDOM/#IF blocks are not supported; every branch was read".to_string());
+            }
+            "LNOBJ" | "LNTYPE" => {
+                warn_once(&mut warnings, "long notes are not supported; they are played as single notes".to_string());
+            }
+            _ if command.len() == 5 && command.starts_with("WAV") => {
+                wavs.insert(command[3..].to_string(), value.to_string());
+            }
+            _ if command.len() == 5 && command.starts_with("BPM") => {
+                let bpm = value.parse().map_err(|e| BeatmapError::Parse {
+                    line: line_no,
+                    message: format!("invalid #{} '{}': {}", command, value, e),
+                })?;
+                extended_bpms.insert(command[3..].to_string(), bpm);
+            }
+            _ if command.len() == 6 && command.starts_with("STOP") => {
+                let stop = value.parse().map_err(|e| BeatmapError::Parse {
+                    line: line_no,
+                    message: format!("invalid #{} '{}': {}", command, value, e),
+                })?;
+                stops.insert(command[4..].to_string(), stop);
+            }
+            _ if command.len() == 8 && command.starts_with("SCROLL") => {
+                let scroll = value.parse().map_err(|e| BeatmapError::Parse {
+                    line: line_no,
+                    message: format!("invalid #{} '{}': {}", command, value, e),
+                })?;
+                scrolls.insert(command[6..].to_string(), scroll);
+            }
+            _ if command.starts_with("BMP") || command.starts_with("BGA") => {
+                warn_once(&mut warnings, "BGA (background animation) is not supported and was ignored".to_string());
+            }
+            // #PLAYER, #ARTIST, #PLAYLEVEL などは使わない
+            _ => {}
+        }
+    }
+
+    // 小節の開始拍を求める
+    let last_measure = objects.iter().map(|object| object.measure).max().unwrap_or(0);
+    let measure_beats = |measure: u32| BEATS_PER_MEASURE * measure_lengths.get(&measure).copied().unwrap_or(1.0);
+    let mut measure_starts = Vec::with_capacity(last_measure as usize + 1);
+    let mut beat = 0.0;
+    for measure in 0..=last_measure {
+        measure_starts.push(beat);
+        beat += measure_beats(measure);
+    }
+    let beat_of = |object: &BmsObject| {
+        measure_starts[object.measure as usize] + object.position * measure_beats(object.measure)
+    };
+    objects.sort_by(|a, b| beat_of(a).total_cmp(&beat_of(b)));
+
+    // テンポマップ（停止の長さは BPM で決まるので，BPM 変化を先に入れる）
+    if initial_bpm <= 0.0 {
+        return Err(BeatmapError::Unsupported(format!("#BPM must be positive (got {})", initial_bpm)));
+    }
+    let mut tempo = TempoMap::new(initial_bpm);
+    for object in &objects {
+        let bpm = match object.channel.as_str() {
+            "03" => u8::from_str_radix(&object.value, 16).ok().map(f64::from),
+            "08" => extended_bpms.get(&object.value).copied(),
+            _ => continue,
+        };
+        match bpm {
+            Some(bpm) if bpm > 0.0 => tempo.add(beat_of(object), TempoEvent::Bpm(bpm)),
+            _ => warn_once(&mut warnings, format!("invalid BPM change '{}' was ignored", object.value)),
+        }
+    }
+    let mut stop_events = Vec::new();
+    for object in objects.iter().filter(|object| object.channel == "09") {
+        let beat = beat_of(object);
+        match stops.get(&object.value) {
+            Some(&units) if units > 0.0 => {
+                let seconds = units / STOP_UNITS_PER_BEAT * 60.0 / tempo.bpm_at(beat);
+                stop_events.push((beat, seconds));
+            }
+            _ => warn_once(&mut warnings, format!("invalid STOP '{}' was ignored", object.value)),
+        }
+    }
+    for (beat, seconds) in stop_events {
+        tempo.add(beat, TempoEvent::Stop(seconds));
+    }
+    for object in objects.iter().filter(|object| object.channel == "SC") {
+        match scrolls.get(&object.value) {
+            // 逆方向のスクロールには対応しないので 0 で止める
+            Some(&speed) if speed < 0.0 => {
+                warn_once(&mut warnings, "negative scroll speeds are not supported and were treated as 0".to_string());
+                tempo.add(beat_of(object), TempoEvent::Scroll(0.0));
+            }
+            Some(&speed) => tempo.add(beat_of(object), TempoEvent::Scroll(speed)),
+            None => warn_once(&mut warnings, format!("undefined SCROLL '{}' was ignored", object.value)),
+        }
+    }
+
+    // キー音のパス（警告が重複しないように 1 度だけ変換する）
+    let mut sound_paths: HashMap<String, Option<String>> = HashMap::new();
+    let mut sound_path = |id: &str, warnings: &mut Vec<String>| -> Option<String> {
+        sound_paths
+            .entry(id.to_string())
+            .or_insert_with(|| wavs.get(id).and_then(|file| to_asset_path(base_dir, file, warnings)))
+            .clone()
+    };
+
+    let mut notes = Vec::new();
+    let mut bgm = Vec::new();
+    let mut open_long_notes: HashSet<String> = HashSet::new();
+    for object in &objects {
+        let time = tempo.beat_to_time(beat_of(object)) as f32;
+        let channel = object.channel.as_str();
+        match channel.as_bytes() {
+            // BGM
+            b"01" => {
+                if let Some(sound) = sound_path(&object.value, &mut warnings) {
+                    bgm.push(BgmEvent { time, sound });
+                }
+            }
+            // BPM 変化・停止・スクロール速度変化（処理済み）
+            b"03" | b"08" | b"09" | b"SC" => {}
+            b"04" | b"06" | b"07" | b"0A" => {
+                warn_once(&mut warnings, "BGA (background animation) is not supported and was ignored".to_string());
+            }
+            // 1P / 2P の通常ノーツ
+            [b'1' | b'2', b'1'..=b'9'] => notes.push(ChartNote {
+                time,
+                keysound: sound_path(&object.value, &mut warnings),
+            }),
+            // 不可視ノーツ
+            [b'3' | b'4', _] => {
+                warn_once(&mut warnings, "invisible notes are not supported and were ignored".to_string());
+            }
+            // ロングノーツ：始点だけを通常ノーツとして扱う
+            [b'5' | b'6', _] => {
+                warn_once(&mut warnings, "long notes are not supported; they are played as single notes".to_string());
+                if open_long_notes.insert(object.channel.clone()) {
+                    notes.push(ChartNote {
+                        time,
+                        keysound: sound_path(&object.value, &mut warnings),
+                    });
+                } else {
+                    open_long_notes.remove(&object.channel);
+                }
+            }
+            // 地雷ノーツ
+            [b'D' | b'E', _] => {
+                warn_once(&mut warnings, "mine notes are not supported and were ignored".to_string());
+            }
+            _ => warn_once(&mut warnings, format!("unsupported channel {} was ignored", channel)),
+        }
+    }
+
+    Ok(Beatmap {
+        chart: Chart { title, notes, bgm, tempo },
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_text(text: &str) -> Result<Beatmap, BeatmapError> {
+        parse(text, Path::new(""))
+    }
+
+    fn note_times(beatmap: &Beatmap) -> Vec<f32> {
+        beatmap.chart.notes.iter().map(|note| note.time).collect()
+    }
+
+    fn parse_error_line(result: Result<Beatmap, BeatmapError>) -> usize {
+        match result {
+            Err(BeatmapError::Parse { line, .. }) => line,
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn channel_data_is_placed_in_the_measure() {
+        // 120 BPM で 1 拍 0.5 秒，小節 1 は 4 拍目から
+        let beatmap = parse_text("#TITLE Test\n#BPM 120\n#00111:01000100\n").unwrap();
+        assert_eq!(beatmap.chart.title, "Test");
+        assert_eq!(note_times(&beatmap), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn measure_length_changes_later_measures() {
+        let beatmap = parse_text("#BPM 120\n#00002:0.5\n#00111:01\n").unwrap();
+        assert_eq!(note_times(&beatmap), vec![1.0]);
+    }
+
+    #[test]
+    fn extended_bpm_and_stop() {
+        let text = "#BPM 120\n#BPM01 240\n#STOP01 48\n#00109:01\n#00208:01\n#00211:01\n#00311:01\n";
+        let beatmap = parse_text(text).unwrap();
+        let tempo = &beatmap.chart.tempo;
+        assert_eq!(tempo.bpm_at(7.9), 120.0);
+        assert_eq!(tempo.bpm_at(8.0), 240.0);
+        // 4 拍目で 1 拍分（120 BPM で 0.5 秒）止まり，8 拍目から 240 BPM
+        assert_eq!(note_times(&beatmap), vec![4.5, 5.5]);
+    }
+
+    #[test]
+    fn long_notes_use_only_the_start() {
+        let beatmap = parse_text("#BPM 120\n#00151:0101\n#00251:0100\n#00351:01\n").unwrap();
+        assert_eq!(note_times(&beatmap), vec![2.0, 4.0]);
+        assert!(beatmap.warnings.iter().any(|warning| warning.contains("long notes")));
+    }
+
+    #[test]
+    fn malformed_lines_are_parse_errors() {
+        assert_eq!(parse_error_line(parse_text("#BPM 120\n#00111:010\n")), 2);
+        assert_eq!(parse_error_line(parse_text("#BPM abc\n")), 1);
+        assert_eq!(parse_error_line(parse_text("#BPM 120\n\n#BPM01 fast\n")), 3);
+        assert_eq!(parse_error_line(parse_text("#00002:half\n")), 1);
+    }
+
+    #[test]
+    fn multibyte_characters_are_parse_errors() {
+        assert_eq!(parse_error_line(parse_text("#BPM 120\n#abあ:00\n")), 2);
+        assert_eq!(parse_error_line(parse_text("#BPM 120\n#00111:あい\n")), 2);
+        // 見出しの行の値は複数バイトの文字でもよい
+        assert_eq!(parse_text("#TITLE 吾輩は猫である\n#00111:01\n").unwrap().chart.title, "吾輩は猫である");
+    }
+}
@@ -0,0 +1,235 @@
+///
+/// osu!mania（.osu）形式の読み込み
+///
+use std::path::Path;
+use super::{to_asset_path, warn_once, Beatmap, BeatmapError};
+use crate::bevy_timing_game::chart::{BgmEvent, Chart, ChartNote};
+use crate::bevy_timing_game::tempo::{TempoEvent, TempoMap};
+
+// osu! のゲームモード（3 が mania）
+const MODE_MANIA: u32 = 3;
+// HitObject の type のビット
+const TYPE_HOLD_NOTE: u32 = 1 << 7;
+
+///
+/// osu! のテキストを譜面に変換する
+/// * text : &str          .osu ファイルの内容
+/// * base_dir : &Path     .osu ファイルのあるディレクトリ（AudioFilename などの相対パスの基準）
+///
+pub fn parse(text: &str, base_dir: &Path) -> Result<Beatmap, BeatmapError> {
+    let mut warnings = Vec::new();
+    let mut section = String::new();
+    let mut mode = 0;
+    let mut title = String::new();
+    let mut audio_file = None;
+    let mut notes = Vec::new();
+    // (時刻（ミリ秒）, beatLength, uninherited)
+    let mut timing_points: Vec<(f64, f64, bool)> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+
+        match section.as_str() {
+            "General" | "Metadata" => {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match key.trim() {
+                    "AudioFilename" => audio_file = Some(value.to_string()),
+                    "Mode" => {
+                        mode = value.parse().map_err(|e| BeatmapError::Parse {
+                            line: line_no,
+                            message: format!("invalid Mode '{}': {}", value, e),
+                        })?;
+                    }
+                    "Title" => title = value.to_string(),
+                    _ => {}
+                }
+            }
+            "Events" => {
+                warn_once(&mut warnings, "background images, videos and storyboards are not supported and were ignored".to_string());
+            }
+            "TimingPoints" => {
+                // time,beatLength,meter,sampleSet,sampleIndex,volume,uninherited,effects
+                let fields: Vec<&str> = line.split(',').collect();
+                let parse_field = |index: usize| {
+                    fields
+                        .get(index)
+                        .and_then(|value| value.trim().parse::<f64>().ok())
+                        .ok_or_else(|| BeatmapError::Parse {
+                            line: line_no,
+                            message: format!("invalid timing point '{}'", line),
+                        })
+                };
+                let time = parse_field(0)?;
+                let beat_length = parse_field(1)?;
+                // uninherited が 0 の場合は BPM ではなくスクロール速度の変化
+                let uninherited = fields.get(6).is_none_or(|value| value.trim() != "0");
+                timing_points.push((time, beat_length, uninherited));
+            }
+            "HitObjects" => {
+                // x,y,time,type,hitSound,objectParams,hitSample
+                let fields: Vec<&str> = line.split(',').collect();
+                let parse_field = |index: usize| -> Result<i64, BeatmapError> {
+                    fields
+                        .get(index)
+                        .and_then(|value| value.trim().parse::<i64>().ok())
+                        .ok_or_else(|| BeatmapError::Parse {
+                            line: line_no,
+                            message: format!("invalid hit object '{}'", line),
+                        })
+                };
+                let time = parse_field(2)?;
+                let object_type = parse_field(3)? as u32;
+                if object_type & TYPE_HOLD_NOTE != 0 {
+                    warn_once(&mut warnings, "hold notes are not supported; they are played as single notes".to_string());
+                }
+
+                // hitSample の最後の項目がカスタムのヒット音
+                // 通常ノーツは 6 番目，ホールドノーツは "endTime:hitSample" の形
+                let hit_sample = fields.last().copied().unwrap_or("");
+                let keysound = hit_sample
+                    .rsplit(':')
+                    .next()
+                    .map(str::trim)
+                    .filter(|file| fields.len() > 5 && !file.is_empty() && file.contains('.'))
+                    .and_then(|file| to_asset_path(base_dir, file, &mut warnings));
+
+                notes.push(ChartNote {
+                    time: time as f32 / 1000.0,
+                    keysound,
+                });
+            }
+            // [Editor], [Difficulty], [Colours] は使わない
+            _ => {}
+        }
+    }
+
+    if mode != MODE_MANIA {
+        return Err(BeatmapError::Unsupported(format!(
+            "osu! mode {} is not osu!mania (mode {})",
+            mode, MODE_MANIA
+        )));
+    }
+
+    // osu! のノーツはミリ秒で配置されているので，テンポマップは見た目のスクロールにだけ使う
+    let tempo = build_tempo_map(timing_points, &mut warnings);
+
+    // 楽曲ファイルは曲の開始時に再生する
+    let bgm = audio_file
+        .and_then(|file| to_asset_path(base_dir, &file, &mut warnings))
+        .map(|sound| BgmEvent { time: 0.0, sound })
+        .into_iter()
+        .collect();
+
+    Ok(Beatmap {
+        chart: Chart { title, notes, bgm, tempo },
+        warnings,
+    })
+}
+
+///
+/// タイミングポイントからテンポマップを作成する
+/// * uninherited なポイント : beatLength は 1 拍のミリ秒数（BPM の変化）
+/// * inherited なポイント   : beatLength は -100 / スクロール速度
+///
+fn build_tempo_map(mut timing_points: Vec<(f64, f64, bool)>, warnings: &mut Vec<String>) -> TempoMap {
+    timing_points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let initial_bpm = timing_points
+        .iter()
+        .find(|(_, beat_length, uninherited)| *uninherited && *beat_length > 0.0)
+        .map(|(_, beat_length, _)| 60_000.0 / beat_length);
+    let Some(initial_bpm) = initial_bpm else {
+        warn_once(warnings, "no BPM timing point was found; notes scroll at a constant speed".to_string());
+        return TempoMap::default();
+    };
+
+    let mut tempo = TempoMap::new(initial_bpm);
+    for (time, beat_length, uninherited) in timing_points {
+        let beat = tempo.time_to_beat(time / 1000.0);
+        if uninherited {
+            if beat_length <= 0.0 {
+                warn_once(warnings, format!("invalid BPM timing point at {}ms was ignored", time));
+                continue;
+            }
+            // BPM が変わるとスクロール速度は等速に戻る
+            tempo.add(beat, TempoEvent::Bpm(60_000.0 / beat_length));
+            tempo.add(beat, TempoEvent::Scroll(1.0));
+        } else if beat_length < 0.0 {
+            tempo.add(beat, TempoEvent::Scroll(-100.0 / beat_length));
+        } else {
+            warn_once(warnings, format!("invalid scroll speed timing point at {}ms was ignored", time));
+        }
+    }
+    tempo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIA: &str = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+Mode: 3
+
+[Metadata]
+Title:Test Song
+
+[TimingPoints]
+0,500,4,1,0,100,1,0
+2000,-50,4,1,0,100,0,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1500,128,0,2000:0:0:0:0:
+";
+
+    fn parse_error_line(result: Result<Beatmap, BeatmapError>) -> usize {
+        match result {
+            Err(BeatmapError::Parse { line, .. }) => line,
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn mania_notes_and_timing_points() {
+        let beatmap = parse(MANIA, Path::new("")).unwrap();
+        assert_eq!(beatmap.chart.title, "Test Song");
+        let times: Vec<f32> = beatmap.chart.notes.iter().map(|note| note.time).collect();
+        assert_eq!(times, vec![1.0, 1.5]);
+        let tempo = &beatmap.chart.tempo;
+        assert_eq!(tempo.initial_bpm(), 120.0);
+        // 2 秒（4 拍目）からスクロール速度が 2 倍
+        assert_eq!(tempo.beat_to_scroll(4.0), 4.0);
+        assert_eq!(tempo.beat_to_scroll(5.0), 6.0);
+        assert!(beatmap.warnings.iter().any(|warning| warning.contains("hold notes")));
+    }
+
+    #[test]
+    fn non_mania_mode_is_unsupported() {
+        let text = MANIA.replace("Mode: 3", "Mode: 0");
+        assert!(matches!(parse(&text, Path::new("")), Err(BeatmapError::Unsupported(_))));
+    }
+
+    #[test]
+    fn malformed_lines_are_parse_errors() {
+        assert_eq!(parse_error_line(parse(&MANIA.replace("Mode: 3", "Mode: mania"), Path::new(""))), 5);
+        let text = format!("{}64,192,soon,1,0,0:0:0:0:\n", MANIA);
+        assert_eq!(parse_error_line(parse(&text, Path::new(""))), 17);
+        let text = MANIA.replace("0,500,4,1,0,100,1,0", "0,fast");
+        assert_eq!(parse_error_line(parse(&text, Path::new(""))), 11);
+    }
+}
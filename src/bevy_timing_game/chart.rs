@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use std::collections::BTreeSet;
+use super::tempo::{TempoEvent, TempoMap};
+
+// デモ譜面の設定
+const DEMO_NOTE_COUNT: usize = 32;
+const DEMO_NOTE_INTERVAL: f32 = 0.5;
+const DEMO_START_OFFSET: f32 = 2.0;
+// デモ譜面のキー音（ド・ミ・ソ・高いド）と，それを順に鳴らす旋律
+const DEMO_KEYSOUNDS: [&str; 4] = [
+    "sounds/demo/c5.wav",
+    "sounds/demo/e5.wav",
+    "sounds/demo/g5.wav",
+    "sounds/demo/c6.wav",
+];
+const DEMO_MELODY: [usize; 8] = [0, 1, 2, 1, 0, 1, 2, 3];
+const DEMO_TITLE: &str = "Demo";
+const DEMO_BPM: f64 = 120.0;
+
+// ボーナス譜面（実績で解放）の設定
+// 1 小節分のリズムを繰り返し，途中で BPM が上がる
+const BONUS_TITLE: &str = "Bonus";
+const BONUS_BPM: f64 = 120.0;
+const BONUS_FAST_BPM: f64 = 160.0;
+const BONUS_START_BEAT: f64 = 4.0;
+const BONUS_FAST_BEAT: f64 = 20.0;
+const BONUS_MEASURES: usize = 8;
+const BONUS_RHYTHM: [f64; 6] = [0.0, 1.0, 1.5, 2.0, 3.0, 3.5];
+const BONUS_KEYSOUND: &str = "sounds/timing.ogg";
+
+///
+/// 譜面データ
+/// ノーツ・BGM は時刻の昇順に並んでいる前提
+/// ノーツの見た目の位置は tempo から求める
+///
+#[derive(Resource, Clone, Default)]
+pub struct Chart {
+    pub title: String,
+    pub notes: Vec<ChartNote>,
+    pub bgm: Vec<BgmEvent>,
+    pub tempo: TempoMap,
+}
+
+///
+/// 譜面上の 1 ノーツ
+/// * time : f32                  曲の開始からの判定時刻（秒）
+/// * keysound : Option<String>   ノーツ固有の音声ファイル（BMS のキー音に相当）
+///
+#[derive(Clone, Debug)]
+pub struct ChartNote {
+    pub time: f32,
+    pub keysound: Option<String>,
+}
+
+///
+/// 譜面上で自動再生される音
+/// * time : f32       曲の開始からの再生時刻（秒）
+/// * sound : String   音声ファイル（osu! の楽曲ファイル，BMS の BGM チャンネルなど）
+///
+#[derive(Clone, Debug)]
+pub struct BgmEvent {
+    pub time: f32,
+    pub sound: String,
+}
+
+impl Chart {
+    ///
+    /// 組み込みのデモ譜面を作成する（ノーツごとに高さの違うキー音で旋律を鳴らす）
+    ///
+    pub fn demo() -> Self {
+        let notes = (0..DEMO_NOTE_COUNT)
+            .map(|i| ChartNote {
+                time: DEMO_START_OFFSET + i as f32 * DEMO_NOTE_INTERVAL,
+                keysound: Some(DEMO_KEYSOUNDS[DEMO_MELODY[i % DEMO_MELODY.len()]].to_string()),
+            })
+            .collect();
+        Chart {
+            title: DEMO_TITLE.to_string(),
+            notes,
+            bgm: Vec::new(),
+            tempo: TempoMap::new(DEMO_BPM),
+        }
+    }
+
+    ///
+    /// 組み込みのボーナス譜面を作成する
+    ///
+    pub fn bonus() -> Self {
+        let mut tempo = TempoMap::new(BONUS_BPM);
+        tempo.add(BONUS_FAST_BEAT, TempoEvent::Bpm(BONUS_FAST_BPM));
+        let notes = (0..BONUS_MEASURES)
+            .flat_map(|measure| BONUS_RHYTHM.iter().map(move |beat| BONUS_START_BEAT + measure as f64 * 4.0 + beat))
+            .map(|beat| ChartNote {
+                time: tempo.beat_to_time(beat) as f32,
+                keysound: Some(BONUS_KEYSOUND.to_string()),
+            })
+            .collect();
+        Chart {
+            title: BONUS_TITLE.to_string(),
+            notes,
+            bgm: Vec::new(),
+            tempo,
+        }
+    }
+
+    ///
+    /// キー音を持つノーツが 1 つでもあれば，キー音モードで再生する
+    ///
+    pub fn is_keysound_mode(&self) -> bool {
+        self.notes.iter().any(|note| note.keysound.is_some())
+    }
+
+    ///
+    /// 譜面で使われる音声ファイル（キー音・BGM）のパス一覧（重複なし）
+    ///
+    pub fn sound_paths(&self) -> BTreeSet<&str> {
+        self.notes
+            .iter()
+            .filter_map(|note| note.keysound.as_deref())
+            .chain(self.bgm.iter().map(|event| event.sound.as_str()))
+            .collect()
+    }
+
+    ///
+    /// 譜面の識別用ハッシュ値（FNV-1a）
+    /// ベストスコアの保存先などに使うため，実行環境によらず同じ値になるようにしている
+    ///
+    pub fn hash(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for note in &self.notes {
+            let bytes = note
+                .time
+                .to_le_bytes()
+                .into_iter()
+                .chain(note.keysound.as_deref().unwrap_or("").bytes())
+                .chain([0]);
+            for byte in bytes {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+
+    ///
+    /// 最後のノーツの判定時刻
+    ///
+    pub fn last_note_time(&self) -> f32 {
+        self.notes.last().map_or(0.0, |note| note.time)
+    }
+
+    ///
+    /// ノーツと BGM を時刻順に並べ替える
+    ///
+    pub fn sort(&mut self) {
+        self.notes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.bgm.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy::color::palettes::css;
+use super::{
+    despawn_missed_notes, finish_song, AppState, GameMode, GaugeType, Judgements, PlayerSide, Settings,
+    SongFailed,
+};
+
+// HARD ゲージの増減（最大 100）
+const GAUGE_MAX: f32 = 100.0;
+const GAUGE_PERFECT_RECOVERY: f32 = 1.0;
+const GAUGE_GOOD_RECOVERY: f32 = 0.5;
+const GAUGE_MISS_DAMAGE: f32 = 10.0;
+
+// ゲージの表示（スコアボードの下）
+const GAUGE_BAR_SIZE: Vec2 = Vec2::new(200.0, 12.0);
+const GAUGE_BAR_TOP: f32 = 56.0;
+const GAUGE_BAR_LEFT: f32 = 12.0;
+const GAUGE_BG_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+const GAUGE_COLOR: Color = Color::Srgba(css::CRIMSON);
+
+///
+/// HARD ゲージ
+/// 1 人プレイで Settings の gauge が Hard のときだけ使う
+/// ゲージは判定の回数の変化から増減させるので，判定の処理には手を入れない
+///
+pub struct GaugePlugin;
+
+impl Plugin for GaugePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::PlayingGame), setup_gauge.run_if(hard_gauge_enabled))
+            .add_systems(
+                Update,
+                update_gauge
+                    .after(despawn_missed_notes)
+                    .before(finish_song)
+                    .run_if(in_state(AppState::PlayingGame).and(resource_exists::<HardGauge>)),
+            )
+            .add_systems(OnExit(AppState::PlayingGame), remove_gauge);
+    }
+}
+
+///
+/// HARD ゲージの状態
+/// * life : f32             残り（0 で途中終了）
+/// * seen : Judgements      前のフレームまでに反映した判定の回数
+///
+#[derive(Resource)]
+struct HardGauge {
+    life: f32,
+    seen: Judgements,
+}
+
+///
+/// ゲージの残りの表示
+///
+#[derive(Component)]
+struct GaugeBar;
+
+///
+/// HARD ゲージを使うか
+///
+fn hard_gauge_enabled(settings: Res<Settings>, game_mode: Res<GameMode>) -> bool {
+    settings.gauge == GaugeType::Hard && *game_mode == GameMode::Single
+}
+
+///
+/// PlayingGame 遷移時のセットアップ関数
+/// ゲージを満タンにし，表示を生成する
+///
+fn setup_gauge (mut commands: Commands) {
+    commands.insert_resource(HardGauge {
+        life: GAUGE_MAX,
+        seen: Judgements::default(),
+    });
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(GAUGE_BAR_TOP),
+                left: Val::Px(GAUGE_BAR_LEFT),
+                width: Val::Px(GAUGE_BAR_SIZE.x),
+                height: Val::Px(GAUGE_BAR_SIZE.y),
+                ..default()
+            },
+            BackgroundColor(GAUGE_BG_COLOR),
+            StateScoped(AppState::PlayingGame),
+        ))
+        .with_child((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(GAUGE_COLOR),
+            GaugeBar,
+        ));
+}
+
+///
+/// 判定の回数の増えた分だけゲージを増減させる
+/// なくなったら曲を途中で終わらせる
+///
+fn update_gauge (
+    mut hard_gauge: ResMut<HardGauge>,
+    mut song_failed: ResMut<SongFailed>,
+    mut bar_query: Query<&mut Node, With<GaugeBar>>,
+    player_query: Query<(&PlayerSide, &Judgements)>,
+) {
+    let Some((_, judgements)) = player_query.iter().find(|(player_side, _)| **player_side == PlayerSide::One) else {
+        return;
+    };
+    let perfect = judgements.perfect - hard_gauge.seen.perfect;
+    let good = judgements.good - hard_gauge.seen.good;
+    let miss = judgements.miss - hard_gauge.seen.miss;
+    hard_gauge.seen = *judgements;
+
+    hard_gauge.life = (hard_gauge.life
+        + perfect as f32 * GAUGE_PERFECT_RECOVERY
+        + good as f32 * GAUGE_GOOD_RECOVERY
+        - miss as f32 * GAUGE_MISS_DAMAGE)
+        .clamp(0.0, GAUGE_MAX);
+    for mut node in &mut bar_query {
+        node.width = Val::Percent(hard_gauge.life / GAUGE_MAX * 100.0);
+    }
+    if hard_gauge.life <= 0.0 {
+        song_failed.0 = true;
+    }
+}
+
+///
+/// PlayingGame を抜けたらゲージを片付ける
+///
+fn remove_gauge (mut commands: Commands) {
+    commands.remove_resource::<HardGauge>();
+}
@@ -0,0 +1,348 @@
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy::color::palettes::css;
+use bevy::sprite::Anchor;
+use super::chart::Chart;
+use super::{
+    finish_song, update_scoreboard, AppState, GameMode, PlayerSide, ScoreBoard, SongClock, SAVE_DIR,
+    SCOREBOARD_COLOR, SLIDER_PERFECT_POINTS,
+};
+use super::results::SongResult;
+
+// ベストスコアがない場合の目標（最大スコアに対する割合．"A" 判定相当）
+const PACEMAKER_TARGET_GRADE: (&str, f32) = ("A", 0.8);
+
+// ペースメーカーの表示設定
+const PACEMAKER_BAR_SIZE: Vec2 = Vec2::new(300.0, 12.0);
+const PACEMAKER_BAR_POS: Vec2 = Vec2::new(0.0, -120.0);
+const PACEMAKER_BAR_GAP: f32 = 4.0;
+const PACEMAKER_BG_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const PACEMAKER_CURRENT_COLOR: Color = Color::Srgba(css::ROYAL_BLUE);
+const PACEMAKER_TARGET_COLOR: Color = Color::Srgba(css::DIM_GRAY);
+const PACEMAKER_FONT_SIZE: f32 = 24.0;
+const PACEMAKER_DELTA_LEFT: f32 = 260.0;
+const PACEMAKER_AHEAD_COLOR: Color = Color::Srgba(css::GREEN);
+const PACEMAKER_BEHIND_COLOR: Color = Color::Srgba(css::RED);
+
+///
+/// ゴースト（ペースメーカー）機能
+/// 1 人プレイの時だけ，過去のベストスコアまたは目標判定と現在のスコアを比較表示する
+///
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::PlayingGame),
+            setup_pacemaker.run_if(resource_equals(GameMode::Single)),
+        )
+        .add_systems(
+            Update,
+            (record_score_curve, update_pacemaker)
+                .chain()
+                .after(update_scoreboard)
+                .before(finish_song)
+                .run_if(in_state(AppState::PlayingGame))
+                .run_if(resource_equals(GameMode::Single)),
+        )
+        .add_systems(
+            OnEnter(AppState::Results),
+            save_best_run.run_if(resource_equals(GameMode::Single)),
+        );
+    }
+}
+
+///
+/// 曲の経過時間ごとのスコアの推移
+/// スコアが変化した時刻だけを記録する
+///
+#[derive(Clone, Default, Debug)]
+struct ScoreCurve {
+    samples: Vec<(f32, isize)>,
+}
+
+impl ScoreCurve {
+    ///
+    /// スコアを記録する（前回から変化がなければ何もしない）
+    ///
+    fn record(&mut self, time: f32, score: isize) {
+        if self.samples.last().map_or(score != 0, |(_, last)| *last != score) {
+            self.samples.push((time, score));
+        }
+    }
+
+    ///
+    /// 指定時刻でのスコア
+    ///
+    fn score_at(&self, time: f32) -> isize {
+        let index = self.samples.partition_point(|(t, _)| *t <= time);
+        if index == 0 { 0 } else { self.samples[index - 1].1 }
+    }
+
+    ///
+    /// 最終スコア
+    ///
+    fn final_score(&self) -> isize {
+        self.samples.last().map_or(0, |(_, score)| *score)
+    }
+
+    ///
+    /// テキスト形式から読み込む
+    /// 1 行に "時刻 スコア" を記述する．'#' から始まる行は無視する
+    ///
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut samples = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(time), Some(score), None) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(format!("line {}: expected \"<time> <score>\"", line_no + 1));
+            };
+            let time = time.parse::<f32>().map_err(|e| format!("line {}: {}", line_no + 1, e))?;
+            let score = score.parse::<isize>().map_err(|e| format!("line {}: {}", line_no + 1, e))?;
+            samples.push((time, score));
+        }
+        Ok(ScoreCurve { samples })
+    }
+
+    ///
+    /// テキスト形式に書き出す
+    ///
+    fn to_text(&self, chart_hash: &str) -> String {
+        let mut text = format!("# best run for chart {}\n", chart_hash);
+        for (time, score) in &self.samples {
+            text.push_str(&format!("{:.3} {}\n", time, score));
+        }
+        text
+    }
+}
+
+///
+/// ペースメーカーの比較対象
+///
+#[derive(Resource)]
+enum PacemakerTarget {
+    // 過去のベストスコアの推移
+    BestRun(ScoreCurve),
+    // 目標とする判定（最大スコアに対する割合）
+    Grade(&'static str, f32),
+}
+
+///
+/// 今回のプレイのスコア推移
+///
+#[derive(Resource, Default)]
+struct CurrentRun(ScoreCurve);
+
+#[derive(Component)]
+struct PacemakerCurrentBar;
+
+#[derive(Component)]
+struct PacemakerTargetBar;
+
+#[derive(Component)]
+struct PacemakerDelta;
+
+///
+/// ベストスコアの保存先
+///
+fn best_run_path(chart: &Chart) -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(format!("best_{}.txt", chart.hash()))
+}
+
+///
+/// 譜面の最大スコア（全て "良" の場合）
+///
+fn max_score(chart: &Chart) -> isize {
+    chart.notes.len() as isize * SLIDER_PERFECT_POINTS
+}
+
+///
+/// 指定時刻までに判定時刻を迎えたノーツで取りうる最大スコア
+///
+fn max_score_at(chart: &Chart, time: f32) -> isize {
+    chart.notes.iter().filter(|note| note.time <= time).count() as isize * SLIDER_PERFECT_POINTS
+}
+
+///
+/// PlayingGame 遷移時のセットアップ関数
+/// ベストスコアを読み込み，ペースメーカーを表示する
+///
+fn setup_pacemaker (
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    chart: Res<Chart>,
+) {
+    // ベストスコアがなければ目標判定と比較する
+    let target = match fs::read_to_string(best_run_path(&chart)) {
+        Ok(text) => match ScoreCurve::parse(&text) {
+            Ok(curve) => PacemakerTarget::BestRun(curve),
+            Err(e) => {
+                warn!("ignoring broken best run file: {}", e);
+                PacemakerTarget::Grade(PACEMAKER_TARGET_GRADE.0, PACEMAKER_TARGET_GRADE.1)
+            }
+        },
+        Err(_) => PacemakerTarget::Grade(PACEMAKER_TARGET_GRADE.0, PACEMAKER_TARGET_GRADE.1),
+    };
+    let target_label = match &target {
+        PacemakerTarget::BestRun(_) => "vs Best".to_string(),
+        PacemakerTarget::Grade(grade, _) => format!("vs {}", grade),
+    };
+    commands.insert_resource(target);
+    commands.insert_resource(CurrentRun::default());
+
+    // ペースメーカーのバー（上段が今回，下段が比較対象）
+    let bar_offset = (PACEMAKER_BAR_SIZE.y + PACEMAKER_BAR_GAP) / 2.0;
+    for y in [PACEMAKER_BAR_POS.y + bar_offset, PACEMAKER_BAR_POS.y - bar_offset] {
+        commands.spawn((
+            Sprite {
+                color: PACEMAKER_BG_COLOR,
+                custom_size: Some(PACEMAKER_BAR_SIZE),
+                ..default()
+            },
+            Transform::from_xyz(PACEMAKER_BAR_POS.x, y, 1.0),
+            StateScoped(AppState::PlayingGame),
+        ));
+    }
+    commands.spawn((
+        pacemaker_bar(PACEMAKER_CURRENT_COLOR, PACEMAKER_BAR_POS.y + bar_offset),
+        PacemakerCurrentBar,
+        StateScoped(AppState::PlayingGame),
+    ));
+    commands.spawn((
+        pacemaker_bar(PACEMAKER_TARGET_COLOR, PACEMAKER_BAR_POS.y - bar_offset),
+        PacemakerTargetBar,
+        StateScoped(AppState::PlayingGame),
+    ));
+
+    // スコアボードの横に差分を表示する
+    commands.spawn((
+        Text::new(format!("{} ", target_label)),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: PACEMAKER_FONT_SIZE,
+            ..default()
+        },
+        TextColor(SCOREBOARD_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(0.0),
+            left: Val::Px(PACEMAKER_DELTA_LEFT),
+            ..default()
+        },
+        StateScoped(AppState::PlayingGame),
+    ))
+    .with_child((
+        TextSpan::new("+0"),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: PACEMAKER_FONT_SIZE,
+            ..default()
+        },
+        TextColor(PACEMAKER_AHEAD_COLOR),
+        PacemakerDelta,
+    ));
+}
+
+///
+/// 左端を基準に伸び縮みするバー
+///
+fn pacemaker_bar(color: Color, y: f32) -> (Sprite, Transform) {
+    (
+        Sprite {
+            color,
+            custom_size: Some(Vec2::new(0.0, PACEMAKER_BAR_SIZE.y)),
+            anchor: Anchor::CenterLeft,
+            ..default()
+        },
+        Transform::from_xyz(PACEMAKER_BAR_POS.x - PACEMAKER_BAR_SIZE.x / 2.0, y, 2.0),
+    )
+}
+
+///
+/// 今回のスコアの推移を記録する
+///
+fn record_score_curve (
+    mut current_run: ResMut<CurrentRun>,
+    song_clock: Res<SongClock>,
+    player_query: Query<(&PlayerSide, &ScoreBoard), Without<TextSpan>>,
+) {
+    let now = song_clock.0.elapsed_secs();
+    for (player_side, score_board) in &player_query {
+        if *player_side == PlayerSide::One {
+            current_run.0.record(now, score_board.score);
+        }
+    }
+}
+
+///
+/// ペースメーカーの表示を更新する
+///
+fn update_pacemaker (
+    current_run: Res<CurrentRun>,
+    target: Res<PacemakerTarget>,
+    song_clock: Res<SongClock>,
+    chart: Res<Chart>,
+    mut current_bar_query: Query<&mut Sprite, (With<PacemakerCurrentBar>, Without<PacemakerTargetBar>)>,
+    mut target_bar_query: Query<&mut Sprite, (With<PacemakerTargetBar>, Without<PacemakerCurrentBar>)>,
+    mut delta_query: Query<(&mut TextSpan, &mut TextColor), With<PacemakerDelta>>,
+) {
+    let now = song_clock.0.elapsed_secs();
+    let current_score = current_run.0.final_score();
+    let target_score = match &*target {
+        PacemakerTarget::BestRun(curve) => curve.score_at(now),
+        PacemakerTarget::Grade(_, rate) => (max_score_at(&chart, now) as f32 * rate) as isize,
+    };
+
+    // バーの長さは譜面の最大スコアに対する割合
+    let max_score = max_score(&chart).max(1) as f32;
+    let bar_width = |score: isize| (score as f32 / max_score).clamp(0.0, 1.0) * PACEMAKER_BAR_SIZE.x;
+    current_bar_query.single_mut().custom_size = Some(Vec2::new(bar_width(current_score), PACEMAKER_BAR_SIZE.y));
+    target_bar_query.single_mut().custom_size = Some(Vec2::new(bar_width(target_score), PACEMAKER_BAR_SIZE.y));
+
+    // 差分の表示
+    let delta = current_score - target_score;
+    let (mut text_span, mut text_color) = delta_query.single_mut();
+    **text_span = format!("{:+}", delta);
+    text_color.0 = if delta >= 0 { PACEMAKER_AHEAD_COLOR } else { PACEMAKER_BEHIND_COLOR };
+}
+
+///
+/// Results 遷移時に，ベストスコアを更新していれば推移を保存する
+///
+fn save_best_run (
+    current_run: Res<CurrentRun>,
+    target: Res<PacemakerTarget>,
+    song_result: Res<SongResult>,
+    chart: Res<Chart>,
+) {
+    // 途中で終わった曲の推移は残さない
+    if song_result.failed {
+        return;
+    }
+    let Some(final_score) = song_result
+        .players
+        .iter()
+        .find(|player| player.player_side == PlayerSide::One)
+        .map(|player| player.score)
+    else {
+        return;
+    };
+    let best_score = match &*target {
+        PacemakerTarget::BestRun(curve) => curve.final_score(),
+        PacemakerTarget::Grade(..) => 0,
+    };
+    if final_score <= best_score {
+        return;
+    }
+
+    let result = fs::create_dir_all(SAVE_DIR)
+        .and_then(|_| fs::write(best_run_path(&chart), current_run.0.to_text(&chart.hash())));
+    if let Err(e) = result {
+        warn!("failed to save best run: {}", e);
+    }
+}
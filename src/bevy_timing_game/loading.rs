@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy::asset::{LoadState, UntypedHandle};
+use super::{AppState, KeySoundBank, HIT_SOUND};
+use super::chart::Chart;
+
+// 画面で使うフォント
+const GAME_FONT: &str = "fonts/FiraSans-Bold.ttf";
+
+// 進捗バー
+const LOADING_BAR_SIZE: Vec2 = Vec2::new(500.0, 24.0);
+const LOADING_BAR_BG_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const LOADING_BAR_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+
+// 進捗・エラーの文字
+const LOADING_FONT_SIZE: f32 = 28.0;
+const LOADING_TEXT_COLOR: Color = Color::BLACK;
+const LOADING_ERROR_FONT_SIZE: f32 = 20.0;
+const LOADING_ERROR_COLOR: Color = Color::srgb(0.8, 0.0, 0.0);
+
+///
+/// 曲の開始前にフォント・打鍵音・キー音・BGM を読み込む
+/// 全て読み込めたら PlayingGame に移り，読み込めないファイルがあれば画面にエラーを表示する
+///
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Loading), (setup_loading_screen, start_loading).chain())
+            .add_systems(
+                Update,
+                (update_loading_progress, back_to_title_on_error)
+                    .chain()
+                    .run_if(in_state(AppState::Loading)),
+            );
+    }
+}
+
+///
+/// 読み込み中のアセット
+/// ハンドルを保持しておくことで，曲の途中で解放・再読み込みされないようにする
+/// * handles : Vec<UntypedHandle>     読み込みを待つハンドル
+/// * failed : Vec<String>             読み込めなかったファイル
+///
+#[derive(Resource, Default)]
+struct PreloadedAssets {
+    handles: Vec<UntypedHandle>,
+    failed: Vec<String>,
+}
+
+///
+/// 進捗バーの読み込み済みの部分
+///
+#[derive(Component)]
+struct LoadingBar;
+
+///
+/// 進捗・エラーの表示
+///
+#[derive(Component)]
+struct LoadingText;
+
+///
+/// Loading 遷移時のセットアップ関数
+/// 進捗バーと進捗の文字を生成する
+///
+fn setup_loading_screen (mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            StateScoped(AppState::Loading),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Loading ..."),
+                TextFont {
+                    font: asset_server.load(GAME_FONT),
+                    font_size: LOADING_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(LOADING_TEXT_COLOR),
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.0)),
+                    ..default()
+                },
+                LoadingText,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(LOADING_BAR_SIZE.x),
+                        height: Val::Px(LOADING_BAR_SIZE.y),
+                        ..default()
+                    },
+                    BackgroundColor(LOADING_BAR_BG_COLOR),
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(LOADING_BAR_COLOR),
+                    LoadingBar,
+                ));
+        });
+}
+
+///
+/// 選んだ曲で使うアセットの読み込みを開始する
+/// キー音・BGM のハンドルは KeySoundBank として曲の間も使う
+///
+fn start_loading (mut commands: Commands, chart: Res<Chart>, asset_server: Res<AssetServer>) {
+    let sounds: Vec<(String, Handle<AudioSource>)> = chart
+        .sound_paths()
+        .into_iter()
+        .map(|path| (path.to_string(), asset_server.load(path.to_string())))
+        .collect();
+    let hit_sound: Handle<AudioSource> = asset_server.load(HIT_SOUND);
+    let font: Handle<Font> = asset_server.load(GAME_FONT);
+
+    let mut handles: Vec<UntypedHandle> = sounds.iter().map(|(_, handle)| handle.clone().untyped()).collect();
+    handles.push(hit_sound.clone().untyped());
+    handles.push(font.untyped());
+
+    commands.insert_resource(PreloadedAssets {
+        handles,
+        failed: Vec::new(),
+    });
+    commands.insert_resource(KeySoundBank {
+        sounds: sounds.into_iter().collect(),
+        hit_sound,
+        keysound_mode: chart.is_keysound_mode(),
+    });
+}
+
+///
+/// 読み込みの進捗を表示する
+/// 全て読み込めたら PlayingGame に移る
+///
+fn update_loading_progress (
+    mut next_state: ResMut<NextState<AppState>>,
+    mut preloaded_assets: ResMut<PreloadedAssets>,
+    mut bar_query: Query<&mut Node, With<LoadingBar>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !preloaded_assets.failed.is_empty() {
+        return;
+    }
+
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+    for handle in &preloaded_assets.handles {
+        match asset_server.load_state(handle.id()) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed(e) => failed.push(format!("{} ({})", path_of(handle), e)),
+            LoadState::NotLoaded | LoadState::Loading => {}
+        }
+    }
+    let total = preloaded_assets.handles.len();
+
+    for mut node in &mut bar_query {
+        node.width = Val::Percent(loaded as f32 / total.max(1) as f32 * 100.0);
+    }
+
+    if !failed.is_empty() {
+        for mut text in &mut text_query {
+            **text = format!("Failed to load:\n{}\n\nEsc: Back to Title", failed.join("\n"));
+        }
+        preloaded_assets.failed = failed;
+        return;
+    }
+
+    for mut text in &mut text_query {
+        **text = format!("Loading ... {} / {}", loaded, total);
+    }
+    if loaded == total {
+        next_state.set(AppState::PlayingGame);
+    }
+}
+
+///
+/// 読み込みに失敗した場合，Esc でタイトル画面に戻る
+///
+fn back_to_title_on_error (
+    mut next_state: ResMut<NextState<AppState>>,
+    mut text_query: Query<(&mut TextFont, &mut TextColor), With<LoadingText>>,
+    preloaded_assets: Res<PreloadedAssets>,
+    key_input: Res<ButtonInput<KeyCode>>,
+) {
+    if preloaded_assets.failed.is_empty() {
+        return;
+    }
+    for (mut text_font, mut text_color) in &mut text_query {
+        text_font.font_size = LOADING_ERROR_FONT_SIZE;
+        text_color.0 = LOADING_ERROR_COLOR;
+    }
+    if key_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+///
+/// エラー表示用のファイルのパス
+///
+fn path_of(handle: &UntypedHandle) -> String {
+    handle.path().map_or_else(|| "(unknown)".to_string(), |path| path.to_string())
+}
@@ -0,0 +1,489 @@
+use bevy::prelude::*;
+use bevy::color::palettes::css;
+use super::{AppState, GameMode, GaugeType, NoteSkin, Settings};
+use super::achievements::{Achievements, BonusSongSelected};
+use crate::audio_mixer::AudioBus;
+use crate::launcher::DemoExit;
+
+// タイトル
+const TITLE_TEXT: &str = "Timing Game";
+const TITLE_FONT_SIZE: f32 = 60.0;
+const TITLE_COLOR: Color = Color::BLACK;
+
+// メニュー項目のボタン
+const MENU_FONT_SIZE: f32 = 32.0;
+const MENU_BUTTON_SIZE: Vec2 = Vec2::new(360.0, 56.0);
+const MENU_BUTTON_MARGIN: f32 = 8.0;
+const MENU_BUTTON_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const MENU_FOCUS_COLOR: Color = Color::Srgba(css::GOLD);
+const MENU_BORDER_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+const MENU_FOCUS_BORDER_COLOR: Color = Color::BLACK;
+const MENU_TEXT_COLOR: Color = Color::BLACK;
+
+// 操作の案内
+const MENU_HINT_FONT_SIZE: f32 = 20.0;
+const MENU_HINT_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+
+// 効果音（カーソル移動は高めの音にする）
+const MENU_SOUND: &str = "sounds/timing.ogg";
+const MENU_MOVE_SOUND_SPEED: f32 = 1.5;
+const MENU_DECIDE_SOUND_SPEED: f32 = 1.0;
+
+// 設定できるスクロール速度
+const SCROLL_SPEEDS: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+// 設定できる背景の暗さ（1.0 で背景を表示しない）
+const BACKGROUND_DIMS: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+// 設定できる音量
+const VOLUMES: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+///
+/// タイトル画面・オプション画面
+/// キーボード（上下キー + Enter / Space）とマウスで項目を選ぶ
+///
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>()
+            .add_systems(OnEnter(AppState::MainMenu), setup_title_menu)
+            .add_systems(OnEnter(AppState::Options), setup_options_menu)
+            .add_systems(
+                Update,
+                (
+                    focus_with_mouse,
+                    focus_with_keyboard,
+                    activate_menu_item,
+                    highlight_focus,
+                    update_menu_labels,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::MainMenu).or(in_state(AppState::Options))),
+            );
+    }
+}
+
+///
+/// メニュー項目
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum MenuItem {
+    // タイトル画面
+    Play,
+    Versus,
+    BonusSong,
+    Practice,
+    Tutorial,
+    Options,
+    Achievements,
+    Quit,
+    // オプション画面
+    ScrollSpeed,
+    BackgroundDim,
+    Gauge,
+    NoteSkin,
+    MusicVolume,
+    SfxVolume,
+    MenuSound,
+    Back,
+}
+
+impl MenuItem {
+    ///
+    /// 表示する文字列
+    ///
+    fn label(self, settings: &Settings) -> String {
+        match self {
+            MenuItem::Play => "Play".to_string(),
+            MenuItem::Versus => "2P Versus".to_string(),
+            MenuItem::BonusSong => "Bonus Song".to_string(),
+            MenuItem::Practice => "Practice".to_string(),
+            MenuItem::Tutorial => "Tutorial".to_string(),
+            MenuItem::Options => "Options".to_string(),
+            MenuItem::Achievements => "Achievements".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+            MenuItem::ScrollSpeed => format!("Scroll Speed  < x{:.2} >", settings.scroll_speed),
+            MenuItem::BackgroundDim => {
+                format!("Background Dim  < {:.0}% >", settings.background_dim * 100.0)
+            }
+            MenuItem::Gauge => {
+                format!("Gauge  < {} >", if settings.gauge == GaugeType::Hard { "Hard" } else { "Normal" })
+            }
+            MenuItem::NoteSkin => format!("Note Skin  < {} >", settings.note_skin.label()),
+            MenuItem::MusicVolume => format!("Music Volume  < {:.0}% >", settings.music_volume * 100.0),
+            MenuItem::SfxVolume => format!("SFX Volume  < {:.0}% >", settings.sfx_volume * 100.0),
+            MenuItem::MenuSound => {
+                format!("Menu Sound  < {} >", if settings.menu_sound { "On" } else { "Off" })
+            }
+            MenuItem::Back => "Back".to_string(),
+        }
+    }
+}
+
+///
+/// メニュー項目のボタン（index は上からの順番）
+///
+#[derive(Component)]
+struct MenuButton {
+    item: MenuItem,
+    index: usize,
+}
+
+///
+/// メニュー項目のボタンの文字
+///
+#[derive(Component)]
+struct MenuLabel(MenuItem);
+
+///
+/// 選択中のメニュー項目の順番
+///
+#[derive(Resource, Default)]
+struct MenuFocus(usize);
+
+///
+/// MainMenu 遷移時のセットアップ関数
+///
+fn setup_title_menu (
+    mut commands: Commands,
+    mut menu_focus: ResMut<MenuFocus>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    achievements: Res<Achievements>,
+) {
+    menu_focus.0 = 0;
+    // ボーナス譜面は実績で解放されるまで表示しない
+    let items: Vec<MenuItem> = [
+        MenuItem::Play,
+        MenuItem::Versus,
+        MenuItem::BonusSong,
+        MenuItem::Practice,
+        MenuItem::Tutorial,
+        MenuItem::Options,
+        MenuItem::Achievements,
+        MenuItem::Quit,
+    ]
+    .into_iter()
+    .filter(|item| *item != MenuItem::BonusSong || achievements.is_bonus_song_unlocked())
+    .collect();
+    spawn_menu(&mut commands, &asset_server, &settings, AppState::MainMenu, TITLE_TEXT, &items);
+}
+
+///
+/// Options 遷移時のセットアップ関数
+///
+fn setup_options_menu (
+    mut commands: Commands,
+    mut menu_focus: ResMut<MenuFocus>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    menu_focus.0 = 0;
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        &settings,
+        AppState::Options,
+        "Options",
+        &[
+            MenuItem::ScrollSpeed,
+            MenuItem::BackgroundDim,
+            MenuItem::Gauge,
+            MenuItem::NoteSkin,
+            MenuItem::MusicVolume,
+            MenuItem::SfxVolume,
+            MenuItem::MenuSound,
+            MenuItem::Back,
+        ],
+    );
+}
+
+///
+/// 見出しとメニュー項目のボタンを縦に並べて生成する
+///
+fn spawn_menu (
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    state: AppState,
+    title: &str,
+    items: &[MenuItem],
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    // Esc で戻れるのはタイトル以外の画面だけ
+    let hint = match state {
+        AppState::MainMenu => "Up/Down: Select  Left/Right: Change  Enter/Space: Decide",
+        _ => "Up/Down: Select  Left/Right: Change  Enter/Space: Decide  Esc: Back",
+    };
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            StateScoped(state),
+        ))
+        .with_children(|parent| {
+            // 見出し
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font: font.clone(),
+                    font_size: TITLE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(TITLE_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(MENU_BUTTON_MARGIN * 4.0)),
+                    ..default()
+                },
+            ));
+
+            // メニュー項目
+            for (index, &item) in items.iter().enumerate() {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(MENU_BUTTON_SIZE.x),
+                            height: Val::Px(MENU_BUTTON_SIZE.y),
+                            margin: UiRect::all(Val::Px(MENU_BUTTON_MARGIN)),
+                            border: UiRect::all(Val::Px(2.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(MENU_BUTTON_COLOR),
+                        BorderColor(MENU_BORDER_COLOR),
+                        MenuButton { item, index },
+                    ))
+                    .with_child((
+                        Text::new(item.label(settings)),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: MENU_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(MENU_TEXT_COLOR),
+                        MenuLabel(item),
+                    ));
+            }
+
+            // 操作の案内
+            parent.spawn((
+                Text::new(hint),
+                TextFont {
+                    font: font.clone(),
+                    font_size: MENU_HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(MENU_HINT_COLOR),
+                Node {
+                    margin: UiRect::top(Val::Px(MENU_BUTTON_MARGIN * 4.0)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+///
+/// メニューの効果音を鳴らす
+///
+fn play_menu_sound (commands: &mut Commands, asset_server: &AssetServer, settings: &Settings, speed: f32) {
+    if settings.menu_sound {
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(MENU_SOUND)),
+            PlaybackSettings::DESPAWN.with_speed(speed),
+            AudioBus::Ui,
+        ));
+    }
+}
+
+///
+/// マウスを乗せた項目を選択する
+///
+fn focus_with_mouse (
+    mut commands: Commands,
+    mut menu_focus: ResMut<MenuFocus>,
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction == Interaction::Hovered && menu_focus.0 != button.index {
+            menu_focus.0 = button.index;
+            play_menu_sound(&mut commands, &asset_server, &settings, MENU_MOVE_SOUND_SPEED);
+        }
+    }
+}
+
+///
+/// 上下キーで項目を選択する
+///
+fn focus_with_keyboard (
+    mut commands: Commands,
+    mut menu_focus: ResMut<MenuFocus>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    button_query: Query<&MenuButton>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    let count = button_query.iter().count();
+    if count == 0 {
+        return;
+    }
+    let focus = if keyboard_input.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        (menu_focus.0 + count - 1) % count
+    } else if keyboard_input.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        (menu_focus.0 + 1) % count
+    } else {
+        return;
+    };
+    menu_focus.0 = focus;
+    play_menu_sound(&mut commands, &asset_server, &settings, MENU_MOVE_SOUND_SPEED);
+}
+
+///
+/// 選択中の項目を決定する（Enter / Space / クリック / 左右キー）
+///
+#[allow(clippy::too_many_arguments)]
+fn activate_menu_item (
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut game_mode: ResMut<GameMode>,
+    mut settings: ResMut<Settings>,
+    mut demo_exit: DemoExit,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    menu_focus: Res<MenuFocus>,
+    button_query: Query<&MenuButton>,
+    clicked_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    current_state: Res<State<AppState>>,
+    asset_server: Res<AssetServer>,
+    achievements: Res<Achievements>,
+) {
+    // Esc でオプション画面から戻る
+    if keyboard_input.just_pressed(KeyCode::Escape) && *current_state.get() == AppState::Options {
+        play_menu_sound(&mut commands, &asset_server, &settings, MENU_DECIDE_SOUND_SPEED);
+        next_state.set(AppState::MainMenu);
+        return;
+    }
+
+    // 押しっぱなしで何度も決定しないよう，押した瞬間だけを見る
+    let clicked = clicked_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.item);
+    let focused = button_query
+        .iter()
+        .find(|button| button.index == menu_focus.0)
+        .map(|button| button.item);
+
+    // 左右キーは設定値の変更だけに使う
+    let step = if keyboard_input.any_just_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
+        -1
+    } else if keyboard_input.any_just_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
+        1
+    } else {
+        0
+    };
+    let decided = keyboard_input.any_just_pressed([KeyCode::Enter, KeyCode::Space]);
+
+    let (item, step) = match (clicked, focused) {
+        (Some(item), _) => (item, 1),
+        (None, Some(item)) if decided => (item, 1),
+        (
+            None,
+            Some(
+                item @ (MenuItem::ScrollSpeed
+                | MenuItem::BackgroundDim
+                | MenuItem::Gauge
+                | MenuItem::NoteSkin
+                | MenuItem::MusicVolume
+                | MenuItem::SfxVolume
+                | MenuItem::MenuSound),
+            ),
+        ) if step != 0 => (item, step),
+        _ => return,
+    };
+    play_menu_sound(&mut commands, &asset_server, &settings, MENU_DECIDE_SOUND_SPEED);
+
+    match item {
+        MenuItem::Play | MenuItem::Versus | MenuItem::BonusSong | MenuItem::Practice | MenuItem::Tutorial => {
+            if item == MenuItem::BonusSong {
+                commands.insert_resource(BonusSongSelected);
+            }
+            *game_mode = match item {
+                MenuItem::Versus => GameMode::Versus,
+                MenuItem::Practice => GameMode::Practice,
+                MenuItem::Tutorial => GameMode::Tutorial,
+                _ => GameMode::Single,
+            };
+            next_state.set(AppState::Loading);
+        }
+        MenuItem::Options => next_state.set(AppState::Options),
+        MenuItem::Achievements => next_state.set(AppState::Achievements),
+        // ランチャーから起動した時はランチャーに戻る
+        MenuItem::Quit => demo_exit.quit(),
+        MenuItem::ScrollSpeed => settings.scroll_speed = cycle(&SCROLL_SPEEDS, settings.scroll_speed, step),
+        MenuItem::BackgroundDim => settings.background_dim = cycle(&BACKGROUND_DIMS, settings.background_dim, step),
+        MenuItem::Gauge => {
+            settings.gauge = match settings.gauge {
+                GaugeType::Normal => GaugeType::Hard,
+                GaugeType::Hard => GaugeType::Normal,
+            };
+        }
+        MenuItem::NoteSkin => {
+            // 解放済みの見た目の中から選ぶ
+            let skins: Vec<NoteSkin> = NoteSkin::ALL
+                .into_iter()
+                .filter(|skin| achievements.is_skin_unlocked(*skin))
+                .collect();
+            let index = skins.iter().position(|skin| *skin == settings.note_skin).unwrap_or(0) as isize;
+            settings.note_skin = skins[(index + step).rem_euclid(skins.len() as isize) as usize];
+        }
+        MenuItem::MusicVolume => settings.music_volume = cycle(&VOLUMES, settings.music_volume, step),
+        MenuItem::SfxVolume => settings.sfx_volume = cycle(&VOLUMES, settings.sfx_volume, step),
+        MenuItem::MenuSound => settings.menu_sound = !settings.menu_sound,
+        MenuItem::Back => next_state.set(AppState::MainMenu),
+    }
+}
+
+///
+/// 設定値の候補の中で，現在の値から step 個先の値を返す（端まで行ったら反対側に戻る）
+///
+fn cycle(values: &[f32], current: f32, step: isize) -> f32 {
+    let index = values.iter().position(|value| *value == current).unwrap_or(0) as isize;
+    values[(index + step).rem_euclid(values.len() as isize) as usize]
+}
+
+///
+/// 選択中の項目を強調表示する
+///
+fn highlight_focus (
+    menu_focus: Res<MenuFocus>,
+    mut button_query: Query<(&MenuButton, &mut BackgroundColor, &mut BorderColor)>,
+) {
+    for (button, mut background_color, mut border_color) in &mut button_query {
+        let focused = button.index == menu_focus.0;
+        background_color.0 = if focused { MENU_FOCUS_COLOR } else { MENU_BUTTON_COLOR };
+        border_color.0 = if focused { MENU_FOCUS_BORDER_COLOR } else { MENU_BORDER_COLOR };
+    }
+}
+
+///
+/// 設定値が変わったら項目の文字を更新する
+///
+fn update_menu_labels (
+    settings: Res<Settings>,
+    mut label_query: Query<(&MenuLabel, &mut Text)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (label, mut text) in &mut label_query {
+        **text = label.0.label(&settings);
+    }
+}
@@ -0,0 +1,175 @@
+use std::env;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, IoTaskPool, Task};
+use study_rust::leaderboard::{self, ScoreEntry};
+use super::{AppState, GameMode};
+use super::chart::Chart;
+use super::results::SongResult;
+
+// リーダーボードサーバーの URL（例 : http://192.168.0.10:7878）を指定する環境変数
+// 指定がなければリーダーボードは使わない
+const LEADERBOARD_ENV_VAR: &str = "TIMING_GAME_LEADERBOARD";
+// 登録するプレイヤー名を指定する環境変数
+const PLAYER_ENV_VAR: &str = "TIMING_GAME_PLAYER";
+const DEFAULT_PLAYER_NAME: &str = "Player";
+
+// 結果画面に表示する件数
+const LEADERBOARD_LIMIT: usize = 10;
+
+// 結果画面の右側に表示する
+const LEADERBOARD_FONT_SIZE: f32 = 18.0;
+const LEADERBOARD_COLOR: Color = Color::BLACK;
+const LEADERBOARD_ERROR_COLOR: Color = Color::srgb(0.8, 0.0, 0.0);
+const LEADERBOARD_MARGIN: f32 = 12.0;
+
+///
+/// 共有リーダーボード
+/// 1 人プレイの結果をサーバーに登録し，結果画面に曲の上位のスコアを表示する
+///
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(config) = LeaderboardConfig::from_env() else {
+            return;
+        };
+        app.insert_resource(config)
+            .add_systems(
+                OnEnter(AppState::Results),
+                submit_score.run_if(resource_equals(GameMode::Single)),
+            )
+            .add_systems(
+                Update,
+                receive_leaderboard.run_if(in_state(AppState::Results).and(resource_exists::<LeaderboardRequest>)),
+            );
+    }
+}
+
+///
+/// リーダーボードの設定
+///
+#[derive(Resource, Clone)]
+struct LeaderboardConfig {
+    endpoint: String,
+    player_name: String,
+}
+
+impl LeaderboardConfig {
+    ///
+    /// 環境変数から設定を読み込む（URL の指定がなければ None）
+    ///
+    fn from_env() -> Option<Self> {
+        let endpoint = env::var(LEADERBOARD_ENV_VAR).ok().filter(|endpoint| !endpoint.is_empty())?;
+        let player_name = env::var(PLAYER_ENV_VAR)
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PLAYER_NAME.to_string());
+        Some(LeaderboardConfig { endpoint, player_name })
+    }
+}
+
+///
+/// 通信中のリクエスト
+/// 登録した自分のスコアと，登録後に取得した上位のスコア（またはエラー）
+///
+#[derive(Resource)]
+struct LeaderboardRequest {
+    entry: ScoreEntry,
+    task: Task<Result<Vec<ScoreEntry>, String>>,
+}
+
+///
+/// 結果画面のリーダーボードの表示
+///
+#[derive(Component)]
+struct LeaderboardText;
+
+///
+/// Results 遷移時に，スコアを登録して上位のスコアを取得する
+/// 通信は別スレッドで行い，結果画面はすぐに表示する
+///
+fn submit_score (
+    mut commands: Commands,
+    config: Res<LeaderboardConfig>,
+    song_result: Res<SongResult>,
+    chart: Res<Chart>,
+    asset_server: Res<AssetServer>,
+) {
+    // 途中で終わった曲は登録しない
+    if song_result.failed {
+        return;
+    }
+    let Some(player) = song_result.players.first() else {
+        return;
+    };
+    let entry = ScoreEntry {
+        player: config.player_name.clone(),
+        song_hash: chart.hash(),
+        score: player.score,
+        perfect: player.judgements.perfect,
+        good: player.judgements.good,
+        miss: player.judgements.miss,
+        replay_checksum: player.replay_checksum.clone(),
+    };
+
+    let endpoint = config.endpoint.clone();
+    let submitted = entry.clone();
+    let task = IoTaskPool::get().spawn(async move {
+        leaderboard::submit(&endpoint, &submitted)
+            .and_then(|_| leaderboard::fetch_top(&endpoint, &submitted.song_hash, LEADERBOARD_LIMIT))
+            .map_err(|e| e.to_string())
+    });
+    commands.insert_resource(LeaderboardRequest { entry, task });
+
+    commands.spawn((
+        Text::new("Leaderboard\nSending ..."),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: LEADERBOARD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(LEADERBOARD_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(LEADERBOARD_MARGIN),
+            right: Val::Px(LEADERBOARD_MARGIN),
+            ..default()
+        },
+        LeaderboardText,
+        StateScoped(AppState::Results),
+    ));
+}
+
+///
+/// 通信が終わったら上位のスコアを表示する
+/// 今回登録したスコアには印を付ける
+///
+fn receive_leaderboard (
+    mut commands: Commands,
+    mut request: ResMut<LeaderboardRequest>,
+    mut text_query: Query<(&mut Text, &mut TextColor), With<LeaderboardText>>,
+) {
+    let Some(result) = block_on(poll_once(&mut request.task)) else {
+        return;
+    };
+
+    let (text, color) = match result {
+        Ok(entries) => {
+            let mut lines = vec!["Leaderboard".to_string()];
+            lines.extend(entries.iter().enumerate().map(|(index, entry)| {
+                let mark = if *entry == request.entry { " <" } else { "" };
+                format!("{:>2}. {}  {}{}", index + 1, entry.player, entry.score, mark)
+            }));
+            if entries.is_empty() {
+                lines.push("No scores yet".to_string());
+            }
+            (lines.join("\n"), LEADERBOARD_COLOR)
+        }
+        Err(e) => (format!("Leaderboard unavailable\n{}", e), LEADERBOARD_ERROR_COLOR),
+    };
+    for (mut leaderboard_text, mut text_color) in &mut text_query {
+        **leaderboard_text = text.clone();
+        text_color.0 = color;
+    }
+    commands.remove_resource::<LeaderboardRequest>();
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use super::{AppState, GameMode, Judgements, PlayerSide};
+
+// 結果画面の設定
+const RESULTS_FONT_SIZE: f32 = 50.0;
+const RESULTS_COLOR: Color = Color::BLACK;
+const RESULTS_JUDGEMENT_FONT_SIZE: f32 = 28.0;
+const RESULTS_FAILED_COLOR: Color = Color::srgb(0.8, 0.0, 0.0);
+const RESULTS_HINT_FONT_SIZE: f32 = 24.0;
+const RESULTS_HINT_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+
+///
+/// 曲の結果
+/// PlayingGame の終了時に記録し，結果画面で表示する
+///
+#[derive(Resource, Clone, Default)]
+pub struct SongResult {
+    pub game_mode: GameMode,
+    // HARD ゲージがなくなって途中で終わった
+    pub failed: bool,
+    pub players: Vec<PlayerResult>,
+}
+
+///
+/// プレイヤー 1 人分の結果
+/// * replay_checksum : String     打鍵した時刻の列のチェックサム
+///
+#[derive(Clone, Debug)]
+pub struct PlayerResult {
+    pub player_side: PlayerSide,
+    pub score: isize,
+    pub judgements: Judgements,
+    pub replay_checksum: String,
+}
+
+impl SongResult {
+    ///
+    /// 2 人対戦の勝者（1 人プレイ・引き分けの場合は None）
+    ///
+    pub fn winner(&self) -> Option<PlayerSide> {
+        if self.game_mode != GameMode::Versus {
+            return None;
+        }
+        let max_score = self.players.iter().map(|player| player.score).max()?;
+        let mut leaders = self.players.iter().filter(|player| player.score == max_score);
+        match (leaders.next(), leaders.next()) {
+            (Some(player), None) => Some(player.player_side),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Results 遷移時のセットアップ関数
+/// スコア・判定の回数と（2 人対戦の場合は）勝者を表示する
+///
+pub fn setup_results_screen (
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    song_result: Res<SongResult>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let mut results = commands.spawn((
+        Text::default(),
+        TextFont {
+            font: font.clone(),
+            font_size: RESULTS_FONT_SIZE,
+            ..default()
+        },
+        TextColor(RESULTS_COLOR),
+        TextLayout::new_with_justify(JustifyText::Center),
+        StateScoped(AppState::Results),
+    ));
+
+    // スコアは大きく，判定の回数はその下に小さく表示する
+    let font_of = |font_size: f32| TextFont {
+        font: font.clone(),
+        font_size,
+        ..default()
+    };
+    results.with_children(|parent| {
+        if song_result.failed {
+            parent.spawn((TextSpan::new("Failed\n"), font_of(RESULTS_FONT_SIZE), TextColor(RESULTS_FAILED_COLOR)));
+        }
+        for player in &song_result.players {
+            let score = match song_result.game_mode {
+                GameMode::Single | GameMode::Practice | GameMode::Tutorial => format!("Score: {}\n", player.score),
+                GameMode::Versus => format!("{} Score: {}\n", player.player_side.label(), player.score),
+            };
+            parent.spawn((TextSpan::new(score), font_of(RESULTS_FONT_SIZE), TextColor(RESULTS_COLOR)));
+            let judgements = format!(
+                "Perfect {}  Good {}  Miss {}\n",
+                player.judgements.perfect, player.judgements.good, player.judgements.miss
+            );
+            parent.spawn((TextSpan::new(judgements), font_of(RESULTS_JUDGEMENT_FONT_SIZE), TextColor(RESULTS_COLOR)));
+        }
+        if song_result.game_mode == GameMode::Versus {
+            let winner = match song_result.winner() {
+                Some(player_side) => format!("{} Win!", player_side.label()),
+                None => "Draw".to_string(),
+            };
+            parent.spawn((TextSpan::new(winner), font_of(RESULTS_FONT_SIZE), TextColor(RESULTS_COLOR)));
+        }
+    });
+
+    // 操作の案内
+    commands.spawn((
+        Text::new("Space: Back to Title"),
+        TextFont {
+            font,
+            font_size: RESULTS_HINT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(RESULTS_HINT_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        StateScoped(AppState::Results),
+    ));
+}
@@ -0,0 +1,259 @@
+///
+/// テンポマップ（BPM 変化・停止・スクロール速度変化）
+/// 拍と時刻の変換，および描画用のスクロール位置の計算を行う
+///
+// BPM の指定がない譜面の BPM
+pub const DEFAULT_BPM: f64 = 120.0;
+
+///
+/// テンポマップ上のイベント
+///
+#[derive(Clone, Copy, Debug)]
+pub enum TempoEvent {
+    // この拍から BPM が変わる
+    Bpm(f64),
+    // この拍で指定秒数だけ譜面が止まる（この拍のノーツは停止の前に判定する）
+    Stop(f64),
+    // この拍から見た目のスクロール速度が変わる（1.0 が等速）
+    Scroll(f64),
+}
+
+///
+/// テンポマップ
+/// イベントは (拍, イベント) の形で拍の昇順に保持する
+///
+#[derive(Clone, Debug)]
+pub struct TempoMap {
+    initial_bpm: f64,
+    events: Vec<(f64, TempoEvent)>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        TempoMap::new(DEFAULT_BPM)
+    }
+}
+
+impl TempoMap {
+    ///
+    /// 一定の BPM のテンポマップを作成する
+    ///
+    pub fn new(initial_bpm: f64) -> Self {
+        TempoMap {
+            initial_bpm,
+            events: Vec::new(),
+        }
+    }
+
+    ///
+    /// 曲の開始時の BPM
+    ///
+    pub fn initial_bpm(&self) -> f64 {
+        self.initial_bpm
+    }
+
+    ///
+    /// イベントを追加する
+    ///
+    pub fn add(&mut self, beat: f64, event: TempoEvent) {
+        // 同じ拍のイベントは追加した順に処理する
+        let index = self.events.partition_point(|(b, _)| *b <= beat);
+        self.events.insert(index, (beat, event));
+    }
+
+    ///
+    /// 指定した拍の BPM
+    ///
+    pub fn bpm_at(&self, beat: f64) -> f64 {
+        self.events
+            .iter()
+            .take_while(|(b, _)| *b <= beat)
+            .fold(self.initial_bpm, |bpm, (_, event)| match event {
+                TempoEvent::Bpm(new_bpm) => *new_bpm,
+                _ => bpm,
+            })
+    }
+
+    ///
+    /// 指定した拍のスクロール速度
+    ///
+    pub fn scroll_at(&self, beat: f64) -> f64 {
+        self.events
+            .iter()
+            .take_while(|(b, _)| *b <= beat)
+            .fold(1.0, |speed, (_, event)| match event {
+                TempoEvent::Scroll(new_speed) => *new_speed,
+                _ => speed,
+            })
+    }
+
+    ///
+    /// 拍の位置を曲の開始からの時刻（秒）に変換する
+    ///
+    pub fn beat_to_time(&self, beat: f64) -> f64 {
+        let mut time = 0.0;
+        let mut current_beat = 0.0;
+        let mut bpm = self.initial_bpm;
+        for &(event_beat, event) in &self.events {
+            // 停止はその拍のノーツより後に起こるので，同じ拍の停止は含めない
+            if event_beat >= beat {
+                break;
+            }
+            time += (event_beat - current_beat) * 60.0 / bpm;
+            current_beat = event_beat;
+            match event {
+                TempoEvent::Bpm(new_bpm) => bpm = new_bpm,
+                TempoEvent::Stop(seconds) => time += seconds,
+                TempoEvent::Scroll(_) => {}
+            }
+        }
+        time + (beat - current_beat) * 60.0 / bpm
+    }
+
+    ///
+    /// 曲の開始からの時刻（秒）を拍の位置に変換する
+    /// 停止中の時刻は停止した拍になる
+    ///
+    pub fn time_to_beat(&self, time: f64) -> f64 {
+        let mut current_time = 0.0;
+        let mut current_beat = 0.0;
+        let mut bpm = self.initial_bpm;
+        for &(event_beat, event) in &self.events {
+            let event_time = current_time + (event_beat - current_beat) * 60.0 / bpm;
+            if time < event_time {
+                break;
+            }
+            current_time = event_time;
+            current_beat = event_beat;
+            match event {
+                TempoEvent::Bpm(new_bpm) => bpm = new_bpm,
+                TempoEvent::Stop(seconds) => {
+                    if time < current_time + seconds {
+                        return current_beat;
+                    }
+                    current_time += seconds;
+                }
+                TempoEvent::Scroll(_) => {}
+            }
+        }
+        current_beat + (time - current_time) * bpm / 60.0
+    }
+
+    ///
+    /// 拍の位置を見た目のスクロール位置（スクロール速度を掛けた拍数）に変換する
+    ///
+    pub fn beat_to_scroll(&self, beat: f64) -> f64 {
+        let mut position = 0.0;
+        let mut current_beat = 0.0;
+        let mut speed = 1.0;
+        for &(event_beat, event) in &self.events {
+            if event_beat >= beat {
+                break;
+            }
+            if let TempoEvent::Scroll(new_speed) = event {
+                position += (event_beat - current_beat) * speed;
+                current_beat = event_beat;
+                speed = new_speed;
+            }
+        }
+        position + (beat - current_beat) * speed
+    }
+
+    ///
+    /// 曲の開始からの時刻（秒）を見た目のスクロール位置に変換する
+    ///
+    pub fn time_to_scroll(&self, time: f64) -> f64 {
+        self.beat_to_scroll(self.time_to_beat(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    ///
+    /// 拍 → 時刻 → 拍 で元に戻るか（0 〜 max_beat を 1/4 拍ごとに調べる）
+    ///
+    fn assert_round_trip(tempo: &TempoMap, max_beat: f64) {
+        let mut beat = 0.0;
+        while beat <= max_beat {
+            assert_close(tempo.time_to_beat(tempo.beat_to_time(beat)), beat);
+            beat += 0.25;
+        }
+    }
+
+    #[test]
+    fn constant_bpm() {
+        let tempo = TempoMap::new(120.0);
+        assert_close(tempo.beat_to_time(4.0), 2.0);
+        assert_close(tempo.time_to_beat(3.0), 6.0);
+        assert_close(tempo.bpm_at(100.0), 120.0);
+        assert_close(tempo.beat_to_scroll(7.5), 7.5);
+        assert_round_trip(&tempo, 16.0);
+    }
+
+    #[test]
+    fn bpm_change_mid_song() {
+        let mut tempo = TempoMap::new(120.0);
+        tempo.add(4.0, TempoEvent::Bpm(240.0));
+        assert_close(tempo.bpm_at(3.9), 120.0);
+        assert_close(tempo.bpm_at(4.0), 240.0);
+        assert_close(tempo.beat_to_time(4.0), 2.0);
+        assert_close(tempo.beat_to_time(8.0), 3.0);
+        assert_close(tempo.time_to_beat(2.5), 6.0);
+        assert_round_trip(&tempo, 16.0);
+    }
+
+    #[test]
+    fn stop_holds_the_beat() {
+        let mut tempo = TempoMap::new(120.0);
+        tempo.add(4.0, TempoEvent::Stop(1.0));
+        // 停止した拍のノーツは停止の前に判定する
+        assert_close(tempo.beat_to_time(4.0), 2.0);
+        assert_close(tempo.beat_to_time(5.0), 3.5);
+        // 停止している間は同じ拍のまま
+        assert_close(tempo.time_to_beat(2.0), 4.0);
+        assert_close(tempo.time_to_beat(2.5), 4.0);
+        assert_close(tempo.time_to_beat(2.999), 4.0);
+        assert_close(tempo.time_to_beat(3.0), 4.0);
+        assert_close(tempo.time_to_beat(3.5), 5.0);
+        assert_round_trip(&tempo, 16.0);
+    }
+
+    #[test]
+    fn several_events_on_the_same_beat() {
+        let mut tempo = TempoMap::new(120.0);
+        tempo.add(4.0, TempoEvent::Bpm(240.0));
+        tempo.add(4.0, TempoEvent::Stop(1.0));
+        tempo.add(4.0, TempoEvent::Bpm(60.0));
+        // 同じ拍のイベントは追加した順に処理する
+        assert_close(tempo.bpm_at(4.0), 60.0);
+        assert_close(tempo.beat_to_time(4.0), 2.0);
+        assert_close(tempo.beat_to_time(5.0), 4.0);
+        assert_close(tempo.time_to_beat(2.5), 4.0);
+        assert_close(tempo.time_to_beat(4.0), 5.0);
+        assert_round_trip(&tempo, 16.0);
+    }
+
+    #[test]
+    fn zero_scroll_segment_freezes_the_position_only() {
+        let mut tempo = TempoMap::new(120.0);
+        tempo.add(2.0, TempoEvent::Scroll(0.0));
+        tempo.add(4.0, TempoEvent::Scroll(1.0));
+        assert_close(tempo.beat_to_scroll(2.0), 2.0);
+        assert_close(tempo.beat_to_scroll(3.0), 2.0);
+        assert_close(tempo.beat_to_scroll(4.0), 2.0);
+        assert_close(tempo.beat_to_scroll(5.0), 3.0);
+        assert_close(tempo.time_to_scroll(1.5), 2.0);
+        assert_close(tempo.scroll_at(1.9), 1.0);
+        assert_close(tempo.scroll_at(3.0), 0.0);
+        assert_close(tempo.scroll_at(4.0), 1.0);
+        // スクロール速度は拍と時刻の変換に影響しない
+        assert_close(tempo.beat_to_time(5.0), 2.5);
+        assert_round_trip(&tempo, 16.0);
+    }
+}
@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use bevy::color::palettes::css;
+use super::{
+    swap_chart, AppState, GameMode, Judgements, Settings, SongClock, GOOD_WINDOW, JUDGE_LINE_X, NOTE_SPAWN_X,
+    NOTE_SPEED, SLIDER_GOOD_RANGE, SLIDER_PERFECT_RANGE, SLIDER_SIZE,
+};
+use super::chart::{Chart, ChartNote};
+use super::tempo::TempoEvent;
+
+// 説明の表示
+const PROMPT_FONT_SIZE: f32 = 26.0;
+const PROMPT_COLOR: Color = Color::BLACK;
+const PROMPT_BOTTOM: f32 = 60.0;
+
+// 判定場所を指し示す枠と見出し
+const HIGHLIGHT_COLOR: Color = Color::Srgba(css::DODGER_BLUE);
+const HIGHLIGHT_LINE_WIDTH: f32 = 3.0;
+const HIGHLIGHT_MARGIN: f32 = 16.0;
+const HIGHLIGHT_FONT_SIZE: f32 = 22.0;
+const HIGHLIGHT_Z: f32 = 4.0;
+
+// ノーツを流し始めるまでの時間に足す余裕（画面右端から判定ラインまで流れてくる時間に加える）
+const NOTES_LEAD_MARGIN: f32 = 0.5;
+
+///
+/// 指し示す判定場所
+///
+#[derive(Clone, Copy, Debug)]
+enum Zone {
+    Good,
+    Perfect,
+}
+
+///
+/// 必要な判定
+///
+#[derive(Clone, Copy, Debug)]
+enum Required {
+    // "可" 以上
+    Good,
+    // "良" のみ
+    Perfect,
+}
+
+///
+/// チュートリアルの手順
+///
+#[derive(Clone, Copy, Debug)]
+enum TutorialStep {
+    // 説明を表示し，Enter / Space が押されるまで待つ
+    Message(&'static str),
+    // 判定場所を枠で指し示す（None で消す）
+    Highlight(Option<Zone>),
+    // ノーツの見た目の速さを変える（1.0 が等速）
+    ScrollSpeed(f64),
+    // interval 秒ごとに count 個のノーツを流し，required 以上の判定を hits 回取れるまで繰り返す
+    Notes { count: usize, interval: f32, hits: usize, required: Required },
+}
+
+impl TutorialStep {
+    ///
+    /// 入力などを待たずにすぐ次の手順に進むか
+    ///
+    fn is_instant(self) -> bool {
+        matches!(self, TutorialStep::Highlight(_) | TutorialStep::ScrollSpeed(_))
+    }
+}
+
+// チュートリアルの台本
+const TUTORIAL_SCRIPT: &[TutorialStep] = &[
+    TutorialStep::Message("Welcome to the Timing Game!\nRed notes flow from right to left."),
+    TutorialStep::Highlight(Some(Zone::Good)),
+    TutorialStep::Message("This grey area is the GOOD zone.\nPress Space while a note is inside it."),
+    TutorialStep::ScrollSpeed(0.5),
+    TutorialStep::Message("Let's try with slow notes first."),
+    TutorialStep::Notes { count: 4, interval: 1.5, hits: 3, required: Required::Good },
+    TutorialStep::Highlight(Some(Zone::Perfect)),
+    TutorialStep::Message("The gold area in the middle is the PERFECT zone.\nHitting it gives twice the points."),
+    TutorialStep::Notes { count: 4, interval: 1.5, hits: 2, required: Required::Perfect },
+    TutorialStep::Highlight(None),
+    TutorialStep::ScrollSpeed(1.0),
+    TutorialStep::Message("Now at normal speed.\nPressing when no note is near scores nothing."),
+    TutorialStep::Notes { count: 8, interval: 0.5, hits: 6, required: Required::Good },
+    TutorialStep::Message("Tutorial complete! You are ready to play."),
+];
+
+///
+/// チュートリアル
+/// TUTORIAL_SCRIPT の手順を順番に実行する
+/// ノーツは空の譜面に追加していくので，判定やスコアは通常のプレイと同じ仕組みで行う
+///
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnExit(AppState::MainMenu),
+            swap_in_tutorial_chart.run_if(resource_equals(GameMode::Tutorial)),
+        )
+        .add_systems(
+            OnEnter(AppState::PlayingGame),
+            setup_tutorial.run_if(resource_equals(GameMode::Tutorial)),
+        )
+        .add_systems(
+            Update,
+            run_tutorial
+                .after(super::tick_song_clock)
+                .before(super::spawn_notes)
+                .run_if(in_state(AppState::PlayingGame).and(resource_equals(GameMode::Tutorial))),
+        );
+    }
+}
+
+///
+/// チュートリアルの進み具合
+/// * step : usize                 実行中の手順
+/// * entered : bool               手順の開始時の処理を済ませたか
+/// * retry : bool                 ノーツの手順をやり直しているか
+/// * notes_end : f32              流したノーツの判定が全て終わる時刻
+/// * baseline : Judgements        ノーツを流し始めた時点の判定の回数
+///
+#[derive(Resource, Default)]
+struct TutorialProgress {
+    step: usize,
+    entered: bool,
+    retry: bool,
+    notes_end: f32,
+    baseline: Judgements,
+}
+
+///
+/// 説明の表示
+///
+#[derive(Component)]
+struct TutorialPrompt;
+
+///
+/// 判定場所を指し示す枠
+///
+#[derive(Component)]
+struct ZoneHighlight;
+
+///
+/// 遊ぶ予定だった譜面を退避し，ノーツのない譜面に差し替える
+///
+fn swap_in_tutorial_chart (mut commands: Commands, mut chart: ResMut<Chart>) {
+    let tutorial_chart = Chart {
+        title: "Tutorial".to_string(),
+        ..default()
+    };
+    swap_chart(&mut commands, &mut chart, tutorial_chart);
+}
+
+///
+/// PlayingGame 遷移時のセットアップ関数
+/// 説明の表示を生成し，台本の最初から始める
+///
+fn setup_tutorial (mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TutorialProgress::default());
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: PROMPT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(PROMPT_COLOR),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            bottom: Val::Px(PROMPT_BOTTOM),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        TutorialPrompt,
+        StateScoped(AppState::PlayingGame),
+    ));
+}
+
+///
+/// ノーツが画面右端から判定ラインまで流れてくる時間に余裕を足したもの
+/// スクロール速度（設定と譜面）や BPM が遅いほど長くなる
+///
+fn notes_lead_time(chart: &Chart, settings: &Settings, now: f32) -> f32 {
+    let beat = chart.tempo.time_to_beat(now as f64);
+    let tempo_ratio = chart.tempo.scroll_at(beat) * chart.tempo.bpm_at(beat) / chart.tempo.initial_bpm();
+    let pixels_per_second = -NOTE_SPEED * settings.scroll_speed * tempo_ratio as f32;
+    (NOTE_SPAWN_X - JUDGE_LINE_X) / pixels_per_second.max(f32::EPSILON) + NOTES_LEAD_MARGIN
+}
+
+///
+/// 台本の手順を進める
+///
+#[allow(clippy::too_many_arguments)]
+fn run_tutorial (
+    mut commands: Commands,
+    mut progress: ResMut<TutorialProgress>,
+    mut chart: ResMut<Chart>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut prompt_query: Query<&mut Text, With<TutorialPrompt>>,
+    highlight_query: Query<Entity, With<ZoneHighlight>>,
+    judgements_query: Query<&Judgements>,
+    song_clock: Res<SongClock>,
+    settings: Res<Settings>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+) {
+    let now = song_clock.0.elapsed_secs();
+    let judgements = judgements_query.iter().next().copied().unwrap_or_default();
+    let step_label = |step: usize| format!("[{}/{}] ", step + 1, TUTORIAL_SCRIPT.len());
+    let mut set_prompt = |text: String| {
+        for mut prompt in &mut prompt_query {
+            **prompt = text.clone();
+        }
+    };
+
+    // すぐに終わる手順はまとめて実行し，入力を待つ手順で止まる
+    loop {
+        let Some(&step) = TUTORIAL_SCRIPT.get(progress.step) else {
+            next_state.set(AppState::MainMenu);
+            return;
+        };
+
+        // 手順の開始時の処理
+        if !progress.entered {
+            progress.entered = true;
+            match step {
+                TutorialStep::Message(message) => {
+                    set_prompt(format!("{}{}\n(Enter / Space: Next)", step_label(progress.step), message));
+                }
+                TutorialStep::Highlight(zone) => {
+                    for entity in &highlight_query {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                    if let Some(zone) = zone {
+                        spawn_zone_highlight(&mut commands, &asset_server, zone);
+                    }
+                }
+                TutorialStep::ScrollSpeed(speed) => {
+                    let beat = chart.tempo.time_to_beat(now as f64);
+                    chart.tempo.add(beat, TempoEvent::Scroll(speed));
+                }
+                TutorialStep::Notes { count, interval, .. } => {
+                    let start = now + notes_lead_time(&chart, &settings, now);
+                    chart.notes.extend((0..count).map(|i| ChartNote {
+                        time: start + i as f32 * interval,
+                        keysound: None,
+                    }));
+                    progress.notes_end = chart.last_note_time() + GOOD_WINDOW;
+                    progress.baseline = judgements;
+                }
+            }
+        }
+
+        // 手順の終了の判定
+        match step {
+            TutorialStep::Message(_) => {
+                if !keyboard_input.any_just_pressed([KeyCode::Enter, KeyCode::Space]) {
+                    return;
+                }
+            }
+            TutorialStep::Highlight(_) | TutorialStep::ScrollSpeed(_) => {}
+            TutorialStep::Notes { hits, required, .. } => {
+                let successes = match required {
+                    Required::Good => {
+                        judgements.perfect + judgements.good - progress.baseline.perfect - progress.baseline.good
+                    }
+                    Required::Perfect => judgements.perfect - progress.baseline.perfect,
+                };
+                let target = match required {
+                    Required::Good => "GOOD or PERFECT",
+                    Required::Perfect => "PERFECT",
+                };
+                let retry = if progress.retry { "Try again! " } else { "" };
+                set_prompt(format!(
+                    "{}{}Hit {} notes with {} ({}/{})",
+                    step_label(progress.step),
+                    retry,
+                    hits,
+                    target,
+                    successes.min(hits),
+                    hits
+                ));
+                if now <= progress.notes_end {
+                    return;
+                }
+                if successes < hits {
+                    // 足りなければ同じノーツをもう一度流す
+                    progress.entered = false;
+                    progress.retry = true;
+                    return;
+                }
+            }
+        }
+
+        progress.step += 1;
+        progress.entered = false;
+        progress.retry = false;
+        if !step.is_instant() {
+            // 同じフレームの入力で次の手順まで進まないようにする
+            return;
+        }
+    }
+}
+
+///
+/// 判定場所の左右に縦線を引き，上に見出しを表示する
+///
+fn spawn_zone_highlight (commands: &mut Commands, asset_server: &AssetServer, zone: Zone) {
+    let (range, label) = match zone {
+        Zone::Good => (SLIDER_GOOD_RANGE, "GOOD zone"),
+        Zone::Perfect => (SLIDER_PERFECT_RANGE, "PERFECT zone"),
+    };
+    let height = SLIDER_SIZE.y + HIGHLIGHT_MARGIN * 2.0;
+    for side in [-1.0, 1.0] {
+        commands.spawn((
+            Sprite {
+                color: HIGHLIGHT_COLOR,
+                custom_size: Some(Vec2::new(HIGHLIGHT_LINE_WIDTH, height)),
+                ..default()
+            },
+            Transform::from_xyz(JUDGE_LINE_X + side * range / 2.0, 0.0, HIGHLIGHT_Z),
+            ZoneHighlight,
+            StateScoped(AppState::PlayingGame),
+        ));
+    }
+    commands.spawn((
+        Text2d::new(label),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: HIGHLIGHT_FONT_SIZE,
+            ..default()
+        },
+        TextColor(HIGHLIGHT_COLOR),
+        Transform::from_xyz(JUDGE_LINE_X, height / 2.0 + HIGHLIGHT_FONT_SIZE, HIGHLIGHT_Z),
+        ZoneHighlight,
+        StateScoped(AppState::PlayingGame),
+    ));
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, process};
+use study_rust::leaderboard::{self, ScoreEntry, SCORES_PATH};
+
+// 待ち受けるアドレスと保存先の既定値
+const DEFAULT_ADDRESS: &str = "0.0.0.0:7878";
+const DEFAULT_SCORES_FILE: &str = "save/leaderboard.tsv";
+// 取得件数の既定値と上限
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+// リクエスト本文の上限（スコア 1 行分なので小さくてよい）
+const MAX_BODY_SIZE: usize = 4096;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+///
+/// リクエスト
+///
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: String,
+}
+
+///
+/// タイミングゲームのリーダーボードサーバー
+/// LAN 内での利用を想定し，スコアはタブ区切りのテキストファイルに追記して保存する
+///
+/// 使い方 : leaderboard_server [ADDRESS] [FILE]
+///
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("Usage : {} [ADDRESS (default {})] [FILE (default {})]", args[0], DEFAULT_ADDRESS, DEFAULT_SCORES_FILE);
+        return;
+    }
+    let address = args.get(1).map_or(DEFAULT_ADDRESS, String::as_str);
+    let scores_file = PathBuf::from(args.get(2).map_or(DEFAULT_SCORES_FILE, String::as_str));
+
+    let mut entries = load_scores(&scores_file).unwrap_or_else(|e| {
+        eprintln!("Problem reading {} : {}", scores_file.display(), e);
+        process::exit(1);
+    });
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+        eprintln!("Problem listening on {} : {}", address, e);
+        process::exit(1);
+    });
+    println!("Leaderboard server listening on http://{} ({} scores in {})", address, entries.len(), scores_file.display());
+
+    // 1 件ずつ順番に処理する（LAN 内の少人数で使う前提）
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| handle_connection(stream, &mut entries, &scores_file));
+        if let Err(e) = result {
+            eprintln!("Connection error : {}", e);
+        }
+    }
+}
+
+///
+/// 保存済みのスコアを読み込む
+/// ファイルがなければ空から始め，読めない行は警告を出して飛ばす
+///
+fn load_scores(path: &Path) -> io::Result<Vec<ScoreEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+        match ScoreEntry::parse_line(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Warning : {}:{} : {}", path.display(), index + 1, e),
+        }
+    }
+    Ok(entries)
+}
+
+///
+/// スコアをファイルに追記する
+///
+fn append_score(path: &Path, entry: &ScoreEntry) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry.to_line())
+}
+
+///
+/// 1 つの接続を処理する
+///
+fn handle_connection(mut stream: TcpStream, entries: &mut Vec<ScoreEntry>, scores_file: &Path) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let (status, body) = match read_request(&stream) {
+        Ok(request) => route(&request, entries, scores_file),
+        Err(message) => (400, message),
+    };
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )
+}
+
+///
+/// リクエストを読み込む
+///
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line".to_string());
+    };
+
+    // ヘッダは Content-Length だけを見る
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| format!("invalid Content-Length '{}'", value.trim()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(format!("body is larger than {} bytes", MAX_BODY_SIZE));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+///
+/// リクエストを処理し，ステータスコードと本文を返す
+///
+fn route(request: &Request, entries: &mut Vec<ScoreEntry>, scores_file: &Path) -> (u16, String) {
+    if request.path != SCORES_PATH {
+        return (404, format!("unknown path {}\n", request.path));
+    }
+    match request.method.as_str() {
+        "GET" => {
+            let Some(song_hash) = request.query.get("song") else {
+                return (400, "missing query parameter 'song'\n".to_string());
+            };
+            let limit = request
+                .query
+                .get("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(DEFAULT_LIMIT)
+                .min(MAX_LIMIT);
+            let body: String = leaderboard::top_scores(entries, song_hash, limit)
+                .iter()
+                .map(|entry| entry.to_line() + "\n")
+                .collect();
+            (200, body)
+        }
+        "POST" => {
+            let entry = match ScoreEntry::parse_line(request.body.trim()) {
+                Ok(entry) => entry,
+                Err(e) => return (400, format!("{}\n", e)),
+            };
+            // 同じプレイを 2 度登録しない
+            if entries.iter().any(|registered| registered.is_same_play(&entry)) {
+                return (409, "this play is already registered\n".to_string());
+            }
+            if let Err(e) = append_score(scores_file, &entry) {
+                eprintln!("Problem writing {} : {}", scores_file.display(), e);
+                return (500, "failed to save score\n".to_string());
+            }
+            println!("{} scored {} on {}", entry.player, entry.score, entry.song_hash);
+            entries.push(entry);
+            (201, "ok\n".to_string())
+        }
+        _ => (405, format!("method {} is not allowed\n", request.method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn post(entry: &ScoreEntry) -> Request {
+        Request {
+            method: "POST".to_string(),
+            path: SCORES_PATH.to_string(),
+            query: HashMap::new(),
+            body: entry.to_line() + "\n",
+        }
+    }
+
+    fn entry(player: &str, press_times_ms: &[u32]) -> ScoreEntry {
+        ScoreEntry {
+            player: player.to_string(),
+            song_hash: "song".to_string(),
+            score: 0,
+            perfect: 0,
+            good: 0,
+            miss: 8,
+            replay_checksum: leaderboard::replay_checksum(press_times_ms),
+        }
+    }
+
+    #[test]
+    fn duplicate_plays_are_rejected_but_plays_without_input_are_not() {
+        let scores_file = env::temp_dir().join(format!("study_rust_leaderboard_{}.tsv", process::id()));
+        let _ = fs::remove_file(&scores_file);
+        let mut entries = Vec::new();
+
+        let played = entry("alice", &[500, 1000, 1500]);
+        assert_eq!(route(&post(&played), &mut entries, &scores_file).0, 201);
+        assert_eq!(route(&post(&played), &mut entries, &scores_file).0, 409);
+        // 1 度も打鍵しなかったプレイは誰のものでも同じチェックサムになる
+        assert_eq!(route(&post(&entry("alice", &[])), &mut entries, &scores_file).0, 201);
+        assert_eq!(route(&post(&entry("bob", &[])), &mut entries, &scores_file).0, 201);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(load_scores(&scores_file).unwrap(), entries);
+        fs::remove_file(&scores_file).unwrap();
+    }
+}
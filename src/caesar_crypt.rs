@@ -1,38 +1,47 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+// execute で使うシフト数
+const EXECUTE_SHIFT: i16 = 3;
+
 ///
-/// シーザー暗号化の実行
+/// シーザー暗号の実行のエラー
 ///
-#[allow(unused)]
-pub fn execute(input_str: &str) {
-    // シーザー暗号
-    let tgt_str = input_str;
-    println!("{}", tgt_str);
-    let enc_str = caesar_rotate(&tgt_str, 3);
-    println!("{}", enc_str);
-    let dec_str = caesar_rotate(&enc_str, -3);
-    println!("{}", dec_str);
+#[derive(Debug)]
+pub enum CaesarError {
+    // 結果の書き出しの失敗
+    Io(io::Error),
+}
+
+impl fmt::Display for CaesarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaesarError::Io(e) => write!(f, "Problem writing result : {}", e),
+        }
+    }
+}
+
+impl Error for CaesarError {}
 
-    let result_str: String = run_caesar_encrypt(tgt_str, 3);
-    println!("{}", result_str);
+impl From<io::Error> for CaesarError {
+    fn from(e: io::Error) -> Self {
+        CaesarError::Io(e)
+    }
 }
 
 ///
-/// 文字列の暗号化（シーザー暗号）
-/// ### Arguments
-/// * text : &str        暗号化したい文字列
-/// * shift : i32        文字列をシフトする数
-/// ### Return
-/// * enc_str : String   文字列の暗号化
+/// シーザー暗号化の実行
+/// 元の文字列，暗号化した文字列，それを復号した文字列を 1 行ずつ表示する
 ///
-fn run_caesar_encrypt(text: &str, shift: i16) -> String {
-    let mut result: String = String::new();
-    for c in text.chars() {
-        let mut c_char = c;
-        if c_char.is_alphabetic() {
-            c_char = (c_char as u8 + shift as u8) as char;
-        }
-        result.push(c_char);
-    }
-    result
+pub fn execute(input_str: &str) -> Result<(), CaesarError> {
+    let enc_str = caesar_rotate(input_str, EXECUTE_SHIFT);
+    let dec_str = caesar_rotate(&enc_str, -EXECUTE_SHIFT);
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", input_str)?;
+    writeln!(out, "{}", enc_str)?;
+    writeln!(out, "{}", dec_str)?;
+    Ok(())
 }
 
 ///
@@ -41,7 +50,7 @@ fn run_caesar_encrypt(text: &str, shift: i16) -> String {
 /// (I) shift: i16 -> 文字のシフト数
 /// (R) result: String -> 暗号化後の文字列
 ///
-fn caesar_rotate(text: &str, shift: i16) -> String {
+pub fn caesar_rotate(text: &str, shift: i16) -> String {
     // 変換結果の文字列を取得するオブジェクト
     let mut result = String::new();
 
@@ -56,11 +65,42 @@ fn caesar_rotate(text: &str, shift: i16) -> String {
         // 大文字の時のシフト処理
         if 'A' <= ch && ch <= 'Z' {
             let a = 'A' as i16;
-            let enc = (((ch as i16) - a + shift + 26) % 26 + a) as u8;
-            result.push(enc as char);
+            // シフト数は 26 を超えても負でもよい
+            let enc = ((ch as i16) - a + shift.rem_euclid(26)) % 26 + a;
+            result.push(enc as u8 as char);
         } else {
             result.push(ch as char);
         }
     }
     return result;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_and_wraps_within_the_alphabet() {
+        assert_eq!(caesar_rotate("ABC xyz", 3), "DEF ABC");
+        assert_eq!(caesar_rotate("HELLO, WORLD!", 13), "URYYB, JBEYQ!");
+    }
+
+    #[test]
+    fn negative_shift_reverses_the_rotation() {
+        assert_eq!(caesar_rotate("DEF", -3), "ABC");
+        assert_eq!(caesar_rotate(&caesar_rotate("RUST", 5), -5), "RUST");
+        assert_eq!(caesar_rotate("A", i16::MIN), caesar_rotate("A", i16::MIN.rem_euclid(26)));
+    }
+
+    #[test]
+    fn shifts_above_26_wrap_around() {
+        assert_eq!(caesar_rotate("ABC", 26), "ABC");
+        assert_eq!(caesar_rotate("ABC", 29), "DEF");
+        assert_eq!(caesar_rotate("ABC", -55), "XYZ");
+    }
+
+    #[test]
+    fn other_characters_are_kept() {
+        assert_eq!(caesar_rotate("ÿ 1 あ", 3), "ÿ 1 あ");
+    }
+}
@@ -0,0 +1,576 @@
+///
+/// コマンドラインの解析とサブコマンドの実行
+/// 使い方 : study_rust <COMMAND> [OPTIONS] [ARGS]
+///
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+use std::env;
+use study_rust::grep::MatchMode;
+use study_rust::{caesar_crypt, fern, life_game, munou, my_math, Config};
+use crate::{bevy_practice, bevy_timing_game, launcher};
+
+// プログラム名（ヘルプの表示用）
+const PROGRAM_NAME: &str = "study_rust";
+
+// 終了コード（成功は 0）
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+
+///
+/// オプションの定義
+/// * long : &str                 長い名前（--long）
+/// * short : Option<char>        短い名前（-s）
+/// * value : Option<&str>        値の名前（None なら値を取らないフラグ）
+/// * help : &str                 説明
+///
+struct OptionSpec {
+    long: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+///
+/// サブコマンドの定義
+/// * name : &str                 名前
+/// * summary : &str              一覧に表示する説明
+/// * args : &str                 位置引数の書式（ヘルプの表示用）
+/// * options : &[OptionSpec]     受け付けるオプション
+/// * run : fn                    実行する関数
+///
+struct Command {
+    name: &'static str,
+    summary: &'static str,
+    args: &'static str,
+    options: &'static [OptionSpec],
+    run: fn(&Matches) -> Result<(), CliError>,
+}
+
+// 全てのサブコマンドで使える --help
+const HELP_OPTION: OptionSpec = OptionSpec {
+    long: "help",
+    short: Some('h'),
+    value: None,
+    help: "Show this help",
+};
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "fern",
+        summary: "Draw a Barnsley fern into an image file",
+        args: "",
+        options: &[
+            OptionSpec { long: "out", short: Some('o'), value: Some("PATH"), help: "Image file to write (default image.png)" },
+            OptionSpec { long: "depth", short: Some('d'), value: Some("N"), help: "Recursion depth (default 23)" },
+        ],
+        run: run_fern,
+    },
+    Command {
+        name: "fib",
+        summary: "Print the N-th Fibonacci number",
+        args: "[N]",
+        options: &[],
+        run: run_fib,
+    },
+    Command {
+        name: "caesar",
+        summary: "Encrypt or decrypt text with a Caesar cipher (reads stdin without TEXT)",
+        args: "[TEXT...]",
+        options: &[
+            OptionSpec { long: "shift", short: Some('s'), value: Some("N"), help: "Number of letters to shift (default 3)" },
+            OptionSpec { long: "decrypt", short: Some('d'), value: None, help: "Shift backwards to decrypt" },
+        ],
+        run: run_caesar,
+    },
+    Command {
+        name: "life",
+        summary: "Run Conway's Game of Life in the terminal",
+        args: "",
+        options: &[
+            OptionSpec { long: "width", short: Some('W'), value: Some("N"), help: "Grid width (default 90)" },
+            OptionSpec { long: "height", short: Some('H'), value: Some("N"), help: "Grid height (default 30)" },
+            OptionSpec { long: "generations", short: Some('g'), value: Some("N"), help: "Number of generations (default 1000)" },
+            OptionSpec { long: "interval", short: Some('i'), value: Some("MS"), help: "Milliseconds between generations (default 100)" },
+        ],
+        run: run_life,
+    },
+    Command {
+        name: "munou",
+        summary: "Chat with a Markov chain chatbot",
+        args: "",
+        options: &[
+            OptionSpec { long: "corpus", short: Some('c'), value: Some("PATH"), help: "Text file to learn from" },
+        ],
+        run: run_munou,
+    },
+    Command {
+        name: "bevy-practice",
+        summary: "Open the spatial audio sandbox",
+        args: "",
+        options: &[],
+        run: run_bevy_practice,
+    },
+    Command {
+        name: "timing",
+        summary: "Play the timing game",
+        args: "",
+        options: &[
+            OptionSpec { long: "chart", short: Some('c'), value: Some("PATH"), help: "Chart file to play (.bms / .bme / .osu)" },
+        ],
+        run: run_timing,
+    },
+    Command {
+        name: "launcher",
+        summary: "Choose a Bevy demo from a menu",
+        args: "",
+        options: &[],
+        run: run_launcher,
+    },
+    Command {
+        name: "search",
+        summary: "Print the lines of files that match a regex or string (searches directories recursively)",
+        args: "QUERY [PATH...]",
+        options: &[
+            OptionSpec { long: "fixed-strings", short: Some('F'), value: None, help: "Treat QUERY as a plain string, not a regex" },
+            OptionSpec { long: "word-regexp", short: Some('w'), value: None, help: "Match only whole words" },
+            OptionSpec { long: "ignore-case", short: Some('i'), value: None, help: "Ignore case (also set by IGNORE_CASE)" },
+            OptionSpec { long: "invert-match", short: Some('v'), value: None, help: "Print the lines that do not match" },
+            OptionSpec { long: "include", short: None, value: Some("GLOB"), help: "Search only files matching GLOB (repeatable)" },
+            OptionSpec { long: "exclude", short: None, value: Some("GLOB"), help: "Skip files and directories matching GLOB (repeatable)" },
+            OptionSpec { long: "no-ignore", short: None, value: None, help: "Do not read .gitignore / .ignore files" },
+            OptionSpec { long: "threads", short: Some('j'), value: Some("N"), help: "Number of search threads (default: number of CPUs)" },
+            OptionSpec { long: "unordered", short: None, value: None, help: "Print files as soon as they are searched" },
+            OptionSpec { long: "count", short: Some('c'), value: None, help: "Print only the number of matching lines" },
+            OptionSpec { long: "only-matching", short: Some('o'), value: None, help: "Print only the matched parts of each line" },
+            OptionSpec { long: "color", short: None, value: Some("WHEN"), help: "Highlight matches: auto, always or never" },
+            OptionSpec { long: "after-context", short: Some('A'), value: Some("N"), help: "Print N lines after each match" },
+            OptionSpec { long: "before-context", short: Some('B'), value: Some("N"), help: "Print N lines before each match" },
+            OptionSpec { long: "context", short: Some('C'), value: Some("N"), help: "Print N lines before and after each match" },
+        ],
+        run: run_search,
+    },
+];
+
+///
+/// コマンドラインの誤り（終了コード 2）と実行時の誤り（終了コード 1）
+///
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Failure(Box<dyn Error>),
+}
+
+impl<E: Error + 'static> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError::Failure(Box::new(e))
+    }
+}
+
+///
+/// 解析したオプションと位置引数
+/// 値は指定した順に全て入る（フラグは値なしの空で入る）
+///
+struct Matches {
+    options: HashMap<&'static str, Vec<String>>,
+    positionals: Vec<String>,
+}
+
+impl Matches {
+    fn flag(&self, long: &str) -> bool {
+        self.options.contains_key(long)
+    }
+
+    ///
+    /// オプションの値（複数回指定されていれば最後の値）
+    ///
+    fn string(&self, long: &str) -> Option<&str> {
+        self.options.get(long).and_then(|values| values.last()).map(String::as_str)
+    }
+
+    ///
+    /// 複数回指定できるオプションの全ての値
+    ///
+    fn strings(&self, long: &str) -> Vec<String> {
+        self.options.get(long).cloned().unwrap_or_default()
+    }
+
+    ///
+    /// オプションの値を型に変換する（指定がなければ default）
+    ///
+    fn value<T: FromStr>(&self, long: &str, default: T) -> Result<T, CliError>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.string(long) {
+            Some(value) => value
+                .parse()
+                .map_err(|e| CliError::Usage(format!("invalid value '{}' for --{} : {}", value, long, e))),
+            None => Ok(default),
+        }
+    }
+}
+
+///
+/// 引数を解析してサブコマンドを実行し，終了コードを返す
+///
+pub fn run(args: &[String]) -> ExitCode {
+    let Some(name) = args.get(1) else {
+        eprint!("{}", general_help());
+        return ExitCode::from(EXIT_USAGE);
+    };
+    if matches!(name.as_str(), "help" | "-h" | "--help") {
+        // help <COMMAND> でサブコマンドのヘルプ
+        match args.get(2).and_then(|name| find_command(name)) {
+            Some(command) => print!("{}", command_help(command)),
+            None => print!("{}", general_help()),
+        }
+        return ExitCode::SUCCESS;
+    }
+    let Some(command) = find_command(name) else {
+        eprintln!("error: unknown command '{}'\n", name);
+        eprint!("{}", general_help());
+        return ExitCode::from(EXIT_USAGE);
+    };
+
+    let result = parse_options(command, &args[2..]).and_then(|matches| {
+        if matches.flag(HELP_OPTION.long) {
+            print!("{}", command_help(command));
+            return Ok(());
+        }
+        (command.run)(&matches)
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n", message);
+            eprint!("{}", command_help(command));
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(CliError::Failure(e)) => {
+            eprintln!("Application error : {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+///
+/// サブコマンドの一覧
+///
+fn general_help() -> String {
+    let mut help = format!("Usage: {} <COMMAND> [OPTIONS]\n\nCommands:\n", PROGRAM_NAME);
+    for command in COMMANDS {
+        help += &format!("  {:<15} {}\n", command.name, command.summary);
+    }
+    help += &format!("\nRun '{} help <COMMAND>' or '{} <COMMAND> --help' for the options of a command.\n", PROGRAM_NAME, PROGRAM_NAME);
+    help
+}
+
+///
+/// サブコマンドのヘルプ
+///
+fn command_help(command: &Command) -> String {
+    let usage = format!("{} {} [OPTIONS] {}", PROGRAM_NAME, command.name, command.args);
+    let mut help = format!("{}\n\nUsage: {}\n\nOptions:\n", command.summary, usage.trim_end());
+    for option in command.options.iter().chain([&HELP_OPTION]) {
+        let short = option.short.map_or("    ".to_string(), |short| format!("-{}, ", short));
+        let long = match option.value {
+            Some(value) => format!("--{} <{}>", option.long, value),
+            None => format!("--{}", option.long),
+        };
+        help += &format!("  {}{:<22} {}\n", short, long, option.help);
+    }
+    help
+}
+
+///
+/// オプションと位置引数を分ける
+/// --name value / --name=value / -n value / -nvalue / -abc の形を受け付け，-- より後は全て位置引数にする
+///
+fn parse_options(command: &Command, args: &[String]) -> Result<Matches, CliError> {
+    let mut matches = Matches {
+        options: HashMap::new(),
+        positionals: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            matches.positionals.extend(args.by_ref().cloned());
+            break;
+        }
+        let (spec, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let spec = command
+                .options
+                .iter()
+                .chain([&HELP_OPTION])
+                .find(|spec| spec.long == name)
+                .ok_or_else(|| CliError::Usage(format!("unknown option '--{}'", name)))?;
+            (spec, value)
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty() && !is_number(shorts)) {
+            // -iv は -i -v と同じ，値を取るオプションより後ろはその値（-A3 / -iA3）
+            let mut value_option = None;
+            for (i, short) in shorts.char_indices() {
+                let spec = command
+                    .options
+                    .iter()
+                    .chain([&HELP_OPTION])
+                    .find(|spec| spec.short == Some(short))
+                    .ok_or_else(|| CliError::Usage(format!("unknown option '-{}' in '{}'", short, arg)))?;
+                if spec.value.is_some() {
+                    let rest = &shorts[i + short.len_utf8()..];
+                    value_option = Some((spec, Some(rest.to_string()).filter(|rest| !rest.is_empty())));
+                    break;
+                }
+                matches.options.entry(spec.long).or_default();
+            }
+            match value_option {
+                Some(option) => option,
+                None => continue,
+            }
+        } else {
+            // "-" だけや負の数は位置引数として扱う
+            matches.positionals.push(arg.clone());
+            continue;
+        };
+
+        let value = match (spec.value, inline_value) {
+            (None, None) => None,
+            (None, Some(_)) => return Err(CliError::Usage(format!("option '--{}' does not take a value", spec.long))),
+            (Some(_), Some(value)) => Some(value),
+            (Some(name), None) => Some(
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| CliError::Usage(format!("option '--{}' requires a value <{}>", spec.long, name)))?,
+            ),
+        };
+        matches.options.entry(spec.long).or_default().extend(value);
+    }
+    Ok(matches)
+}
+
+///
+/// 負の数（-5 や -0.5）か
+///
+fn is_number(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_digit() || c == '.') && text.parse::<f64>().is_ok()
+}
+
+///
+/// 位置引数を取らないサブコマンドで，位置引数が渡されていたら誤りにする
+///
+fn no_positionals(matches: &Matches) -> Result<(), CliError> {
+    match matches.positionals.first() {
+        Some(arg) => Err(CliError::Usage(format!("unexpected argument '{}'", arg))),
+        None => Ok(()),
+    }
+}
+
+fn run_fern(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    let out = matches.string("out").unwrap_or(fern::SAVE_FILE);
+    let depth = matches.value("depth", fern::DEPTH)?;
+    fern::run_draw_fern(Path::new(out), depth).map_err(|e| match e {
+        fern::FernError::Image(_) => CliError::Failure(Box::new(e)),
+        _ => CliError::Usage(e.to_string()),
+    })?;
+    println!("Saved {}", out);
+    Ok(())
+}
+
+fn run_fib(matches: &Matches) -> Result<(), CliError> {
+    let n = match matches.positionals.as_slice() {
+        [] => 10,
+        [n] => n
+            .parse()
+            .map_err(|e| CliError::Usage(format!("invalid number '{}' : {}", n, e)))?,
+        [_, extra, ..] => return Err(CliError::Usage(format!("unexpected argument '{}'", extra))),
+    };
+    // 計算できない項は入力の誤り
+    my_math::run_fib(n).map_err(|e| CliError::Usage(e.to_string()))
+}
+
+fn run_caesar(matches: &Matches) -> Result<(), CliError> {
+    let shift: i16 = matches.value("shift", 3)?;
+    // 26 文字で一周するので 0 〜 25 に収めてから向きを変える（-32768 はそのまま反転すると溢れる）
+    let shift = shift.rem_euclid(26);
+    let shift = if matches.flag("decrypt") { (26 - shift) % 26 } else { shift };
+    if matches.positionals.is_empty() {
+        for line in io::stdin().lock().lines() {
+            println!("{}", caesar_crypt::caesar_rotate(&line?, shift));
+        }
+    } else {
+        println!("{}", caesar_crypt::caesar_rotate(&matches.positionals.join(" "), shift));
+    }
+    Ok(())
+}
+
+fn run_life(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    let default = life_game::LifeOptions::default();
+    let options = life_game::LifeOptions {
+        width: matches.value("width", default.width)?,
+        height: matches.value("height", default.height)?,
+        generations: matches.value("generations", default.generations)?,
+        interval: matches
+            .value("interval", default.interval.as_millis() as u64)
+            .map(Duration::from_millis)?,
+    };
+    life_game::run(&options).map_err(|e| match e {
+        life_game::LifeError::Io(_) => CliError::Failure(Box::new(e)),
+        _ => CliError::Usage(e.to_string()),
+    })?;
+    Ok(())
+}
+
+fn run_munou(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    let corpus = matches.string("corpus").unwrap_or(munou::CORPUS_FILE);
+    munou::execute(Path::new(corpus))?;
+    Ok(())
+}
+
+fn run_bevy_practice(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    bevy_practice::run_bevy_sample();
+    Ok(())
+}
+
+fn run_timing(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    // 譜面は環境変数と同じ仕組みで渡す（ランチャーから起動した時と同じ読み込み方にする）
+    if let Some(chart) = matches.string("chart") {
+        env::set_var(bevy_timing_game::CHART_ENV_VAR, chart);
+    }
+    bevy_timing_game::play_game();
+    Ok(())
+}
+
+fn run_launcher(matches: &Matches) -> Result<(), CliError> {
+    no_positionals(matches)?;
+    launcher::run_launcher();
+    Ok(())
+}
+
+///
+/// ファイルを探すプログラム
+///
+fn run_search(matches: &Matches) -> Result<(), CliError> {
+    // Config は先頭にプログラム名が入った引数を受け取る
+    let args: Vec<String> = [String::from("search")]
+        .into_iter()
+        .chain(matches.positionals.iter().cloned())
+        .collect();
+    let mut config = Config::new(&args).map_err(|err| CliError::Usage(err.to_string()))?;
+
+    // -A / -B は -C より優先する
+    let context = matches.value("context", 0)?;
+    if matches.flag("fixed-strings") {
+        config.options.mode = MatchMode::FixedString;
+    }
+    config.options.whole_word = matches.flag("word-regexp");
+    config.options.ignore_case |= matches.flag("ignore-case");
+    config.options.invert_match = matches.flag("invert-match");
+    config.options.after_context = matches.value("after-context", context)?;
+    config.options.before_context = matches.value("before-context", context)?;
+    config.include = matches.strings("include");
+    config.exclude = matches.strings("exclude");
+    config.no_ignore = matches.flag("no-ignore");
+    config.parallel.threads = matches.value("threads", 0)?;
+    config.parallel.ordered = !matches.flag("unordered");
+    config.count = matches.flag("count");
+    config.only_matching = matches.flag("only-matching");
+    // auto は Config::new で決めた（端末なら色を付ける）まま
+    match matches.string("color").unwrap_or("auto") {
+        "auto" => {}
+        "always" => config.color = true,
+        "never" => config.color = false,
+        when => return Err(CliError::Usage(format!("invalid value '{}' for --color : expected auto, always or never", when))),
+    }
+
+    study_rust::run(config).map_err(CliError::Failure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str, args: &[&str]) -> Result<Matches, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_options(find_command(command).unwrap(), &args)
+    }
+
+    fn usage_error(result: Result<Matches, CliError>) -> String {
+        match result {
+            Err(CliError::Usage(message)) => message,
+            Err(CliError::Failure(e)) => panic!("expected a usage error, got failure : {}", e),
+            Ok(_) => panic!("expected a usage error"),
+        }
+    }
+
+    #[test]
+    fn long_options_take_separate_or_inline_values() {
+        let matches = parse("search", &["--after-context", "2", "--before-context=3", "--include=*.rs", "query"]).unwrap();
+        assert_eq!(matches.string("after-context"), Some("2"));
+        assert_eq!(matches.string("before-context"), Some("3"));
+        assert_eq!(matches.strings("include"), vec!["*.rs"]);
+        assert_eq!(matches.positionals, vec!["query"]);
+    }
+
+    #[test]
+    fn short_options_can_be_combined_and_take_attached_values() {
+        let matches = parse("search", &["-iv", "-A3", "-cB", "1", "-iC2", "query"]).unwrap();
+        assert!(matches.flag("ignore-case"));
+        assert!(matches.flag("invert-match"));
+        assert!(matches.flag("count"));
+        assert_eq!(matches.string("after-context"), Some("3"));
+        assert_eq!(matches.string("before-context"), Some("1"));
+        assert_eq!(matches.string("context"), Some("2"));
+        assert_eq!(matches.positionals, vec!["query"]);
+
+        let matches = parse("caesar", &["-s3", "text"]).unwrap();
+        assert_eq!(matches.value("shift", 0).unwrap(), 3);
+    }
+
+    #[test]
+    fn double_dash_ends_the_options() {
+        let matches = parse("search", &["-i", "--", "-v", "--count"]).unwrap();
+        assert!(matches.flag("ignore-case"));
+        assert!(!matches.flag("invert-match"));
+        assert_eq!(matches.positionals, vec!["-v", "--count"]);
+    }
+
+    #[test]
+    fn dash_and_negative_numbers_are_positionals() {
+        let matches = parse("fib", &["-5"]).unwrap();
+        assert_eq!(matches.positionals, vec!["-5"]);
+        let matches = parse("search", &["query", "-"]).unwrap();
+        assert_eq!(matches.positionals, vec!["query", "-"]);
+    }
+
+    #[test]
+    fn unknown_options_are_usage_errors() {
+        assert!(usage_error(parse("search", &["--colour", "query"])).contains("'--colour'"));
+        assert!(usage_error(parse("search", &["-x", "query"])).contains("'-x'"));
+        assert!(usage_error(parse("search", &["-ix", "query"])).contains("'-x' in '-ix'"));
+    }
+
+    #[test]
+    fn missing_and_unexpected_values_are_usage_errors() {
+        assert!(usage_error(parse("search", &["query", "-A"])).contains("requires a value"));
+        assert!(usage_error(parse("search", &["query", "--threads"])).contains("requires a value"));
+        assert!(usage_error(parse("search", &["--count=yes", "query"])).contains("does not take a value"));
+    }
+}
@@ -1,23 +1,85 @@
-use image::{Rgb, RgbImage};
+///
+/// シダ植物（バーンズリーのシダ）の描画
+///
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use image::{ImageError, Rgb, RgbImage};
+
+// 画像の大きさ・保存先・再帰の深さの既定値
+pub const WIDTH: u32 = 1024;
+pub const HEIGHT: u32 = 1024;
+pub const SAVE_FILE: &str = "image.png";
+pub const DEPTH: i64 = 23;
+
+///
+/// シダの描画のエラー
+///
+#[derive(Debug)]
+pub enum FernError {
+    // 幅か高さが 0
+    InvalidSize(u32, u32),
+    // 再帰の深さが負
+    NegativeDepth(i64),
+    // 画像の保存の失敗
+    Image(ImageError),
+}
+
+impl fmt::Display for FernError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FernError::InvalidSize(width, height) => write!(f, "invalid image size {}x{}", width, height),
+            FernError::NegativeDepth(depth) => write!(f, "depth must not be negative: {}", depth),
+            FernError::Image(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for FernError {}
 
-const WIDTH: u32 = 1024;
-const HEIGHT: u32 = 1024;
-const SAVE_FILE: &str = "image.png";
+impl From<ImageError> for FernError {
+    fn from(e: ImageError) -> Self {
+        FernError::Image(e)
+    }
+}
 
 ///
 /// シダ植物の描画
+/// ### Arguments
+/// * save_file : &Path      保存する画像ファイル（拡張子で形式が決まる）
+/// * depth : i64            再帰の深さ（大きいほど細かくなるが時間がかかる）
 ///
-#[allow(unused)]
-pub fn run_draw_fern() {
+pub fn run_draw_fern(save_file: &Path, depth: i64) -> Result<(), FernError> {
     // シダの描画
+    let img = render_fern(WIDTH, HEIGHT, depth)?;
+
+    // ファイルに保存する
+    img.save(save_file)?;
+    Ok(())
+}
+
+///
+/// シダを描いた画像を作る
+/// ### Arguments
+/// * width : u32            画像の幅
+/// * height : u32           画像の高さ
+/// * depth : i64            再帰の深さ
+/// ### Return
+/// * img : RgbImage         黒地に緑でシダを描いた画像
+///
+pub fn render_fern(width: u32, height: u32, depth: i64) -> Result<RgbImage, FernError> {
+    if width == 0 || height == 0 {
+        return Err(FernError::InvalidSize(width, height));
+    }
+    if depth < 0 {
+        return Err(FernError::NegativeDepth(depth));
+    }
     // 画像バッファの作成
-    let mut img = RgbImage::new(WIDTH, HEIGHT);
+    let mut img = RgbImage::new(width, height);
 
     // 描画
-    draw_fern(&mut img, 23, 0.0, 0.0);
-
-    // ファイルに保存する
-    image::save_buffer(SAVE_FILE, &img, WIDTH, HEIGHT, image::ColorType::Rgb8).unwrap();
+    draw_fern(&mut img, depth, 0.0, 0.0);
+    Ok(img)
 }
 
 ///
@@ -49,9 +111,42 @@ fn draw_fern(img: &mut RgbImage, k: i64, x: f64, y: f64) {
     }
 
     // 座標を計算 --- (*5)
-    let ss = HEIGHT as f64 * 0.97;
-    let xx = (x * ss + (WIDTH as f64) * 0.5) as u32 - 1;
-    let yy = ((HEIGHT as f64) - y * ss) as u32 - 1;
-    // 描画 --- (*6)
-    img.put_pixel(xx, yy, Rgb([120, 255, 110]));
+    let (width, height) = img.dimensions();
+    let ss = height as f64 * 0.97;
+    let xx = ((x * ss + (width as f64) * 0.5) as u32).saturating_sub(1);
+    let yy = ((height as f64 - y * ss) as u32).saturating_sub(1);
+    // 描画 --- (*6)（画像の外にはみ出た点は描かない）
+    if xx < width && yy < height {
+        img.put_pixel(xx, yy, Rgb([120, 255, 110]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_size_is_an_error() {
+        assert!(matches!(render_fern(0, 10, 1), Err(FernError::InvalidSize(0, 10))));
+        assert!(matches!(render_fern(10, 0, 1), Err(FernError::InvalidSize(10, 0))));
+    }
+
+    #[test]
+    fn negative_depth_is_an_error() {
+        assert!(matches!(render_fern(10, 10, -1), Err(FernError::NegativeDepth(-1))));
+    }
+
+    #[test]
+    fn depth_zero_draws_only_the_root() {
+        let img = render_fern(10, 10, 0).unwrap();
+        let green: Vec<(u32, u32)> = img.enumerate_pixels().filter(|(_, _, pixel)| pixel[1] > 0).map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(green, vec![(4, 9)]);
+    }
+
+    #[test]
+    fn deeper_ferns_stay_inside_the_image() {
+        let img = render_fern(40, 30, 8).unwrap();
+        assert_eq!(img.dimensions(), (40, 30));
+        assert!(img.pixels().any(|pixel| pixel[1] > 0));
+    }
 }
@@ -0,0 +1,559 @@
+///
+/// ファイルの中から文字列を含む行を探す（grep のような検索）
+/// search で探した結果をデータで返し，run でそれを表示する
+/// 探す文字列は正規表現（既定）か固定の文字列で，単語全体との一致にもできる
+/// ディレクトリは再帰的に探し，.gitignore などで無視するファイルとバイナリファイルは飛ばす
+/// ファイルは複数のスレッドで並列に探す
+///
+mod ignore;
+mod parallel;
+mod walk;
+
+pub use parallel::{search_files, ParallelOptions};
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use crossterm::style::{StyledContent, Stylize};
+use memmap2::Mmap;
+use regex::{Regex, RegexBuilder};
+
+// 設定されていれば大文字・小文字を区別せずに探す環境変数
+pub const IGNORE_CASE_ENV_VAR: &str = "IGNORE_CASE";
+
+// 離れた行のまとまりの間に表示する区切り
+const GROUP_SEPARATOR: &str = "--";
+
+// バイナリファイルか調べる先頭のバイト数（この中に NUL があればバイナリ）
+const BINARY_CHECK_LEN: usize = 8000;
+
+// これ以上の大きさのファイルはメモリに読み込まずにマップする
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+///
+/// 検索の設定
+/// * query : String              探す文字列
+/// * paths : Vec<PathBuf>        探すファイルかディレクトリ
+/// * options : SearchOptions     行の選び方
+/// * include : Vec<String>       ディレクトリの中で探すファイルのグロブ（空なら全て）
+/// * exclude : Vec<String>       ディレクトリの中で飛ばすファイルとディレクトリのグロブ
+/// * no_ignore : bool            .gitignore / .ignore の規則を使わない
+/// * parallel : ParallelOptions  並列に探す設定
+/// * count : bool                行ではなく一致した行数だけを表示する
+/// * only_matching : bool        行全体ではなく一致した部分だけを表示する
+/// * color : bool                一致した部分や行番号に色を付ける
+///
+pub struct Config {
+    pub query: String,
+    pub paths: Vec<PathBuf>,
+    pub options: SearchOptions,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub no_ignore: bool,
+    pub parallel: ParallelOptions,
+    pub count: bool,
+    pub only_matching: bool,
+    pub color: bool,
+}
+
+impl Config {
+    ///
+    /// [プログラム名, 探す文字列, パス...] の引数から設定を作る（パスがなければ今のディレクトリを探す）
+    /// IGNORE_CASE_ENV_VAR が設定されていれば大文字・小文字を区別しない
+    /// 色は標準出力が端末の時だけ付ける
+    ///
+    pub fn new(args: &[String]) -> Result<Config, &str> {
+        if args.len() < 2 {
+            return Err("Not enough arguments");
+        }
+
+        let query = args[1].clone();
+        let paths = match &args[2..] {
+            [] => vec![PathBuf::from(".")],
+            paths => paths.iter().map(PathBuf::from).collect(),
+        };
+        let options = SearchOptions {
+            ignore_case: env::var_os(IGNORE_CASE_ENV_VAR).is_some(),
+            ..SearchOptions::default()
+        };
+
+        Ok(Config {
+            query,
+            paths,
+            options,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            no_ignore: false,
+            parallel: ParallelOptions::default(),
+            count: false,
+            only_matching: false,
+            color: io::stdout().is_terminal(),
+        })
+    }
+}
+
+///
+/// 探す文字列の解釈
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    // 正規表現
+    #[default]
+    Regex,
+    // 固定の文字列（. や * もそのまま探す）
+    FixedString,
+}
+
+///
+/// 行の選び方
+/// * mode : MatchMode            探す文字列の解釈
+/// * whole_word : bool           前後が単語の文字でない所だけで一致させる
+/// * ignore_case : bool          大文字・小文字を区別しない
+/// * invert_match : bool         含まない行を選ぶ
+/// * before_context : usize      選んだ行の前に表示する行数
+/// * after_context : usize       選んだ行の後に表示する行数
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    pub mode: MatchMode,
+    pub whole_word: bool,
+    pub ignore_case: bool,
+    pub invert_match: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+}
+
+///
+/// 探す文字列を正規表現にしたもの（ファイルごとに作り直さないように分けておく）
+///
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    ///
+    /// 探す文字列と行の選び方から作る（正規表現が誤っていればエラー）
+    ///
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Matcher, regex::Error> {
+        let pattern = match options.mode {
+            MatchMode::Regex => query.to_string(),
+            MatchMode::FixedString => regex::escape(query),
+        };
+        // 前後が単語の文字でないこと（grep -w と同じ）
+        let pattern = if options.whole_word {
+            format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern)
+        } else {
+            pattern
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignore_case)
+            .build()?;
+        Ok(Matcher { regex })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    ///
+    /// 一致した部分のバイト位置（空の一致は除く）
+    ///
+    pub fn find_spans(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .map(|m| m.range())
+            .filter(|span| !span.is_empty())
+            .collect()
+    }
+}
+
+///
+/// 結果の行の種類
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    // 選んだ行
+    Match,
+    // 前後に表示する行
+    Context,
+}
+
+///
+/// 結果の 1 行
+/// * number : usize      行番号（1 から）
+/// * text : String       行の内容（改行は含まない）
+/// * kind : LineKind     選んだ行か前後の行か
+/// * spans : Vec<Range<usize>>   一致した部分のバイト位置（含まない行を選んだ時と前後の行は空）
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchLine {
+    pub number: usize,
+    pub text: String,
+    pub kind: LineKind,
+    pub spans: Vec<Range<usize>>,
+}
+
+///
+/// 文字列を含む行と，その前後の行を探す
+/// ### Arguments
+/// * matcher : &Matcher          探す文字列
+/// * contents : &str             探すテキスト
+/// * options : &SearchOptions    行の選び方
+/// ### Return
+/// * lines : Vec<SearchLine>     行番号の順に並んだ結果（同じ行は 1 度だけ入る）
+///
+pub fn search(matcher: &Matcher, contents: &str, options: &SearchOptions) -> Vec<SearchLine> {
+    let is_match = |line: &str| matcher.is_match(line) != options.invert_match;
+    let context = |number: usize, text: &str| SearchLine {
+        number,
+        text: text.to_string(),
+        kind: LineKind::Context,
+        spans: Vec::new(),
+    };
+
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut result = Vec::new();
+    // まだ結果に入れていない最初の行と，後ろの行を表示する範囲の終わり（どちらも 0 から）
+    let mut next_line = 0;
+    let mut after_end = 0;
+    for (index, &text) in lines.iter().enumerate() {
+        if is_match(text) {
+            let start = index.saturating_sub(options.before_context).max(next_line);
+            for (before, &before_text) in lines.iter().enumerate().take(index).skip(start) {
+                result.push(context(before + 1, before_text));
+            }
+            let spans = if options.invert_match { Vec::new() } else { matcher.find_spans(text) };
+            result.push(SearchLine { number: index + 1, text: text.to_string(), kind: LineKind::Match, spans });
+            next_line = index + 1;
+            after_end = index + 1 + options.after_context;
+        } else if index < after_end {
+            result.push(context(index + 1, text));
+            next_line = index + 1;
+        }
+    }
+    result
+}
+
+///
+/// ファイルを読み込んで探す（MMAP_THRESHOLD 以上のファイルはマップする）
+/// ### Return
+/// * lines : Option<Vec<SearchLine>>     バイナリファイルなら None（UTF-8 でない部分は置き換える）
+///
+pub fn search_file(matcher: &Matcher, path: &Path, options: &SearchOptions) -> io::Result<Option<Vec<SearchLine>>> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len >= MMAP_THRESHOLD {
+        // SAFETY: 読むだけだが，探している間に他のプロセスがファイルを切り詰めるとアクセスで落ちることがある
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(search_bytes(matcher, &mmap, options))
+    } else {
+        let mut bytes = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut bytes)?;
+        Ok(search_bytes(matcher, &bytes, options))
+    }
+}
+
+fn search_bytes(matcher: &Matcher, bytes: &[u8], options: &SearchOptions) -> Option<Vec<SearchLine>> {
+    if is_binary(bytes) {
+        return None;
+    }
+    let contents = String::from_utf8_lossy(bytes);
+    Some(search(matcher, &contents, options))
+}
+
+///
+/// 先頭に NUL があればバイナリファイルとみなす
+///
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_CHECK_LEN).any(|&byte| byte == 0)
+}
+
+///
+/// 検索結果の行数
+///
+pub fn count_matches(lines: &[SearchLine]) -> usize {
+    lines.iter().filter(|line| line.kind == LineKind::Match).count()
+}
+
+///
+/// 色を付ける（color が false ならそのまま）
+///
+fn paint<'a>(text: &'a str, color: bool, style: fn(&'a str) -> StyledContent<&'a str>) -> String {
+    if color { style(text).to_string() } else { text.to_string() }
+}
+
+///
+/// パスと行番号と区切りを付ける
+/// 選んだ行は「パス:行番号:」，前後の行は「パス-行番号-」（path が None ならパスは付けない）
+///
+fn line_prefix(path: Option<&Path>, line: &SearchLine, color: bool) -> String {
+    let separator = match line.kind {
+        LineKind::Match => ":",
+        LineKind::Context => "-",
+    };
+    let separator = paint(separator, color, |s| s.cyan());
+    let number = line.number.to_string();
+    let number = paint(&number, color, |s| s.green());
+    match path {
+        Some(path) => {
+            let path = path.display().to_string();
+            format!("{}{}{}{}", paint(&path, color, |s| s.magenta()), separator, number, separator)
+        }
+        None => format!("{}{}", number, separator),
+    }
+}
+
+///
+/// 一致した部分を赤の太字にした行の内容
+///
+fn highlight(line: &SearchLine, color: bool) -> String {
+    if !color {
+        return line.text.clone();
+    }
+    let mut result = String::new();
+    let mut last = 0;
+    for span in &line.spans {
+        result += &line.text[last..span.start];
+        result += &line.text[span.clone()].red().bold().to_string();
+        last = span.end;
+    }
+    result += &line.text[last..];
+    result
+}
+
+///
+/// 1 つのファイルの結果の行を grep と同じ形で表示する
+///
+fn print_lines(path: Option<&Path>, lines: &[SearchLine], config: &Config, printed: &mut bool) {
+    for output in format_lines(path, lines, config, printed) {
+        println!("{}", output);
+    }
+}
+
+///
+/// 1 つのファイルの結果の行を grep と同じ形の文字列にする
+/// 離れたまとまりの間は「--」，only_matching なら選んだ行の一致した部分を 1 つずつ表示する
+/// ### Arguments
+/// * path : Option<&Path>        行の前に付けるパス
+/// * lines : &[SearchLine]       結果の行
+/// * config : &Config            表示の設定
+/// * printed : &mut bool         前のファイルで何か表示したか（ファイルの間にも「--」を入れる）
+/// ### Return
+/// * outputs : Vec<String>       表示する行（改行は含まない）
+///
+fn format_lines(path: Option<&Path>, lines: &[SearchLine], config: &Config, printed: &mut bool) -> Vec<String> {
+    let with_context = config.options.before_context > 0 || config.options.after_context > 0;
+    let mut outputs = Vec::new();
+    let mut previous: Option<usize> = None;
+    for line in lines {
+        let new_group = match previous {
+            Some(previous) => line.number > previous + 1,
+            None => *printed,
+        };
+        if with_context && new_group {
+            outputs.push(paint(GROUP_SEPARATOR, config.color, |s| s.cyan()));
+        }
+        previous = Some(line.number);
+        *printed = true;
+        if config.only_matching {
+            for span in &line.spans {
+                let matched = &line.text[span.clone()];
+                outputs.push(format!("{}{}", line_prefix(path, line, config.color), paint(matched, config.color, |s| s.red().bold())));
+            }
+        } else {
+            outputs.push(format!("{}{}", line_prefix(path, line, config.color), highlight(line, config.color)));
+        }
+    }
+    outputs
+}
+
+///
+/// パスの中のファイルを探し，結果を表示する
+/// ディレクトリを探す時か複数のパスを探す時は，行の前にパスを付ける
+/// 読めないファイルは警告を表示して飛ばす
+/// 結果はファイルの順に表示する（parallel.ordered が false なら探し終えた順）
+///
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let matcher = Matcher::new(&config.query, &config.options)?;
+    let walk_options = walk::WalkOptions {
+        include: config.include.iter().map(|glob| ignore::Glob::new(glob)).collect::<Result<_, _>>()?,
+        exclude: config.exclude.iter().map(|glob| ignore::Glob::new(glob)).collect::<Result<_, _>>()?,
+        use_ignore_files: !config.no_ignore,
+    };
+    let files = walk::collect_files(&config.paths, &walk_options)?;
+    let with_path = config.paths.len() > 1 || config.paths.iter().any(|path| path.is_dir());
+
+    let mut printed = false;
+    search_files(&matcher, &files, &config.options, &config.parallel, |file, result| {
+        let lines = match result {
+            Ok(Some(lines)) => lines,
+            // バイナリファイル
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Problem reading file {} : {}", file.display(), e);
+                return;
+            }
+        };
+        let path = with_path.then_some(file);
+        if config.count {
+            let count = count_matches(&lines);
+            // grep -c と同じく，一致しなかったファイルも 0 と表示する
+            match path {
+                Some(path) => println!("{}:{}", paint(&path.display().to_string(), config.color, |s| s.magenta()), count),
+                None => println!("{}", count),
+            }
+        } else {
+            print_lines(path, &lines, &config, &mut printed);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_search(query: &str, contents: &str, options: SearchOptions) -> Vec<SearchLine> {
+        let matcher = Matcher::new(query, &options).unwrap();
+        search(&matcher, contents, &options)
+    }
+
+    fn numbers(lines: &[SearchLine], kind: LineKind) -> Vec<usize> {
+        lines.iter().filter(|line| line.kind == kind).map(|line| line.number).collect()
+    }
+
+    const TEXT: &str = "one\ntwo match\nthree\nfour match\nfive\nsix\nseven\neight match\nnine";
+
+    #[test]
+    fn overlapping_context_prints_each_line_once() {
+        let options = SearchOptions {
+            before_context: 2,
+            after_context: 2,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("match", TEXT, options);
+        let all: Vec<usize> = lines.iter().map(|line| line.number).collect();
+        assert_eq!(all, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![2, 4, 8]);
+        assert_eq!(lines[1].spans, vec![4..9]);
+    }
+
+    #[test]
+    fn context_is_cut_at_the_edges() {
+        let options = SearchOptions {
+            before_context: 1,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("one|five", TEXT, options);
+        assert_eq!(numbers(&lines, LineKind::Context), vec![4]);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![1, 5]);
+    }
+
+    #[test]
+    fn invert_match_selects_the_other_lines_without_spans() {
+        let options = SearchOptions {
+            invert_match: true,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("match", TEXT, options);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![1, 3, 5, 6, 7, 9]);
+        assert!(lines.iter().all(|line| line.spans.is_empty()));
+        assert_eq!(count_matches(&lines), 6);
+    }
+
+    #[test]
+    fn count_matches_ignores_context_lines() {
+        let options = SearchOptions {
+            after_context: 3,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("match", TEXT, options);
+        assert!(lines.len() > 3);
+        assert_eq!(count_matches(&lines), 3);
+        assert_eq!(count_matches(&run_search("missing", TEXT, SearchOptions::default())), 0);
+    }
+
+    #[test]
+    fn empty_query_matches_every_line_without_spans() {
+        let lines = run_search("", "a\n\nb\n", SearchOptions::default());
+        assert_eq!(numbers(&lines, LineKind::Match), vec![1, 2, 3]);
+        assert!(lines.iter().all(|line| line.spans.is_empty()));
+    }
+
+    #[test]
+    fn crlf_line_endings_are_not_part_of_the_line() {
+        let lines = run_search("match$", "first\r\nsecond match\r\nthird\r\n", SearchOptions::default());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].number, 2);
+        assert_eq!(lines[0].text, "second match");
+    }
+
+    #[test]
+    fn binary_files_are_skipped() {
+        let matcher = Matcher::new("a", &SearchOptions::default()).unwrap();
+        assert_eq!(search_bytes(&matcher, b"a\0b", &SearchOptions::default()), None);
+        assert_eq!(search_bytes(&matcher, b"a\nb", &SearchOptions::default()).map(|lines| lines.len()), Some(1));
+    }
+
+    #[test]
+    fn fixed_strings_match_regex_characters_literally() {
+        let options = SearchOptions {
+            mode: MatchMode::FixedString,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("a.*b", "a.*b\naxxb\n", options);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![1]);
+        assert_eq!(lines[0].spans, vec![0..4]);
+        // 正規表現としては 2 行とも一致する
+        assert_eq!(count_matches(&run_search("a.*b", "a.*b\naxxb\n", SearchOptions::default())), 2);
+    }
+
+    #[test]
+    fn whole_word_needs_non_word_characters_around_the_match() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let lines = run_search("foo", "foobar\nfoo.bar\nbarfoo\n(foo)\n", options);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![2, 4]);
+        assert_eq!(lines[0].spans, vec![0..3]);
+        assert_eq!(lines[1].spans, vec![1..4]);
+    }
+
+    #[test]
+    fn whole_word_with_a_pattern_starting_with_a_non_word_character() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        // 単語の文字で始まらないパターンも，前の文字が単語の文字でなければ一致する
+        let lines = run_search(r"\.rs", "main.rs\nfile .rs here\n.rsx\n", options);
+        assert_eq!(numbers(&lines, LineKind::Match), vec![2]);
+        assert_eq!(lines[0].spans, vec![5..8]);
+        let options = SearchOptions {
+            mode: MatchMode::FixedString,
+            ..options
+        };
+        assert_eq!(numbers(&run_search("-v", "a -v b\na-v\n-v\n", options), LineKind::Match), vec![1, 3]);
+    }
+
+    #[test]
+    fn only_matching_prints_one_span_per_line() {
+        let mut config = Config::new(&["grep".to_string(), "o+".to_string()]).unwrap();
+        config.only_matching = true;
+        config.color = false;
+        let lines = run_search("o+", "foo boo\nbar\nzoo\n", config.options);
+        let mut printed = false;
+        let outputs = format_lines(None, &lines, &config, &mut printed);
+        assert_eq!(outputs, vec!["1:oo", "1:oo", "3:oo"]);
+        assert!(printed);
+
+        let outputs = format_lines(Some(Path::new("a.txt")), &lines, &config, &mut printed);
+        assert_eq!(outputs, vec!["a.txt:1:oo", "a.txt:1:oo", "a.txt:3:oo"]);
+    }
+}
@@ -0,0 +1,314 @@
+///
+/// .gitignore の形式の無視するパターンと，--include / --exclude のグロブ
+/// パスは全て「/」区切りの，無視ファイルのあるディレクトリ（または検索の起点）からの相対パスで比べる
+///
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+
+// ディレクトリごとに読む無視ファイル（後のものほど優先する）
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+///
+/// グロブのパターン
+/// * *  : / 以外の 0 文字以上
+/// * ?  : / 以外の 1 文字
+/// * ** : / を含む 0 文字以上（**/ や /** の形で使う）
+/// * [abc] [a-z] [!abc] : 文字の集合
+///
+/// / を含むパターンは相対パス全体と，含まないパターンはファイル名と比べる
+///
+#[derive(Clone, Debug)]
+pub struct Glob {
+    regex: Regex,
+    anchored: bool,
+    dir_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, regex::Error> {
+        // 末尾の / はディレクトリだけに一致させる
+        let (pattern, dir_only) = match pattern.strip_suffix('/') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+        // 先頭や途中に / があれば相対パス全体と比べる
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let regex = Regex::new(&format!("^{}$", glob_to_regex(pattern)))?;
+        Ok(Glob { regex, anchored, dir_only })
+    }
+
+    ///
+    /// 相対パス（/ 区切り）が一致するか
+    ///
+    pub fn is_match(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.regex.is_match(relative)
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            self.regex.is_match(name)
+        }
+    }
+}
+
+///
+/// グロブを正規表現に変換する
+///
+fn glob_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                match chars.get(i + 2) {
+                    // **/ : 0 個以上のディレクトリ
+                    Some('/') if at_start => {
+                        regex += "(?:.*/)?";
+                        i += 3;
+                        continue;
+                    }
+                    // 末尾の ** : 中の全て
+                    None if at_start => regex += ".*",
+                    _ => regex += "[^/]*",
+                }
+                i += 2;
+                continue;
+            }
+            '*' => regex += "[^/]*",
+            '?' => regex += "[^/]",
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(len) if len > 0 => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{}", rest),
+                        None => class,
+                    };
+                    regex += &format!("[{}]", class.replace('\\', "\\\\"));
+                    i += len + 2;
+                    continue;
+                }
+                _ => regex += "\\[",
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex += &regex::escape(&chars[i + 1].to_string());
+                i += 1;
+            }
+            c => regex += &regex::escape(&c.to_string()),
+        }
+        i += 1;
+    }
+    regex
+}
+
+///
+/// 無視ファイルの 1 行
+/// * glob : Glob        パターン
+/// * negated : bool     ! で始まる（無視しない）
+///
+#[derive(Clone, Debug)]
+struct Rule {
+    glob: Glob,
+    negated: bool,
+}
+
+///
+/// 1 つのディレクトリの無視ファイルの規則
+/// * base : PathBuf       無視ファイルのあるディレクトリ（上のディレクトリの無視ファイルなら検索の起点）
+/// * prefix : String      無視ファイルのあるディレクトリから base までの相対パス（ふつうは空）
+/// * rules : Vec<Rule>    ファイルに書かれた順の規則（後の規則ほど優先する）
+///
+#[derive(Clone, Debug)]
+pub struct IgnoreFile {
+    base: PathBuf,
+    prefix: String,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    ///
+    /// ディレクトリの .gitignore と .ignore を読み込む（どちらもなければ None）
+    ///
+    pub fn from_dir(dir: &Path) -> io::Result<Option<IgnoreFile>> {
+        let mut text = String::new();
+        for name in IGNORE_FILES {
+            match fs::read_to_string(dir.join(name)) {
+                Ok(contents) => {
+                    text += &contents;
+                    text.push('\n');
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if text.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(IgnoreFile::parse(dir, &text)))
+    }
+
+    ///
+    /// 無視ファイルの内容を読み込む
+    /// 空行と # で始まる行は飛ばし，正規表現にできないパターンは無視する
+    ///
+    pub fn parse(base: &Path, text: &str) -> IgnoreFile {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (pattern, negated) = match line.strip_prefix('!') {
+                    Some(pattern) => (pattern, true),
+                    None => (line, false),
+                };
+                // \# や \! は文字そのもの
+                let pattern = pattern.strip_prefix('\\').filter(|p| p.starts_with(['#', '!'])).unwrap_or(pattern);
+                Glob::new(pattern).ok().map(|glob| Rule { glob, negated })
+            })
+            .collect();
+        IgnoreFile {
+            base: base.to_path_buf(),
+            prefix: String::new(),
+            rules,
+        }
+    }
+
+    ///
+    /// 検索の起点より上のディレクトリの無視ファイルを，起点の下のパスと比べられるようにする
+    /// ### Arguments
+    /// * root : &Path        検索の起点
+    /// * prefix : String     無視ファイルのあるディレクトリから root までの相対パス
+    ///
+    pub fn rebase(self, root: &Path, prefix: String) -> IgnoreFile {
+        IgnoreFile {
+            base: root.to_path_buf(),
+            prefix,
+            rules: self.rules,
+        }
+    }
+
+    ///
+    /// パスを無視するか
+    /// ### Return
+    /// * Some(true) なら無視，Some(false) なら ! で無視しない，None ならこのファイルの規則に一致しない
+    ///
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = match (relative_path(&self.base, path)?, self.prefix.as_str()) {
+            (relative, "") => relative,
+            (relative, prefix) if relative.is_empty() => prefix.to_string(),
+            (relative, prefix) => format!("{}/{}", prefix, relative),
+        };
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.glob.is_match(&relative, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+///
+/// base からの相対パスを / 区切りの文字列にする（base の下になければ None）
+///
+pub fn relative_path(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(pattern: &str, relative: &str, is_dir: bool) -> bool {
+        Glob::new(pattern).unwrap().is_match(relative, is_dir)
+    }
+
+    fn ignored(text: &str, relative: &str, is_dir: bool) -> Option<bool> {
+        let base = Path::new("/repo");
+        IgnoreFile::parse(base, text).matched(&base.join(relative), is_dir)
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_directories() {
+        assert!(glob_matches("**/target", "target", true));
+        assert!(glob_matches("**/target", "a/b/target", true));
+        assert!(glob_matches("src/**/*.rs", "src/main.rs", false));
+        assert!(glob_matches("src/**/*.rs", "src/grep/walk.rs", false));
+        assert!(!glob_matches("src/**/*.rs", "benches/grep.rs", false));
+        assert!(glob_matches("logs/**", "logs/2024/01.txt", false));
+        assert!(!glob_matches("*.rs", "src/main.rs/x", false));
+    }
+
+    #[test]
+    fn trailing_slash_matches_only_directories() {
+        assert!(glob_matches("build/", "build", true));
+        assert!(glob_matches("build/", "src/build", true));
+        assert!(!glob_matches("build/", "build", false));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored() {
+        assert!(glob_matches("/target", "target", true));
+        assert!(!glob_matches("/target", "sub/target", true));
+        assert!(glob_matches("doc/*.md", "doc/a.md", false));
+        assert!(!glob_matches("doc/*.md", "sub/doc/a.md", false));
+        // / を含まなければどの深さのファイル名とも比べる
+        assert!(glob_matches("*.log", "a/b/c.log", false));
+        assert!(glob_matches("?.txt", "dir/a.txt", false));
+        assert!(!glob_matches("?.txt", "dir/ab.txt", false));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(glob_matches("[abc].txt", "b.txt", false));
+        assert!(glob_matches("file[0-9]", "file7", false));
+        assert!(!glob_matches("[!a-z].txt", "q.txt", false));
+        assert!(glob_matches("[!a-z].txt", "Q.txt", false));
+        // 閉じていない [ は文字そのもの
+        assert!(glob_matches("[abc", "[abc", false));
+    }
+
+    #[test]
+    fn later_rules_win_and_negation_unignores() {
+        let text = "*.log\n!keep.log\n";
+        assert_eq!(ignored(text, "debug.log", false), Some(true));
+        assert_eq!(ignored(text, "keep.log", false), Some(false));
+        assert_eq!(ignored(text, "main.rs", false), None);
+        // 後に書いた規則が ! より優先する
+        let text = "!keep.log\n*.log\n";
+        assert_eq!(ignored(text, "keep.log", false), Some(true));
+    }
+
+    #[test]
+    fn comments_blank_lines_and_escapes() {
+        let text = "# comment\n\n\\#notes\n\\!important\n";
+        assert_eq!(ignored(text, "# comment", false), None);
+        assert_eq!(ignored(text, "#notes", false), Some(true));
+        assert_eq!(ignored(text, "!important", false), Some(true));
+    }
+
+    #[test]
+    fn paths_outside_the_base_do_not_match() {
+        let ignore = IgnoreFile::parse(Path::new("/repo"), "*.log");
+        assert_eq!(ignore.matched(Path::new("/elsewhere/a.log"), false), None);
+    }
+
+    #[test]
+    fn rebased_rules_use_the_original_directory() {
+        let ignore = IgnoreFile::parse(Path::new("/repo"), "/src/generated\ntarget/\n").rebase(Path::new("src"), "src".to_string());
+        assert_eq!(ignore.matched(Path::new("src/generated"), true), Some(true));
+        assert_eq!(ignore.matched(Path::new("src/target"), true), Some(true));
+        assert_eq!(ignore.matched(Path::new("src/main.rs"), false), None);
+    }
+}
@@ -0,0 +1,234 @@
+///
+/// 複数のスレッドでファイルを探す
+/// ワーカーはファイルを 1 つずつ取って探し，結果を容量の決まったチャンネルで呼び出し元のスレッドに送る
+/// 順番を守る時は，まだ渡せない結果を溜めすぎないように，ワーカーが先に進める数を制限する
+///
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use super::{search_file, Matcher, SearchLine, SearchOptions};
+
+// スレッドごとのチャンネルの容量
+const CHANNEL_CAPACITY_PER_THREAD: usize = 4;
+// 順番を守る時に，渡し終えたファイルより先に探してよいファイルの数（スレッドごと）
+const ORDER_WINDOW_PER_THREAD: usize = 8;
+
+///
+/// 並列に探す設定
+/// * threads : usize     ワーカーの数（0 なら CPU の数，1 なら呼び出し元のスレッドだけで探す）
+/// * ordered : bool      結果をファイルの順番に渡す（false なら探し終えた順）
+///
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelOptions {
+    pub threads: usize,
+    pub ordered: bool,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            threads: 0,
+            ordered: true,
+        }
+    }
+}
+
+impl ParallelOptions {
+    ///
+    /// 実際に使うワーカーの数
+    ///
+    pub fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |count| count.get()),
+            threads => threads,
+        }
+    }
+}
+
+// 1 つのファイルの結果（search_file と同じ）
+type FileResult = io::Result<Option<Vec<SearchLine>>>;
+
+///
+/// ファイルを並列に探し，結果を呼び出し元のスレッドで 1 つずつ渡す
+/// ### Arguments
+/// * matcher : &Matcher                  探す文字列
+/// * files : &[PathBuf]                  探すファイル
+/// * options : &SearchOptions            行の選び方
+/// * parallel : &ParallelOptions         並列に探す設定
+/// * on_result : FnMut(&Path, FileResult)    ファイルごとの結果を受け取る関数
+///
+pub fn search_files<F>(matcher: &Matcher, files: &[PathBuf], options: &SearchOptions, parallel: &ParallelOptions, mut on_result: F)
+where
+    F: FnMut(&Path, FileResult),
+{
+    let threads = parallel.thread_count().min(files.len());
+    if threads <= 1 {
+        for file in files {
+            on_result(file, search_file(matcher, file, options));
+        }
+        return;
+    }
+
+    // 次に取るファイルと，呼び出し元に渡し終えたファイルの数
+    let next_file = AtomicUsize::new(0);
+    let delivery = Mutex::new(Delivery::default());
+    let delivery_changed = Condvar::new();
+    let window = threads * ORDER_WINDOW_PER_THREAD;
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::sync_channel::<(usize, FileResult)>(threads * CHANNEL_CAPACITY_PER_THREAD);
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (next_file, delivery, delivery_changed) = (&next_file, &delivery, &delivery_changed);
+            scope.spawn(move || loop {
+                let index = next_file.fetch_add(1, Ordering::Relaxed);
+                if index >= files.len() {
+                    break;
+                }
+                if parallel.ordered {
+                    // 渡し終えたファイルから window 以上先なら，追いつくまで待つ
+                    let mut state = lock(delivery);
+                    while index >= state.delivered + window && !state.stopped {
+                        state = delivery_changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+                    }
+                    if state.stopped {
+                        break;
+                    }
+                }
+                let result = search_file(matcher, &files[index], options);
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        // 全てのワーカーが終わればチャンネルが閉じる
+        drop(sender);
+        let _stop = StopWorkers {
+            delivery: &delivery,
+            delivery_changed: &delivery_changed,
+        };
+
+        // 順番を守る時は，次に渡すファイルの結果が届くまで溜めておく
+        let mut pending: BTreeMap<usize, FileResult> = BTreeMap::new();
+        let mut delivered = 0;
+        for (index, result) in receiver {
+            if !parallel.ordered {
+                on_result(&files[index], result);
+                continue;
+            }
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&delivered) {
+                on_result(&files[delivered], result);
+                delivered += 1;
+            }
+            lock(&delivery).delivered = delivered;
+            delivery_changed.notify_all();
+        }
+    });
+}
+
+///
+/// 順番を守る時の，呼び出し元とワーカーで共有する状態
+/// * delivered : usize    呼び出し元に渡し終えたファイルの数
+/// * stopped : bool       呼び出し元が受け取りを止めた（待っているワーカーも終わる）
+///
+#[derive(Default)]
+struct Delivery {
+    delivered: usize,
+    stopped: bool,
+}
+
+fn lock(delivery: &Mutex<Delivery>) -> MutexGuard<'_, Delivery> {
+    delivery.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+///
+/// 受け取りが終わったら（on_result の panic で抜けた時も）待っているワーカーを起こして終わらせる
+///
+struct StopWorkers<'a> {
+    delivery: &'a Mutex<Delivery>,
+    delivery_changed: &'a Condvar,
+}
+
+impl Drop for StopWorkers<'_> {
+    fn drop(&mut self) {
+        lock(self.delivery).stopped = true;
+        self.delivery_changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    // 探すファイルの数（スレッドの数と先に進める数より多くする）
+    const FILES: usize = 200;
+
+    ///
+    /// ファイルごとの行数
+    ///
+    fn expected_lines(index: usize) -> usize {
+        (index * 37) % 500 + 1
+    }
+
+    ///
+    /// 大きさの違うファイルを作る（探し終える順がばらばらになるように）
+    ///
+    fn write_files(name: &str) -> (PathBuf, Vec<PathBuf>) {
+        let dir = env::temp_dir().join(format!("study_rust_parallel_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = (0..FILES)
+            .map(|index| {
+                let path = dir.join(format!("file_{:03}.txt", index));
+                fs::write(&path, format!("file {}\n", index).repeat(expected_lines(index))).unwrap();
+                path
+            })
+            .collect();
+        (dir, files)
+    }
+
+    ///
+    /// 全てのファイルを探し，渡された順にパスと一致した行数を返す
+    ///
+    fn delivered(files: &[PathBuf], parallel: ParallelOptions) -> Vec<(PathBuf, usize)> {
+        let options = SearchOptions::default();
+        let matcher = Matcher::new("file", &options).unwrap();
+        let mut delivered = Vec::new();
+        search_files(&matcher, files, &options, &parallel, |path, result| {
+            delivered.push((path.to_path_buf(), result.unwrap().unwrap().len()));
+        });
+        delivered
+    }
+
+    #[test]
+    fn ordered_results_follow_the_file_order() {
+        let (dir, files) = write_files("ordered");
+        let results = delivered(&files, ParallelOptions { threads: 4, ordered: true });
+        fs::remove_dir_all(dir).unwrap();
+
+        let paths: Vec<&PathBuf> = results.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, files.iter().collect::<Vec<_>>());
+        for (index, (_, lines)) in results.iter().enumerate() {
+            assert_eq!(*lines, expected_lines(index));
+        }
+    }
+
+    #[test]
+    fn unordered_results_deliver_every_file_once() {
+        let (dir, files) = write_files("unordered");
+        let mut results = delivered(&files, ParallelOptions { threads: 4, ordered: false });
+        fs::remove_dir_all(dir).unwrap();
+
+        results.sort();
+        let paths: Vec<&PathBuf> = results.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, files.iter().collect::<Vec<_>>());
+        for (index, (_, lines)) in results.iter().enumerate() {
+            assert_eq!(*lines, expected_lines(index));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+///
+/// 検索するファイルを集める
+/// ディレクトリは再帰的にたどり，無視ファイルと --include / --exclude のグロブで絞り込む
+///
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::ignore::{relative_path, Glob, IgnoreFile};
+
+// 常に飛ばすディレクトリ
+const SKIP_DIRS: [&str; 1] = [".git"];
+
+///
+/// ファイルの集め方
+/// * include : Vec<Glob>          どれかに一致するファイルだけを集める（空なら全て）
+/// * exclude : Vec<Glob>          一致するファイルとディレクトリは飛ばす
+/// * use_ignore_files : bool      .gitignore / .ignore の規則に従う
+///
+pub struct WalkOptions {
+    pub include: Vec<Glob>,
+    pub exclude: Vec<Glob>,
+    pub use_ignore_files: bool,
+}
+
+///
+/// パスの中のファイルを集める
+/// 直接指定したファイルはグロブや無視ファイルに関係なく集める
+/// ディレクトリが git のリポジトリの中なら，ルート（.git のあるディレクトリ）までの上のディレクトリの無視ファイルも読む
+/// ### Arguments
+/// * paths : &[PathBuf]          ファイルかディレクトリ
+/// * options : &WalkOptions      集め方
+/// ### Return
+/// * files : Vec<PathBuf>        指定した順，ディレクトリの中は名前の順に並んだファイル
+///
+pub fn collect_files(paths: &[PathBuf], options: &WalkOptions) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let metadata = fs::metadata(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{} : {}", path.display(), e)))?;
+        if metadata.is_dir() {
+            let mut ignores = if options.use_ignore_files { parent_ignores(path) } else { Vec::new() };
+            walk_dir(path, path, &mut ignores, options, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+///
+/// 検索の起点より上の，リポジトリのルートまでのディレクトリの無視ファイルを読む
+/// ### Return
+/// * ignores : Vec<IgnoreFile>    ルートに近いものから順に並んだ無視ファイル（リポジトリの外なら空）
+///
+fn parent_ignores(root: &Path) -> Vec<IgnoreFile> {
+    let Ok(absolute) = fs::canonicalize(root) else {
+        return Vec::new();
+    };
+    let Some(repo_root) = absolute.ancestors().find(|dir| dir.join(".git").exists()) else {
+        return Vec::new();
+    };
+    let mut ignores = Vec::new();
+    for dir in absolute.ancestors().skip(1) {
+        if !dir.starts_with(repo_root) {
+            break;
+        }
+        match IgnoreFile::from_dir(dir) {
+            Ok(Some(ignore)) => {
+                let prefix = relative_path(dir, &absolute).unwrap_or_default();
+                ignores.push(ignore.rebase(root, prefix));
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Problem reading ignore file in {} : {}", dir.display(), e),
+        }
+    }
+    ignores.reverse();
+    ignores
+}
+
+///
+/// ディレクトリの中を再帰的にたどる
+/// 読めないディレクトリは警告を表示して飛ばす
+///
+fn walk_dir(dir: &Path, root: &Path, ignores: &mut Vec<IgnoreFile>, options: &WalkOptions, files: &mut Vec<PathBuf>) {
+    let pushed = if options.use_ignore_files {
+        match IgnoreFile::from_dir(dir) {
+            Ok(Some(ignore)) => {
+                ignores.push(ignore);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                eprintln!("Problem reading ignore file in {} : {}", dir.display(), e);
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    match read_entries(dir) {
+        Ok(entries) => {
+            for (path, is_dir) in entries {
+                if is_ignored(&path, is_dir, root, ignores, options) {
+                    continue;
+                }
+                if is_dir {
+                    walk_dir(&path, root, ignores, options, files);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        Err(e) => eprintln!("Problem reading directory {} : {}", dir.display(), e),
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+}
+
+///
+/// ディレクトリの中のファイルとディレクトリを名前の順に返す
+/// シンボリックリンクはたどらない（ループを避けるため）
+///
+fn read_entries(dir: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() || file_type.is_file() {
+            entries.push((entry.path(), file_type.is_dir()));
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+///
+/// たどる時に飛ばすか
+/// 深いディレクトリの無視ファイルほど優先し，--exclude と --include はその後で調べる
+///
+fn is_ignored(path: &Path, is_dir: bool, root: &Path, ignores: &[IgnoreFile], options: &WalkOptions) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    if is_dir && SKIP_DIRS.contains(&name.as_ref()) {
+        return true;
+    }
+    if ignores.iter().rev().find_map(|ignore| ignore.matched(path, is_dir)) == Some(true) {
+        return true;
+    }
+    let relative = relative_path(root, path).unwrap_or_else(|| name.into_owned());
+    if options.exclude.iter().any(|glob| glob.is_match(&relative, is_dir)) {
+        return true;
+    }
+    !is_dir && !options.include.is_empty() && !options.include.iter().any(|glob| glob.is_match(&relative, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    ///
+    /// 一時ディレクトリにファイルを作る（終わったら消す）
+    ///
+    struct TempTree {
+        dir: PathBuf,
+    }
+
+    impl TempTree {
+        fn new(name: &str, files: &[(&str, &str)]) -> TempTree {
+            let dir = env::temp_dir().join(format!("study_rust_walk_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for (path, contents) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            TempTree { dir }
+        }
+
+        fn collect(&self, sub_dir: &str, options: &WalkOptions) -> Vec<String> {
+            let root = self.dir.join(sub_dir);
+            collect_files(std::slice::from_ref(&root), options)
+                .unwrap()
+                .iter()
+                .map(|path| relative_path(&root, path).unwrap())
+                .collect()
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn options(include: &[&str], exclude: &[&str], use_ignore_files: bool) -> WalkOptions {
+        WalkOptions {
+            include: include.iter().map(|pattern| Glob::new(pattern).unwrap()).collect(),
+            exclude: exclude.iter().map(|pattern| Glob::new(pattern).unwrap()).collect(),
+            use_ignore_files,
+        }
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let tree = TempTree::new("globs", &[("a.rs", ""), ("b.txt", ""), ("gen/c.rs", ""), ("src/d.rs", "")]);
+        assert_eq!(tree.collect("", &options(&["*.rs"], &[], false)), vec!["a.rs", "gen/c.rs", "src/d.rs"]);
+        assert_eq!(tree.collect("", &options(&["*.rs"], &["gen/", "a.rs"], false)), vec!["src/d.rs"]);
+    }
+
+    #[test]
+    fn deeper_ignore_files_win() {
+        let tree = TempTree::new("nested", &[
+            (".gitignore", "*.log\n"),
+            ("a.log", ""),
+            ("main.rs", ""),
+            ("logs/.ignore", "!keep.log\n"),
+            ("logs/keep.log", ""),
+            ("logs/old.log", ""),
+        ]);
+        assert_eq!(tree.collect("", &options(&[], &[], true)), vec![".gitignore", "logs/.ignore", "logs/keep.log", "main.rs"]);
+        assert_eq!(tree.collect("", &options(&[], &[".*"], false)).len(), 4);
+    }
+
+    #[test]
+    fn ignore_files_above_the_root_are_read_up_to_the_repository() {
+        let tree = TempTree::new("parents", &[
+            (".gitignore", "*.rs\n"),
+            ("repo/.git/HEAD", ""),
+            ("repo/.gitignore", "*.log\n/sub/skip/\n"),
+            ("repo/sub/a.log", ""),
+            ("repo/sub/b.rs", ""),
+            ("repo/sub/skip/c.txt", ""),
+            ("repo/sub/keep/skip/d.txt", ""),
+        ]);
+        // リポジトリの外の .gitignore（*.rs）は読まない
+        assert_eq!(tree.collect("repo/sub", &options(&[], &[], true)), vec!["b.rs", "keep/skip/d.txt"]);
+        assert_eq!(tree.collect("repo/sub", &options(&[], &[], false)).len(), 4);
+    }
+}
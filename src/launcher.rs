@@ -0,0 +1,334 @@
+///
+/// bevy のデモのランチャー
+/// メニューからデモを選ぶと，同じアプリの中でデモのプラグインを動かす（F1 でメニューに戻る）
+/// デモは Demo の状態ごとに動き，抜けるとデモのエンティティは StateScoped で消える
+///
+use bevy::{
+    app::AppExit,
+    ecs::system::SystemParam,
+    prelude::*,
+    window::PrimaryWindow,
+};
+use crate::audio_mixer::{AudioMixer, AudioMixerPlugin};
+use crate::bevy_practice::SpatialAudioPlugin;
+use crate::bevy_timing_game::TimingGamePlugin;
+
+// ランチャーのウィンドウ
+const LAUNCHER_TITLE: &str = "Bevy Demos";
+const LAUNCHER_WINDOW_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+// 空間音響のデモは操作の案内が多いので広くする（タイミングゲームはランチャーと同じ大きさ）
+const SPATIAL_AUDIO_WINDOW_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+// 見出し
+const TITLE_FONT_SIZE: f32 = 56.0;
+
+// デモのボタン
+const BUTTON_SIZE: Vec2 = Vec2::new(480.0, 84.0);
+const BUTTON_MARGIN: f32 = 8.0;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+const FOCUS_COLOR: Color = Color::srgb(0.35, 0.35, 0.55);
+const BORDER_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+const FOCUS_BORDER_COLOR: Color = Color::WHITE;
+const LABEL_FONT_SIZE: f32 = 30.0;
+const DESCRIPTION_FONT_SIZE: f32 = 16.0;
+const DESCRIPTION_COLOR: Color = Color::srgb(0.75, 0.75, 0.75);
+
+// 操作の案内
+const HINT_FONT_SIZE: f32 = 18.0;
+const HINT_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+// デモからランチャーに戻るキー
+const RETURN_KEY: KeyCode = KeyCode::F1;
+
+///
+/// 動かしているデモ（Launcher ならメニューを表示する）
+/// 単体で起動する時は，そのデモの状態で始める
+///
+#[derive(States, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Demo {
+    #[default]
+    Launcher,
+    SpatialAudio,
+    TimingGame,
+}
+
+impl Demo {
+    const ALL: [Demo; 2] = [Demo::SpatialAudio, Demo::TimingGame];
+
+    fn label(self) -> &'static str {
+        match self {
+            Demo::Launcher => LAUNCHER_TITLE,
+            Demo::SpatialAudio => "Spatial Audio",
+            Demo::TimingGame => "Timing Game",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Demo::Launcher => "",
+            Demo::SpatialAudio => "2D / 3D spatial audio sandbox with attenuation, walls and a mixer",
+            Demo::TimingGame => "Rhythm game with charts, practice mode and achievements",
+        }
+    }
+
+    ///
+    /// デモに合わせたウィンドウの大きさ
+    ///
+    fn window_size(self) -> Vec2 {
+        match self {
+            Demo::Launcher | Demo::TimingGame => LAUNCHER_WINDOW_SIZE,
+            Demo::SpatialAudio => SPATIAL_AUDIO_WINDOW_SIZE,
+        }
+    }
+}
+
+///
+/// ランチャーから起動されたことを表す（デモの Quit でアプリを終了せずメニューに戻る）
+///
+#[derive(Resource)]
+struct Launched;
+
+///
+/// デモを終わらせる
+/// ランチャーから起動したならメニューに戻り，単体で起動したならアプリを終了する
+///
+#[derive(SystemParam)]
+pub struct DemoExit<'w> {
+    launched: Option<Res<'w, Launched>>,
+    next_demo: ResMut<'w, NextState<Demo>>,
+    app_exit: EventWriter<'w, AppExit>,
+}
+
+impl DemoExit<'_> {
+    pub fn quit(&mut self) {
+        if self.launched.is_some() {
+            self.next_demo.set(Demo::Launcher);
+        } else {
+            self.app_exit.send(AppExit::Success);
+        }
+    }
+}
+
+///
+/// ランチャーの実行
+///
+pub fn run_launcher() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                resolution: LAUNCHER_WINDOW_SIZE.into(),
+                title: LAUNCHER_TITLE.into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .init_state::<Demo>()
+        .enable_state_scoped_entities::<Demo>()
+        .insert_resource(Launched)
+        .init_resource::<LauncherFocus>()
+        .add_plugins((AudioMixerPlugin::default(), SpatialAudioPlugin, TimingGamePlugin))
+        .add_systems(OnEnter(Demo::Launcher), (setup_launcher, reset_mixer))
+        .add_systems(
+            Update,
+            (focus_demo, start_demo, highlight_focus)
+                .chain()
+                .run_if(in_state(Demo::Launcher)),
+        )
+        .add_systems(Update, return_to_launcher.run_if(not(in_state(Demo::Launcher))))
+        .add_systems(Update, resize_window.run_if(state_changed::<Demo>))
+        .run();
+}
+
+///
+/// デモのボタン（index は上からの順番）
+///
+#[derive(Component)]
+struct DemoButton {
+    demo: Demo,
+    index: usize,
+}
+
+///
+/// 選択中のデモの順番
+///
+#[derive(Resource, Default)]
+struct LauncherFocus(usize);
+
+///
+/// Launcher 遷移時のセットアップ関数
+/// デモのボタンを縦に並べて生成する
+///
+fn setup_launcher(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands.spawn((Camera2d, StateScoped(Demo::Launcher)));
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            StateScoped(Demo::Launcher),
+        ))
+        .with_children(|parent| {
+            // 見出し
+            parent.spawn((
+                Text::new(LAUNCHER_TITLE),
+                TextFont {
+                    font: font.clone(),
+                    font_size: TITLE_FONT_SIZE,
+                    ..default()
+                },
+                Node {
+                    margin: UiRect::bottom(Val::Px(BUTTON_MARGIN * 4.0)),
+                    ..default()
+                },
+            ));
+
+            // デモ
+            for (index, demo) in Demo::ALL.into_iter().enumerate() {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(BUTTON_SIZE.x),
+                            height: Val::Px(BUTTON_SIZE.y),
+                            margin: UiRect::all(Val::Px(BUTTON_MARGIN)),
+                            border: UiRect::all(Val::Px(2.0)),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_COLOR),
+                        BorderColor(BORDER_COLOR),
+                        DemoButton { demo, index },
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            Text::new(demo.label()),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: LABEL_FONT_SIZE,
+                                ..default()
+                            },
+                        ));
+                        button.spawn((
+                            Text::new(demo.description()),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: DESCRIPTION_FONT_SIZE,
+                                ..default()
+                            },
+                            TextColor(DESCRIPTION_COLOR),
+                        ));
+                    });
+            }
+
+            // 操作の案内
+            parent.spawn((
+                Text::new("Up/Down: Select  Enter/Space: Start  Esc: Quit\nF1 in a demo: Return to this menu"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(HINT_COLOR),
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    margin: UiRect::top(Val::Px(BUTTON_MARGIN * 4.0)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+///
+/// デモで変えたバスの音量やミュートを次のデモに持ち越さない
+///
+fn reset_mixer(mut mixer: ResMut<AudioMixer>) {
+    mixer.reset_buses();
+}
+
+///
+/// 上下キーとマウスで選択中のデモを変える
+///
+fn focus_demo(
+    mut launcher_focus: ResMut<LauncherFocus>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    hovered_query: Query<(&Interaction, &DemoButton), Changed<Interaction>>,
+) {
+    let count = Demo::ALL.len();
+    if keyboard.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        launcher_focus.0 = (launcher_focus.0 + count - 1) % count;
+    }
+    if keyboard.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        launcher_focus.0 = (launcher_focus.0 + 1) % count;
+    }
+    for (interaction, button) in &hovered_query {
+        if *interaction == Interaction::Hovered {
+            launcher_focus.0 = button.index;
+        }
+    }
+}
+
+///
+/// 選択中のデモを始める（Enter / Space / クリック），Esc でアプリを終了する
+///
+fn start_demo(
+    mut next_demo: ResMut<NextState<Demo>>,
+    mut app_exit: EventWriter<AppExit>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    launcher_focus: Res<LauncherFocus>,
+    clicked_query: Query<(&Interaction, &DemoButton), Changed<Interaction>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        app_exit.send(AppExit::Success);
+        return;
+    }
+    let clicked = clicked_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.demo);
+    let decided = keyboard
+        .any_just_pressed([KeyCode::Enter, KeyCode::Space])
+        .then(|| Demo::ALL[launcher_focus.0]);
+    if let Some(demo) = clicked.or(decided) {
+        next_demo.set(demo);
+    }
+}
+
+///
+/// 選択中のデモのボタンを目立たせる
+///
+fn highlight_focus(
+    launcher_focus: Res<LauncherFocus>,
+    mut button_query: Query<(&DemoButton, &mut BackgroundColor, &mut BorderColor)>,
+) {
+    for (button, mut background, mut border) in &mut button_query {
+        let focused = button.index == launcher_focus.0;
+        background.0 = if focused { FOCUS_COLOR } else { BUTTON_COLOR };
+        border.0 = if focused { FOCUS_BORDER_COLOR } else { BORDER_COLOR };
+    }
+}
+
+///
+/// F1 でデモを終わらせてランチャーに戻る
+///
+fn return_to_launcher(keyboard: Res<ButtonInput<KeyCode>>, mut next_demo: ResMut<NextState<Demo>>) {
+    if keyboard.just_pressed(RETURN_KEY) {
+        next_demo.set(Demo::Launcher);
+    }
+}
+
+///
+/// デモに合わせてウィンドウの大きさとタイトルを変える
+///
+fn resize_window(demo: Res<State<Demo>>, mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    let size = demo.window_size();
+    window.resolution.set(size.x, size.y);
+    window.title = demo.label().to_string();
+}
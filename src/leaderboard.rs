@@ -0,0 +1,314 @@
+///
+/// タイミングゲームのリーダーボード
+/// ゲーム（クライアント）と leaderboard_server（サーバー）で共有するスコアの形式と，HTTP でのやり取り
+///
+/// * POST {endpoint}/scores                   本文に 1 行分のスコアを送って登録する
+/// * GET  {endpoint}/scores?song=..&limit=..  曲ごとの上位のスコアを 1 行ずつ返す
+///
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// スコアの登録・取得に使うパス
+pub const SCORES_PATH: &str = "/scores";
+// 1 行分のスコアの項目の区切り
+const FIELD_SEPARATOR: char = '\t';
+// 通信のタイムアウト
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+///
+/// 1 回分のスコア
+/// * player : String             プレイヤー名
+/// * song_hash : String          譜面のハッシュ（Chart::hash）
+/// * score : isize               スコア
+/// * perfect / good / miss       判定ごとの回数
+/// * replay_checksum : String    打鍵した時刻の列のチェックサム（サーバーは同じ曲で同じチェックサムのスコアを重複として断る．打鍵のないプレイは除く）
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoreEntry {
+    pub player: String,
+    pub song_hash: String,
+    pub score: isize,
+    pub perfect: usize,
+    pub good: usize,
+    pub miss: usize,
+    pub replay_checksum: String,
+}
+
+impl ScoreEntry {
+    ///
+    /// 1 行のテキストにする（項目はタブ区切り）
+    ///
+    pub fn to_line(&self) -> String {
+        [
+            sanitize(&self.player),
+            sanitize(&self.song_hash),
+            self.score.to_string(),
+            self.perfect.to_string(),
+            self.good.to_string(),
+            self.miss.to_string(),
+            sanitize(&self.replay_checksum),
+        ]
+        .join(&FIELD_SEPARATOR.to_string())
+    }
+
+    ///
+    /// 同じプレイのスコアか（同じ曲で打鍵した時刻の列のチェックサムが同じ）
+    /// 打鍵しなかったプレイどうしは区別できないので，同じプレイとはみなさない
+    ///
+    pub fn is_same_play(&self, other: &ScoreEntry) -> bool {
+        self.song_hash == other.song_hash
+            && self.replay_checksum == other.replay_checksum
+            && self.replay_checksum != replay_checksum(&[])
+    }
+
+    ///
+    /// to_line で作った 1 行のテキストを読み込む
+    ///
+    pub fn parse_line(line: &str) -> Result<ScoreEntry, LeaderboardError> {
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split(FIELD_SEPARATOR).collect();
+        let [player, song_hash, score, perfect, good, miss, replay_checksum] = fields[..] else {
+            return Err(LeaderboardError::Format(format!("expected 7 fields but got {}", fields.len())));
+        };
+        let number = |name: &str, value: &str| {
+            value
+                .parse::<i64>()
+                .map_err(|e| LeaderboardError::Format(format!("invalid {} '{}': {}", name, value, e)))
+        };
+        let count = |name: &str, value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|e| LeaderboardError::Format(format!("invalid {} '{}': {}", name, value, e)))
+        };
+        if player.is_empty() || song_hash.is_empty() {
+            return Err(LeaderboardError::Format("player and song hash must not be empty".to_string()));
+        }
+        Ok(ScoreEntry {
+            player: player.to_string(),
+            song_hash: song_hash.to_string(),
+            score: number("score", score)? as isize,
+            perfect: count("perfect", perfect)?,
+            good: count("good", good)?,
+            miss: count("miss", miss)?,
+            replay_checksum: replay_checksum.to_string(),
+        })
+    }
+}
+
+///
+/// リーダーボードのエラー
+///
+#[derive(Debug)]
+pub enum LeaderboardError {
+    // 接続・送受信の失敗
+    Io(io::Error),
+    // エンドポイントの URL の誤り
+    Endpoint(String),
+    // サーバーがエラーを返した
+    Status(u16, String),
+    // スコアの書式の誤り
+    Format(String),
+}
+
+impl fmt::Display for LeaderboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaderboardError::Io(e) => write!(f, "{}", e),
+            LeaderboardError::Endpoint(message) => write!(f, "invalid endpoint: {}", message),
+            LeaderboardError::Status(status, message) => write!(f, "server returned {}: {}", status, message.trim()),
+            LeaderboardError::Format(message) => write!(f, "invalid score: {}", message),
+        }
+    }
+}
+
+impl Error for LeaderboardError {}
+
+impl From<io::Error> for LeaderboardError {
+    fn from(e: io::Error) -> Self {
+        LeaderboardError::Io(e)
+    }
+}
+
+///
+/// 打鍵した時刻（ミリ秒）の列のチェックサム（FNV-1a）
+///
+pub fn replay_checksum(press_times_ms: &[u32]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in press_times_ms.iter().flat_map(|time| time.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+///
+/// 曲の上位のスコアを返す
+/// 同じスコアは先に登録した方を上にする
+///
+pub fn top_scores<'a>(entries: &'a [ScoreEntry], song_hash: &str, limit: usize) -> Vec<&'a ScoreEntry> {
+    let mut scores: Vec<&ScoreEntry> = entries.iter().filter(|entry| entry.song_hash == song_hash).collect();
+    scores.sort_by_key(|entry| Reverse(entry.score));
+    scores.truncate(limit);
+    scores
+}
+
+///
+/// スコアをサーバーに登録する
+/// * endpoint : &str      サーバーの URL（例 : http://192.168.0.10:7878）
+///
+pub fn submit(endpoint: &str, entry: &ScoreEntry) -> Result<(), LeaderboardError> {
+    request(endpoint, "POST", SCORES_PATH, &entry.to_line())?;
+    Ok(())
+}
+
+///
+/// 曲の上位のスコアをサーバーから取得する
+///
+pub fn fetch_top(endpoint: &str, song_hash: &str, limit: usize) -> Result<Vec<ScoreEntry>, LeaderboardError> {
+    let path = format!("{}?song={}&limit={}", SCORES_PATH, song_hash, limit);
+    let body = request(endpoint, "GET", &path, "")?;
+    body.lines()
+        .filter(|line| !line.is_empty())
+        .map(ScoreEntry::parse_line)
+        .collect()
+}
+
+///
+/// HTTP/1.1 のリクエストを送り，成功（2xx）ならレスポンスの本文を返す
+/// LAN 内で使う前提なので，http:// のみに対応する
+///
+fn request(endpoint: &str, method: &str, path: &str, body: &str) -> Result<String, LeaderboardError> {
+    let rest = endpoint
+        .strip_prefix("http://")
+        .ok_or_else(|| LeaderboardError::Endpoint(format!("'{}' must start with http://", endpoint)))?;
+    let (host, base_path) = match rest.find('/') {
+        Some(index) => (&rest[..index], rest[index..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    if host.is_empty() {
+        return Err(LeaderboardError::Endpoint(format!("'{}' has no host", endpoint)));
+    }
+    let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    let socket_address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| LeaderboardError::Endpoint(format!("cannot resolve '{}'", host)))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "{} {}{} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        base_path,
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| LeaderboardError::Status(0, "malformed response".to_string()))?;
+    if !(200..300).contains(&status) {
+        return Err(LeaderboardError::Status(status, body.to_string()));
+    }
+    Ok(body.to_string())
+}
+
+///
+/// 区切り文字・改行を空白に置き換える
+///
+fn sanitize(value: &str) -> String {
+    value.replace([FIELD_SEPARATOR, '\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(player: &str, song_hash: &str, score: isize) -> ScoreEntry {
+        ScoreEntry {
+            player: player.to_string(),
+            song_hash: song_hash.to_string(),
+            score,
+            perfect: 10,
+            good: 2,
+            miss: 1,
+            replay_checksum: replay_checksum(&[score as u32, 500, 1000]),
+        }
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let original = entry("alice", "0123abcd", -40);
+        let line = original.to_line();
+        assert_eq!(line.split(FIELD_SEPARATOR).count(), 7);
+        assert_eq!(ScoreEntry::parse_line(&line).unwrap(), original);
+        assert_eq!(ScoreEntry::parse_line(&(line + "\r\n")).unwrap(), original);
+    }
+
+    #[test]
+    fn tabs_and_newlines_are_sanitized() {
+        let mut original = entry("bob\tthe\nbuilder\r", "0123abcd", 900);
+        original.replay_checksum = "ab\tcd".to_string();
+        let line = original.to_line();
+        assert!(!line.contains(['\n', '\r']));
+        let parsed = ScoreEntry::parse_line(&line).unwrap();
+        assert_eq!(parsed.player, "bob the builder ");
+        assert_eq!(parsed.replay_checksum, "ab cd");
+        assert_eq!(parsed.score, 900);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        for line in ["", "a\tb\t1\t2\t3\t4", "a\tb\tx\t2\t3\t4\tc", "a\tb\t1\t-2\t3\t4\tc", "\tb\t1\t2\t3\t4\tc"] {
+            assert!(matches!(ScoreEntry::parse_line(line), Err(LeaderboardError::Format(_))), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn top_scores_are_sorted_per_song() {
+        let entries = vec![
+            entry("a", "song1", 100),
+            entry("b", "song2", 999),
+            entry("c", "song1", 300),
+            entry("d", "song1", 100),
+            entry("e", "song1", 200),
+        ];
+        let players = |limit| -> Vec<&str> {
+            top_scores(&entries, "song1", limit).iter().map(|entry| entry.player.as_str()).collect()
+        };
+        // 同じスコアは先に登録した方が上
+        assert_eq!(players(10), vec!["c", "e", "a", "d"]);
+        assert_eq!(players(2), vec!["c", "e"]);
+        assert!(top_scores(&entries, "song3", 10).is_empty());
+    }
+
+    #[test]
+    fn same_play_needs_the_same_song_and_checksum() {
+        let first = entry("a", "song1", 100);
+        assert!(first.is_same_play(&entry("b", "song1", 100)));
+        assert!(!first.is_same_play(&entry("a", "song2", 100)));
+        assert!(!first.is_same_play(&entry("a", "song1", 101)));
+    }
+
+    #[test]
+    fn plays_without_input_are_never_the_same_play() {
+        let mut first = entry("a", "song1", 0);
+        first.replay_checksum = replay_checksum(&[]);
+        let mut second = entry("b", "song1", 0);
+        second.replay_checksum = replay_checksum(&[]);
+        assert!(!first.is_same_play(&second));
+        assert!(!first.is_same_play(&first.clone()));
+    }
+}
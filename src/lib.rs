@@ -1,28 +1,9 @@
-use std::error::Error;
-use std::fs;
-
-pub struct Config {
-    pub query: String,
-    pub filename: String,
-}
-
-impl Config {
-    pub fn new(args: &[String]) -> Result<Config, &str> {
-        if args.len() < 3 {
-            return Err("Not enough arguments");
-        }
-
-        let query = args[1].clone();
-        let filename = args[2].clone();
-
-        Ok(Config { query, filename })
-    }
-}
-
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-
-    println!("With Text\n{}", contents);
-
-    Ok(())
-}
+pub mod caesar_crypt;
+pub mod fern;
+pub mod grep;
+pub mod leaderboard;
+pub mod life_game;
+pub mod munou;
+pub mod my_math;
+
+pub use grep::{run, search, Config};
@@ -1,41 +1,167 @@
 use lazyrand::rand_usize;
+use std::error::Error;
+use std::fmt;
 use std::thread;
-use std::io::{Result, stdout};
+use std::io::{self, stdout};
 use std::time::Duration;
 use crossterm::{cursor, execute, terminal};
 use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::Clear;
 
-// 定数の宣言
+// 定数の宣言（設定の既定値）
 const GRID_WIDTH: usize = 90; // グリッドの横幅
 const GRID_HEIGHT: usize = 30; // グリッドの縦幅
 const MAX_TERN: usize = 1000; // 世代数
+const INTERVAL_MS: u64 = 100; // 世代ごとの待ち時間（ミリ秒）
 
-#[allow(unused)]
+///
+/// ライフゲームの設定
+/// * width : usize          グリッドの横幅
+/// * height : usize         グリッドの縦幅
+/// * generations : usize    世代数
+/// * interval : Duration    世代ごとの待ち時間
+///
+pub struct LifeOptions {
+    pub width: usize,
+    pub height: usize,
+    pub generations: usize,
+    pub interval: Duration,
+}
 
-pub fn run() -> Result<()> {
-    // 画面の初期化
-    init_screen()?;
+impl Default for LifeOptions {
+    fn default() -> Self {
+        LifeOptions {
+            width: GRID_WIDTH,
+            height: GRID_HEIGHT,
+            generations: MAX_TERN,
+            interval: Duration::from_millis(INTERVAL_MS),
+        }
+    }
+}
+
+///
+/// ライフゲームのエラー
+///
+#[derive(Debug)]
+pub enum LifeError {
+    // セルが 1 つもないグリッド
+    EmptyGrid,
+    // 他の行と長さが違う行
+    RaggedRow { row: usize, expected: usize, found: usize },
+    // 画面の描画の失敗
+    Io(io::Error),
+}
+
+impl fmt::Display for LifeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LifeError::EmptyGrid => write!(f, "grid must have at least one cell"),
+            LifeError::RaggedRow { row, expected, found } => {
+                write!(f, "row {} has {} cells, expected {}", row, found, expected)
+            }
+            LifeError::Io(e) => write!(f, "Problem drawing cells : {}", e),
+        }
+    }
+}
+
+impl Error for LifeError {}
+
+impl From<io::Error> for LifeError {
+    fn from(e: io::Error) -> Self {
+        LifeError::Io(e)
+    }
+}
+
+///
+/// ライフゲームの盤面（端は反対側とつながっている）
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LifeGame {
+    cells: Vec<Vec<bool>>,
+}
+
+impl LifeGame {
+    ///
+    /// 生きているセルを乱数で適当に配置した盤面
+    ///
+    pub fn random(width: usize, height: usize) -> Result<Self, LifeError> {
+        if width == 0 || height == 0 {
+            return Err(LifeError::EmptyGrid);
+        }
+        Ok(LifeGame { cells: init_cells(width, height) })
+    }
+
+    ///
+    /// セルを指定した盤面
+    /// ### Arguments
+    /// * cells : Vec<Vec<bool>>     行ごとのセル（true が生きている），全ての行は同じ長さ
+    ///
+    pub fn from_cells(cells: Vec<Vec<bool>>) -> Result<Self, LifeError> {
+        let expected = cells.first().map_or(0, |row| row.len());
+        if expected == 0 {
+            return Err(LifeError::EmptyGrid);
+        }
+        if let Some((row, found)) = cells
+            .iter()
+            .map(|row| row.len())
+            .enumerate()
+            .find(|&(_, len)| len != expected)
+        {
+            return Err(LifeError::RaggedRow { row, expected, found });
+        }
+        Ok(LifeGame { cells })
+    }
 
+    pub fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    ///
+    /// (x, y) のセルが生きているか（盤面の外は死んでいる扱い）
+    ///
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.cells.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false)
+    }
+
+    pub fn cells(&self) -> &[Vec<bool>] {
+        &self.cells
+    }
+
+    ///
+    /// 1 世代進める
+    ///
+    pub fn step(&mut self) {
+        self.cells = calc_next_gen(&self.cells);
+    }
+}
+
+pub fn run(options: &LifeOptions) -> Result<(), LifeError> {
     // 最初のセルを適当に配置する
-    let mut cells: Vec<Vec<bool>> = init_cells();
+    let mut game = LifeGame::random(options.width, options.height)?;
+
+    // 画面の初期化
+    init_screen()?;
 
     // 世代数だけまわす
-    for i in 1..MAX_TERN + 1 {
+    for i in 1..options.generations + 1 {
         // セルを描画
-        draw_cells(&cells)?;
-        println!("{}/{}", i, MAX_TERN);
+        draw_cells(game.cells())?;
+        println!("{}/{}", i, options.generations);
 
-        // 300 ミリ秒待つ
-        thread::sleep(Duration::from_millis(100));
+        // 決めた時間だけ待つ
+        thread::sleep(options.interval);
 
         // 次の世代の計算を行う
-        cells = calc_next_gen(&cells);
+        game.step();
     }
     Ok(())
 }
 
-fn init_screen() -> Result<()> {
+fn init_screen() -> io::Result<()> {
     // 画面をクリアして，カーソルを(0, 0)に移動
     execute!(stdout()
         , Clear(terminal::ClearType::All)
@@ -43,19 +169,19 @@ fn init_screen() -> Result<()> {
     Ok(())
 }
 
-fn init_cells() -> Vec<Vec<bool>> {
-    let mut cells: Vec<Vec<bool>> = vec![vec![false; GRID_WIDTH]; GRID_HEIGHT];
+fn init_cells(width: usize, height: usize) -> Vec<Vec<bool>> {
+    let mut cells: Vec<Vec<bool>> = vec![vec![false; width]; height];
     // これと同義
-    //let mut vec_sample = Vec::from([Vec::from([false; GRID_WIDTH]); GRID_HEIGHT]);
+    //let mut vec_sample = Vec::from([Vec::from([false; width]); height]);
 
     // 乱数を使って適当に初期化する
-    for _ in 0..(GRID_WIDTH * GRID_HEIGHT / 13) {
-        cells[rand_usize() % GRID_HEIGHT][rand_usize() % GRID_WIDTH] = true;
+    for _ in 0..(width * height / 13) {
+        cells[rand_usize() % height][rand_usize() % width] = true;
     }
     cells
 }
 
-fn draw_cells(cells: &Vec<Vec<bool>>) -> Result<()> {
+fn draw_cells(cells: &[Vec<bool>]) -> io::Result<()> {
     // カーソルを(0, 0) に移動
     execute!(stdout(), cursor::MoveTo(0, 0))?;
     for row in cells {
@@ -78,18 +204,21 @@ fn draw_cells(cells: &Vec<Vec<bool>>) -> Result<()> {
     Ok(())
 }
 
-fn calc_next_gen(cells: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+fn calc_next_gen(cells: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    // グリッドの大きさは渡されたセルから決める
+    let grid_height = cells.len();
+    let grid_width = cells[0].len();
     // 次世代の結果を生成
-    let mut new_gen_cells = vec![vec![false; GRID_WIDTH]; GRID_HEIGHT];
-    for y in 0..GRID_HEIGHT {
-        for x in 0..GRID_WIDTH {
+    let mut new_gen_cells = vec![vec![false; grid_width]; grid_height];
+    for y in 0..grid_height {
+        for x in 0..grid_width {
             // 周囲のセルの生存数を数える
             let mut alive_cnt: u32 = 0;
             for dy in -1..=1 {
                 for dx in -1..=1 {
                     if dx == 0 && dy == 0 { continue; }
-                    let ny = (y as isize + dy + GRID_HEIGHT as isize) as usize % GRID_HEIGHT;
-                    let nx = (x as isize + dx + GRID_WIDTH as isize) as usize % GRID_WIDTH;
+                    let ny = (y as isize + dy + grid_height as isize) as usize % grid_height;
+                    let nx = (x as isize + dx + grid_width as isize) as usize % grid_width;
                     // 生存セルならインクリメント
                     if cells[ny][nx] { alive_cnt += 1; };
                 }
@@ -111,3 +240,54 @@ fn calc_next_gen(cells: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
     // 決まった次世代セルを返す
     new_gen_cells
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///
+    /// "#" を生きているセルとして盤面を作る
+    ///
+    fn grid(rows: &[&str]) -> LifeGame {
+        LifeGame::from_cells(rows.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect()).unwrap()
+    }
+
+    #[test]
+    fn empty_grids_are_errors() {
+        assert!(matches!(LifeGame::from_cells(Vec::new()), Err(LifeError::EmptyGrid)));
+        assert!(matches!(LifeGame::from_cells(vec![Vec::new()]), Err(LifeError::EmptyGrid)));
+        assert!(matches!(LifeGame::random(0, 5), Err(LifeError::EmptyGrid)));
+    }
+
+    #[test]
+    fn ragged_rows_are_errors() {
+        let cells = vec![vec![false; 3], vec![false; 3], vec![false; 2]];
+        assert!(matches!(
+            LifeGame::from_cells(cells),
+            Err(LifeError::RaggedRow { row: 2, expected: 3, found: 2 })
+        ));
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let vertical = grid(&[".....", "..#..", "..#..", "..#..", "....."]);
+        let horizontal = grid(&[".....", ".....", ".###.", ".....", "....."]);
+        let mut game = vertical.clone();
+        game.step();
+        assert_eq!(game, horizontal);
+        game.step();
+        assert_eq!(game, vertical);
+    }
+
+    #[test]
+    fn block_is_stable_and_edges_wrap() {
+        // 四隅のセルは端でつながって 2x2 のブロックになる
+        let corners = grid(&["#...#", ".....", ".....", "#...#"]);
+        let mut game = corners.clone();
+        game.step();
+        assert_eq!(game, corners);
+        assert_eq!((game.width(), game.height()), (5, 4));
+        assert!(game.is_alive(4, 3));
+        assert!(!game.is_alive(5, 0));
+    }
+}
@@ -1,15 +1,50 @@
-use std::io::{Result};
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::path::Path;
 use regex::Regex;
 use vibrato::{Dictionary, Tokenizer};
 use lazyrand;
 use std::io::{self, Write};
 
+// 学習に使うテキストの既定値
+pub const CORPUS_FILE: &str = "assets/wagahaiwa_nekodearu_utf8.txt";
+
+// 形態素解析の辞書の既定値
+pub const DICTIONARY_FILE: &str = "../assets/system.dic.zst";
+
 // 特殊な単語 ID
-static TOP_WORD_ID: isize = 0;
-static END_WORD_ID: isize = 1;
+pub static TOP_WORD_ID: isize = 0;
+pub static END_WORD_ID: isize = 1;
 
+///
+/// 人工無能のエラー
+///
+#[derive(Debug)]
+pub enum MunouError {
+    // ファイルの読み書きの失敗
+    Io(io::Error),
+    // 形態素解析の辞書の読み込みの失敗
+    Dictionary(String),
+}
+
+impl fmt::Display for MunouError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MunouError::Io(e) => write!(f, "Problem reading file : {}", e),
+            MunouError::Dictionary(e) => write!(f, "Problem loading dictionary : {}", e),
+        }
+    }
+}
+
+impl Error for MunouError {}
+
+impl From<io::Error> for MunouError {
+    fn from(e: io::Error) -> Self {
+        MunouError::Io(e)
+    }
+}
 
 ///
 /// マルコフ連鎖を用いている構造体
@@ -27,12 +62,21 @@ pub struct MarkovChain {
 impl MarkovChain {
 
     ///
-    /// コンストラクション
+    /// コンストラクション（既定の辞書 DICTIONARY_FILE を使う）
+    ///
+    pub fn new() -> Result<Self, MunouError> {
+        Self::with_dictionary(Path::new(DICTIONARY_FILE))
+    }
+
+    ///
+    /// 辞書を指定したコンストラクション
+    /// ### Arguments
+    /// * dictionary : &Path     zstd で圧縮した vibrato の形態素解析の辞書
     ///
-    pub fn new() -> Self {
+    pub fn with_dictionary(dictionary: &Path) -> Result<Self, MunouError> {
         // 形態素解析の辞書を読み込む
-        let reader = zstd::Decoder::new(fs::File::open("../assets/system.dic.zst").unwrap()).unwrap();
-        let dict = Dictionary::read(reader).unwrap();
+        let reader = zstd::Decoder::new(fs::File::open(dictionary)?)?;
+        let dict = Dictionary::read(reader).map_err(|e| MunouError::Dictionary(e.to_string()))?;
 
         // 単語辞書の初期化
         let mut word_hash = HashMap::new();
@@ -43,12 +87,12 @@ impl MarkovChain {
         words.push("。".to_string());
 
         // 構造体の作成
-        MarkovChain {
+        Ok(MarkovChain {
             words,
             word_hash,
             chain: HashMap::new(),
             tokenizer: Tokenizer::new(dict),
-        }
+        })
     }
 
     ///
@@ -108,10 +152,24 @@ impl MarkovChain {
         }
     }
 
+    ///
+    /// 文章から名詞を抜き出す
+    ///
+    pub fn extract_nouns(&self, text: &str) -> Vec<String> {
+        let mut worker = self.tokenizer.new_worker();
+        worker.reset_sentence(text);
+        worker.tokenize();
+        worker
+            .token_iter()
+            .filter(|t| t.feature().contains("名詞"))
+            .map(|t| t.surface().to_string())
+            .collect()
+    }
+
     ///
     /// 文章を生成する
     ///
-    pub fn generate(&mut self) -> String {
+    pub fn generate(&self) -> String {
         self.generate_text(TOP_WORD_ID, TOP_WORD_ID)
     }
 
@@ -152,13 +210,14 @@ impl MarkovChain {
 
 ///
 /// 人工無能 実行用の関数
+/// ### Arguments
+/// * corpus : &Path     学習に使うテキストファイル
 ///
-#[allow(unused)]
-pub fn execute() -> Result<()> {
+pub fn execute(corpus: &Path) -> Result<(), MunouError> {
     // 人工無能を生成 --- (*10)
-    let mut markov = MarkovChain::new();
+    let mut markov = MarkovChain::new()?;
     // テキストファイルを読み込んで学習する --- (*11)
-    let text = fs::read_to_string("assets/wagahaiwa_nekodearu_utf8.txt")?;
+    let text = fs::read_to_string(corpus)?;
     println!("テキストを学習しています...");
     let lines: Vec<&str> = text.split("\n").collect();
     for line in lines {
@@ -179,17 +238,8 @@ pub fn execute() -> Result<()> {
             break;
         }
         // 入力した内容から適当に名詞を抽出して、それを含む文章を生成する --- (*15)
-        let mut worker = markov.tokenizer.new_worker();
-        worker.reset_sentence(input);
-        worker.tokenize();
-        let words: Vec<String> = worker.token_iter().map(|t| {
-            if t.feature().contains("名詞") {
-                t.surface().to_string()
-            } else {
-                "".to_string()
-            }
-        }).filter(|w| !w.is_empty()).collect();
-        let output = if words.len() == 0 {
+        let words: Vec<String> = markov.extract_nouns(&input);
+        let output = if words.is_empty() {
             markov.generate()
         } else {
             let word: String = lazyrand::choice(&words).unwrap();
@@ -206,4 +256,22 @@ pub fn execute() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_dictionary_is_an_io_error() {
+        let result = MarkovChain::with_dictionary(Path::new("no/such/dictionary.dic.zst"));
+        assert!(matches!(result, Err(MunouError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn errors_describe_the_problem() {
+        let error = MunouError::Dictionary("broken header".to_string());
+        assert_eq!(error.to_string(), "Problem loading dictionary : broken header");
+        let error = MunouError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(error.to_string(), "Problem reading file : gone");
+    }
+}
@@ -1,19 +1,63 @@
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// i32 に収まる最大の項（F(46) = 1836311903）
+pub const MAX_FIB_INPUT: i32 = 46;
+
+///
+/// フィボナッチ数列の計算のエラー
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FibError {
+    // 負の項
+    Negative(i32),
+    // i32 に収まらない項
+    Overflow(i32),
+}
+
+impl fmt::Display for FibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FibError::Negative(n) => write!(f, "n must not be negative: {}", n),
+            FibError::Overflow(n) => write!(f, "F({}) does not fit in i32", n),
+        }
+    }
+}
+
+impl Error for FibError {}
 
 ///
 /// フィボナッチ数列
 ///
-#[allow(unused)]
-pub fn run_fib(input_num: i32) {
+pub fn run_fib(input_num: i32) -> Result<(), FibError> {
     // 整数
     let n_count: i32 = input_num;
 
-    // 計算結果を保持する配列c
-    let mut hash_map: HashMap<i32, i32> = HashMap::new();
-
     // フィボナッチ数列の計算
-    let n_result: i32 = calc_fib(10, &mut hash_map);
+    let n_result: i32 = fib(n_count)?;
     println!("calc_fibo {} is {}.", n_count, n_result);
+    Ok(())
+}
+
+///
+/// フィボナッチ数列の第 n 項
+/// ### Arguments
+/// * n : i32                    項の番号（0 から）
+/// ### Return
+/// * F(n) : i32                 負の項や i32 に収まらない項はエラー
+///
+pub fn fib(n: i32) -> Result<i32, FibError> {
+    if n < 0 {
+        return Err(FibError::Negative(n));
+    }
+    // 大きな項は再帰が深くなりすぎるので，計算する前に弾く
+    if n > MAX_FIB_INPUT {
+        return Err(FibError::Overflow(n));
+    }
+    // 計算結果を保持する配列
+    let mut hash_map: HashMap<i32, i32> = HashMap::new();
+    calc_fib(n, &mut hash_map).ok_or(FibError::Overflow(n))
 }
 
 ///
@@ -21,18 +65,41 @@ pub fn run_fib(input_num: i32) {
 ///          0 | n = 0
 /// F(n) = { 1 | n = 1
 ///          F(n-1) + F(n-2) | n >= 2
+/// i32 に収まらなければ None
 ///
-fn calc_fib(n_count: i32, h: &mut HashMap<i32, i32>) -> i32 {
+fn calc_fib(n_count: i32, h: &mut HashMap<i32, i32>) -> Option<i32> {
     if n_count == 0 || n_count == 1 {
         h.entry(n_count).or_insert(n_count);
-        n_count
+        Some(n_count)
+    } else if let Some(&result) = h.get(&n_count) {
+        Some(result)
     } else {
-        if h.get(&n_count) != None {
-            *h.get(&n_count).unwrap()
-        } else {
-            let result = calc_fib(n_count - 1, h) + calc_fib(n_count - 2, h);
-            h.entry(n_count).or_insert(result);
-            result
-        }
+        let result = calc_fib(n_count - 1, h)?.checked_add(calc_fib(n_count - 2, h)?)?;
+        h.entry(n_count).or_insert(result);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_terms() {
+        assert_eq!(fib(0), Ok(0));
+        assert_eq!(fib(1), Ok(1));
+        assert_eq!(fib(10), Ok(55));
+    }
+
+    #[test]
+    fn largest_term_that_fits_in_i32() {
+        assert_eq!(fib(MAX_FIB_INPUT), Ok(1_836_311_903));
+        assert_eq!(fib(MAX_FIB_INPUT + 1), Err(FibError::Overflow(47)));
+    }
+
+    #[test]
+    fn huge_and_negative_terms_are_errors() {
+        assert_eq!(fib(10_000_000), Err(FibError::Overflow(10_000_000)));
+        assert_eq!(fib(-1), Err(FibError::Negative(-1)));
     }
 }